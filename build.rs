@@ -0,0 +1,4 @@
+// rebuilds the binary when a migration is added, so it gets embedded by sqlx::migrate!
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -0,0 +1,5 @@
+update loks set address = null where address < 0;
+update loks set lokmaus_name = null where lokmaus_name = '';
+update loks set producer = null where producer = '';
+update loks set management = null where management = '';
+update loks set image_path = null where image_path = '';
@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS images
+(
+    path      TEXT PRIMARY KEY NOT NULL,
+    ref_count INTEGER          NOT NULL
+);
+
+insert into images (path, ref_count)
+select image_path, count(*) from loks where image_path is not null group by image_path;
+
+CREATE TRIGGER IF NOT EXISTS lok_image_added AFTER INSERT ON loks
+WHEN new.image_path IS NOT NULL
+BEGIN
+    insert into images (path, ref_count) values (new.image_path, 1)
+    on conflict (path) do update set ref_count = ref_count + 1;
+END;
+
+CREATE TRIGGER IF NOT EXISTS lok_image_removed AFTER DELETE ON loks
+WHEN old.image_path IS NOT NULL
+BEGIN
+    update images set ref_count = ref_count - 1 where path = old.image_path;
+    delete from images where path = old.image_path and ref_count <= 0;
+END;
+
+CREATE TRIGGER IF NOT EXISTS lok_image_replaced AFTER UPDATE OF image_path ON loks
+WHEN old.image_path IS NOT new.image_path
+BEGIN
+    update images set ref_count = ref_count - 1 where path = old.image_path;
+    delete from images where path = old.image_path and ref_count <= 0;
+    insert into images (path, ref_count) select new.image_path, 1 where new.image_path is not null
+    on conflict (path) do update set ref_count = ref_count + 1;
+END;
@@ -0,0 +1,42 @@
+CREATE TABLE IF NOT EXISTS lok_images
+(
+    id         INTEGER PRIMARY KEY NOT NULL,
+    lok_id     INTEGER             NOT NULL REFERENCES loks (id) ON DELETE CASCADE,
+    position   INTEGER             NOT NULL,
+    path       TEXT                NOT NULL,
+    caption    TEXT    default null,
+    is_primary BOOLEAN             NOT NULL default false
+);
+
+CREATE INDEX IF NOT EXISTS lok_images_by_lok ON lok_images (lok_id, position);
+
+insert into lok_images (lok_id, position, path, is_primary)
+select id, 0, image_path, true from loks where image_path is not null;
+
+-- the reference counts stay the same, only the table holding the references changes
+DROP TRIGGER IF EXISTS lok_image_added;
+DROP TRIGGER IF EXISTS lok_image_removed;
+DROP TRIGGER IF EXISTS lok_image_replaced;
+
+alter table loks drop column image_path;
+
+CREATE TRIGGER IF NOT EXISTS lok_image_added AFTER INSERT ON lok_images
+BEGIN
+    insert into images (path, ref_count) values (new.path, 1)
+    on conflict (path) do update set ref_count = ref_count + 1;
+END;
+
+CREATE TRIGGER IF NOT EXISTS lok_image_removed AFTER DELETE ON lok_images
+BEGIN
+    update images set ref_count = ref_count - 1 where path = old.path;
+    delete from images where path = old.path and ref_count <= 0;
+END;
+
+CREATE TRIGGER IF NOT EXISTS lok_image_replaced AFTER UPDATE OF path ON lok_images
+WHEN old.path IS NOT new.path
+BEGIN
+    update images set ref_count = ref_count - 1 where path = old.path;
+    delete from images where path = old.path and ref_count <= 0;
+    insert into images (path, ref_count) values (new.path, 1)
+    on conflict (path) do update set ref_count = ref_count + 1;
+END;
@@ -0,0 +1,26 @@
+-- the uncropped image an image was cropped from, it is kept to crop it again
+alter table lok_images add column original_path TEXT default null;
+
+-- originals are referenced like the images themselves, so they are not deleted while a lok needs them
+CREATE TRIGGER IF NOT EXISTS lok_image_original_added AFTER INSERT ON lok_images
+WHEN new.original_path IS NOT NULL
+BEGIN
+    insert into images (path, ref_count) values (new.original_path, 1)
+    on conflict (path) do update set ref_count = ref_count + 1;
+END;
+
+CREATE TRIGGER IF NOT EXISTS lok_image_original_removed AFTER DELETE ON lok_images
+WHEN old.original_path IS NOT NULL
+BEGIN
+    update images set ref_count = ref_count - 1 where path = old.original_path;
+    delete from images where path = old.original_path and ref_count <= 0;
+END;
+
+CREATE TRIGGER IF NOT EXISTS lok_image_original_replaced AFTER UPDATE OF original_path ON lok_images
+WHEN old.original_path IS NOT new.original_path
+BEGIN
+    update images set ref_count = ref_count - 1 where path = old.original_path;
+    delete from images where path = old.original_path and ref_count <= 0;
+    insert into images (path, ref_count) select new.original_path, 1 where new.original_path IS NOT NULL
+    on conflict (path) do update set ref_count = ref_count + 1;
+END;
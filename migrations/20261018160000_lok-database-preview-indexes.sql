@@ -0,0 +1,4 @@
+-- the preview list is sorted and paged in SQL, one index per sort order
+CREATE INDEX IF NOT EXISTS loks_by_address ON loks (address, name, id);
+CREATE INDEX IF NOT EXISTS loks_by_name ON loks (name, address, id);
+CREATE INDEX IF NOT EXISTS loks_by_lokmaus_name ON loks (lokmaus_name, address, name, id);
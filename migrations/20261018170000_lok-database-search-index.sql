@@ -0,0 +1,24 @@
+-- the search text holds the words of every searched field, it is written by the application
+ALTER TABLE loks ADD COLUMN search_text TEXT NOT NULL DEFAULT '';
+
+-- every trigram of the search texts, the candidates of a search are looked up here
+CREATE VIRTUAL TABLE IF NOT EXISTS lok_search USING fts5(search_text, content = 'loks', content_rowid = 'id', tokenize = 'trigram');
+
+CREATE TRIGGER IF NOT EXISTS lok_search_added AFTER INSERT ON loks
+BEGIN
+    insert into lok_search (rowid, search_text) values (new.id, new.search_text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS lok_search_removed AFTER DELETE ON loks
+BEGIN
+    insert into lok_search (lok_search, rowid, search_text) values ('delete', old.id, old.search_text);
+END;
+
+CREATE TRIGGER IF NOT EXISTS lok_search_replaced AFTER UPDATE OF search_text ON loks
+BEGIN
+    insert into lok_search (lok_search, rowid, search_text) values ('delete', old.id, old.search_text);
+    insert into lok_search (rowid, search_text) values (new.id, new.search_text);
+END;
+
+-- registers the existing loks with an empty text, the application writes their texts when it opens the database
+insert into lok_search (lok_search) values ('rebuild');
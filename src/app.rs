@@ -5,38 +5,96 @@ mod page;
 pub mod backend;
 pub mod ui;
 mod settings;
+mod data_location;
+mod collections;
+mod backup;
+mod snapshots;
+mod maintenance;
+mod image_store;
+mod image_editor;
+mod bulk_import;
 
+use crate::app::backend::database::preview_lok::PreviewLok;
+use crate::app::backend::database::preview_query::{PreviewFilter, PreviewQuery, PreviewSort};
+use crate::app::backend::database::search_query::SearchQueryError;
+use crate::app::backend::database::DatabaseError;
+use crate::app::bulk_import::ImportMatch;
+use crate::app::data_location::{Arguments, DataLocation};
+use crate::app::maintenance::Issue;
 use crate::app::message::Message;
-use crate::app::page::{Page, Pages};
+use crate::app::page::home_page::LokListState;
+use crate::app::page::settings_page::collection_error;
+use crate::app::page::{image_edited, image_imported, Page, PageStatus, Pages};
 use crate::app::persistent_data::PersistentData;
 use crate::app::settings::Settings;
+use crate::app::snapshots::{Snapshot, SnapshotReason};
 use crate::app::state::State;
+use crate::app::ui::notification::{localized_backup_error, Notification, Notifications};
+use backend::any_backend::{AnyBackend, JSON_FILE_EXTENSION, SQLITE_FILE_EXTENSION};
+use backend::memory_backend::MEMORY_DB_URL;
 use backend::resource_manager::LokResourceManager;
-use backend::sqlite_backend::SQLiteBackend;
-use iced::{event, window, Element, Event, Subscription, Task};
+use iced::widget::stack;
+use iced::{event, keyboard, window, Element, Event, Subscription, Task};
+use rfd::FileDialog;
+use rust_i18n::set_locale;
+use std::path::PathBuf;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
-const DB_URL: &str = "sqlite://data/lokbuch.db";
 
 pub(crate) struct Lokbuch {
     state: State,
-    lok_resource_manager: LokResourceManager<SQLiteBackend>,
+    lok_resource_manager: LokResourceManager<AnyBackend>,
     moving_icon_frames: iced_gif::Frames,
     page: Pages,
     settings: Settings,
+    notifications: Notifications,
+    data_location: DataLocation,
+    collections: Vec<String>,
+    snapshots: Vec<Snapshot>,
+    /// The result of the last database check, ```None``` while it is running.
+    maintenance_issues: Option<Vec<Issue>>,
+    /// The images of a folder and their loks, while the bulk import is reviewed.
+    bulk_import: Vec<ImportMatch>,
+    /// The loks the images of the bulk import can be attached to.
+    bulk_import_loks: Vec<PreviewLok>,
+    db_url: String,
+    database_error: Option<DatabaseError>,
+    /// Progress of the database operation the current page waits for.
+    page_status: PageStatus,
+    /// The text of the search input, it is kept with the search of the resource manager until another collection is opened.
+    search_input: String,
+    /// Why the search input could not be parsed, the results of the last valid search are shown meanwhile.
+    search_error: Option<SearchQueryError>,
+    lok_list: LokListState,
 }
 
 impl Lokbuch {
     pub(crate) fn new() -> (Self, Task<Message>) {
+        let arguments = Arguments::parse(std::env::args());
+        let base_location = DataLocation::resolve(&arguments);
+
         (Lokbuch {
             page: Pages::Loading,
             state: State::default(),
             lok_resource_manager: LokResourceManager::default(),
             moving_icon_frames: ui::moving_icon_frames(),
             settings: Settings::default(),
+            notifications: Notifications::default(),
+            data_location: base_location.clone(),
+            collections: Vec::new(),
+            snapshots: Vec::new(),
+            maintenance_issues: None,
+            bulk_import: Vec::new(),
+            bulk_import_loks: Vec::new(),
+            db_url: base_location.db_url(),
+            database_error: None,
+            page_status: PageStatus::Ready,
+            search_input: String::new(),
+            search_error: None,
+            lok_list: LokListState::default(),
         },
          Task::batch(vec![
-             Task::perform(PersistentData::init_app_and_backend(DB_URL), Message::Loaded),
+             Task::perform(PersistentData::init_app_and_backend(base_location, arguments.demo), Message::Loaded),
              iced::window::latest().and_then(move |id| iced::window::toggle_maximize(id))
          ]))
     }
@@ -48,18 +106,227 @@ impl Lokbuch {
     pub(crate) fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::EventOccurred(event) => {
-                if let Event::Window(window::Event::CloseRequested) = event {
-                    self.settings.save();
-                    window::latest().and_then(window::close)
-                } else {
-                    Task::none()
+                match event {
+                    Event::Window(window::Event::CloseRequested) => {
+                        if let Err(err) = self.settings.save(&self.data_location.settings_path()) {
+                            println!("Failed to save settings on close: {}", err);
+                        }
+
+                        let close = window::latest().and_then(window::close);
+
+                        if !self.has_snapshots() {
+                            return close;
+                        }
+
+                        // the window is closed once the snapshot is taken
+                        let db_url = self.db_url.clone();
+                        let data_location = self.data_location.clone();
+                        let snapshot_count = self.settings.snapshot_count;
+
+                        Task::future(async move {
+                            if let Err(err) = snapshots::rotate(&db_url, &data_location, SnapshotReason::Shutdown, snapshot_count).await {
+                                println!("Failed to take shutdown snapshot: {}", err);
+                            }
+                        }).discard().chain(close)
+                    }
+
+                    // files and pasted images are handed to the current page, only the input mask uses them
+                    Event::Window(window::Event::FileDropped(path)) => {
+                        self.page.as_page_struct().update(self, Message::ImageFileDropped(path))
+                    }
+
+                    // a focused text input captures the shortcut, so text is still pasted as usual
+                    Event::Keyboard(keyboard::Event::KeyPressed { key: keyboard::Key::Character(character), modifiers, .. })
+                        if modifiers.command() && character.as_str() == "v" => {
+                        self.page.as_page_struct().update(self, Message::PasteImage)
+                    }
+
+                    _ => Task::none()
+                }
+            }
+
+            Message::Notify(notification) => {
+                self.notifications.push(notification);
+                Task::none()
+            }
+
+            Message::DismissNotification(id) => {
+                self.notifications.dismiss(id);
+                Task::none()
+            }
+
+            Message::PreviewsLoaded(query, result) => {
+                self.lok_list.is_loading_more = false;
+
+                match result {
+                    Ok(page) => {
+                        self.lok_resource_manager.show_previews(&query, page);
+                        Task::none()
+                    }
+                    Err(err) => Task::done(Message::Notify(Notification::database_error(t!("notification.search_failed"), &err))),
+                }
+            }
+
+            Message::OpenDatabase => {
+                let file = FileDialog::new()
+                    .add_filter(t!("collection.collection_files"), &[SQLITE_FILE_EXTENSION, JSON_FILE_EXTENSION])
+                    .pick_file();
+
+                match file {
+                    Some(path) => Lokbuch::open_database(AnyBackend::url_from_path(&path)),
+                    None => Task::none(),
+                }
+            }
+
+            Message::NewCollectionFile => {
+                // the format of the collection is chosen by the file extension
+                let file = FileDialog::new()
+                    .add_filter(t!("collection.sqlite_files"), &[SQLITE_FILE_EXTENSION])
+                    .add_filter(t!("collection.json_files"), &[JSON_FILE_EXTENSION])
+                    .set_file_name("lokbuch.db")
+                    .save_file();
+
+                let Some(path) = file else {
+                    return Task::none();
+                };
+
+                // the file dialog already asked, whether an existing file may be replaced
+                match collections::move_replaced_file(&path) {
+                    Ok(_) => Lokbuch::open_database(AnyBackend::url_from_path(&path)),
+                    Err(err) => collection_error(t!("notification.collection_create_failed"), &err),
+                }
+            }
+
+            Message::StartDemo => {
+                Lokbuch::open_database(MEMORY_DB_URL.to_string())
+            }
+
+            Message::LeaveDemo => {
+                Lokbuch::open_database(self.data_location.db_url())
+            }
+
+            Message::DatabaseOpened(db_url, result) => {
+                match result {
+                    Ok(lrm) => {
+                        self.db_url = db_url;
+                        self.lok_resource_manager = lrm.with_image_location(self.owned_image_location());
+                        self.database_error = None;
+                        self.state.clear();
+                        self.search_input.clear();
+                        self.search_error = None;
+                        self.lok_list = LokListState::default();
+                        self.refresh_snapshots();
+
+                        self.change_page_to(Pages::Home);
+                    }
+                    Err(err) => {
+                        println!("Couldn't open database {}: {}", db_url, err);
+                        self.database_error = Some(err);
+
+                        self.change_page_to(Pages::Recovery);
+                    }
+                }
+                Task::none()
+            }
+
+            Message::CreateBackup => {
+                let file = FileDialog::new()
+                    .add_filter(t!("backup.backup_files"), &[backup::BACKUP_FILE_EXTENSION])
+                    .set_file_name(backup::default_file_name())
+                    .save_file();
+
+                let Some(target) = file else {
+                    return Task::none();
+                };
+
+                let db_url = self.db_url.clone();
+                let data_location = self.data_location.clone();
+                let settings = self.settings.clone();
+
+                Task::perform(
+                    async move { backup::create_backup(&db_url, &data_location, &settings, &target).await },
+                    Message::BackupCreated,
+                )
+            }
+
+            Message::BackupCreated(result) => {
+                let notification = match result {
+                    Ok(summary) if summary.skipped_images.is_empty() => {
+                        Notification::info(t!("notification.backup_created", images = summary.images))
+                    }
+                    Ok(summary) => {
+                        Notification::info(t!("notification.backup_created_incomplete", images = summary.images, skipped = summary.skipped_images.len()))
+                    }
+                    Err(err) => {
+                        println!("Backup failed: {}", err);
+                        Notification::error(format!("{} {}", t!("notification.backup_failed"), localized_backup_error(&err)))
+                    }
+                };
+
+                Task::done(Message::Notify(notification))
+            }
+
+            Message::RestoreBackup => {
+                let file = FileDialog::new()
+                    .add_filter(t!("backup.backup_files"), &[backup::BACKUP_FILE_EXTENSION])
+                    .pick_file();
+
+                match file {
+                    Some(archive) => self.restore_backup(archive),
+                    None => Task::none(),
                 }
             }
 
+            Message::BackupRestored(result) => {
+                let notification = match result {
+                    Ok(settings) => {
+                        // only the language is taken over, the directories belong to this computer
+                        if let Some(settings) = settings {
+                            self.settings.language = settings.language;
+                            set_locale(&self.settings.language);
+
+                            if let Err(err) = self.settings.save(&self.data_location.settings_path()) {
+                                println!("Failed to save restored settings: {}", err);
+                            }
+                        }
+
+                        Notification::info(t!("notification.backup_restored"))
+                    }
+                    Err(err) => {
+                        println!("Restore failed: {}", err);
+                        Notification::error(format!("{} {}", t!("notification.restore_failed"), localized_backup_error(&err)))
+                    }
+                };
+
+                // the collection was closed for the restore, it is opened again in any case
+                Task::batch([
+                    Task::done(Message::Notify(notification)),
+                    Lokbuch::open_database(self.db_url.clone()),
+                ])
+            }
+
+            Message::SnapshotRestored(result) => {
+                let notification = match result {
+                    Ok(_) => Notification::info(t!("notification.snapshot_restored")),
+                    Err(err) => Notification::database_error(t!("notification.snapshot_restore_failed"), &err),
+                };
+
+                Task::batch([
+                    Task::done(Message::Notify(notification)),
+                    Lokbuch::open_database(self.db_url.clone()),
+                ])
+            }
+
+            // images are processed in the background, the input mask may have been left meanwhile
+            Message::ImageImported(result) => image_imported(self, result),
+
+            Message::ImageEdited(index, original, result) => image_edited(self, index, original, result),
+
             Message::Settings => {
                 self.state = State {
                     ..State::default()
                 };
+                self.refresh_snapshots();
 
                 self.change_page_to(Pages::Settings);
                 Task::none()
@@ -74,10 +341,126 @@ impl Lokbuch {
     }
 
     pub(crate) fn view(&self) -> Element<'_, Message> {
-        self.page.as_page_struct().view(self)
+        let page = self.page.as_page_struct().view(self);
+
+        if self.notifications.is_empty() {
+            page
+        } else {
+            stack![page, self.notifications.view()].into()
+        }
     }
 
     pub(crate) fn change_page_to(&mut self, page: Pages) {
         self.page = page;
+        self.page_status = PageStatus::Ready;
+    }
+
+    /// Returns true, while the current page waits for the database.
+    pub(crate) fn is_busy(&self) -> bool {
+        matches!(self.page_status, PageStatus::Loading | PageStatus::Saving)
+    }
+
+    /// Changes the search of the home page, its first page is loaded in the background.
+    pub(crate) fn search_previews(&mut self, filter: PreviewFilter, sort: PreviewSort) -> Task<Message> {
+        let query = self.lok_resource_manager.search(filter, sort);
+        self.load_previews(query)
+    }
+
+    /// Loads a page of previews in the background, the result is delivered with ```Message::PreviewsLoaded```.
+    pub(crate) fn load_previews(&self, query: PreviewQuery) -> Task<Message> {
+        Task::perform(
+            self.lok_resource_manager.query_previews(query.clone()),
+            move |result| Message::PreviewsLoaded(query, result),
+        )
+    }
+
+    /// Returns true, if the current collection is kept in memory only.
+    pub(crate) fn is_demo(&self) -> bool {
+        self.db_url.starts_with(MEMORY_DB_URL)
+    }
+
+    /// Returns true, if the given collection of the data directory is open.
+    /// ```None``` stands for the default collection.
+    pub(crate) fn is_open_collection(&self, collection: Option<&str>) -> bool {
+        self.is_selected_collection(collection) && self.db_url == self.data_location.db_url()
+    }
+
+    /// Returns true, if the settings and the data location point to the given collection, even while the demo or a file is open.
+    /// Such a collection must not be renamed or deleted.
+    pub(crate) fn is_selected_collection(&self, collection: Option<&str>) -> bool {
+        self.data_location.collection() == collection
+    }
+
+    /// Reads the names of the collections from the data directory.
+    pub(crate) fn refresh_collections(&mut self) {
+        match collections::list(&self.data_location) {
+            Ok(names) => { self.collections = names; }
+            Err(err) => {
+                println!("Couldn't list collections: {}", err);
+                self.collections.clear();
+            }
+        }
+    }
+
+    /// Closes the current collection and replaces it with the content of a backup archive in the background.
+    /// The result is delivered with ```Message::BackupRestored```.
+    /// Only the database of the selected collection is replaced, never a file opened from elsewhere,
+    /// as the images of the collection directory belong to it.
+    pub(crate) fn restore_backup(&self, archive: PathBuf) -> Task<Message> {
+        if !self.has_snapshots() {
+            return Task::done(Message::Notify(Notification::error(t!("notification.restore_foreign_collection"))));
+        }
+
+        let handle = self.lok_resource_manager.handle();
+        let db_path = AnyBackend::path_from_url(&self.db_url);
+        let data_location = self.data_location.clone();
+
+        Task::perform(
+            async move {
+                handle.close().await;
+                backup::restore_backup(&archive, &db_path, &data_location).await
+            },
+            Message::BackupRestored,
+        )
+    }
+
+    /// Returns true, if the open database is the one of the selected collection.
+    /// Only then snapshots are taken, collection files opened from elsewhere and the demo have none.
+    pub(crate) fn has_snapshots(&self) -> bool {
+        self.db_url == self.data_location.db_url()
+    }
+
+    /// Returns the location of the image files, the open database may delete.
+    /// The demo and collection files opened from elsewhere share the image directory with the selected collection,
+    /// so they never delete images.
+    pub(crate) fn owned_image_location(&self) -> Option<DataLocation> {
+        self.data_location.owned_by(&self.db_url)
+    }
+
+    /// Reads the list of snapshots of the open collection.
+    pub(crate) fn refresh_snapshots(&mut self) {
+        if !self.has_snapshots() {
+            self.snapshots.clear();
+            return;
+        }
+
+        match snapshots::list(&self.data_location) {
+            Ok(snapshots) => { self.snapshots = snapshots; }
+            Err(err) => {
+                println!("Couldn't list snapshots: {}", err);
+                self.snapshots.clear();
+            }
+        }
+    }
+
+    /// Opens the database at the given url in the background.
+    /// The result is delivered with ```Message::DatabaseOpened```.
+    pub(crate) fn open_database(db_url: String) -> Task<Message> {
+        let url = db_url.clone();
+
+        Task::perform(
+            async move { LokResourceManager::<AnyBackend>::build(&url).await },
+            move |result| Message::DatabaseOpened(db_url.clone(), result),
+        )
     }
 }
\ No newline at end of file
@@ -1,25 +1,44 @@
 use crate::app::backend::database::lok::Lok;
 use crate::app::backend::database::preview_lok::PreviewLok;
+use crate::app::backend::database::preview_query::{PreviewPage, PreviewQuery};
 use crate::app::backend::database::DatabaseError;
 use std::fmt::Debug;
 
 pub mod database;
 pub mod resource_manager;
+pub mod preview_cache;
 mod test;
 pub mod sqlite_backend;
+pub mod memory_backend;
+pub mod json_backend;
+pub mod any_backend;
 
 /// The backend is responsible for the direct communication with the database.
 /// It encapsulates the concrete SQL statements.
 pub trait Backend: Sized + Clone + Default + Debug {
     async fn build(db_url: &str) -> Result<Self, DatabaseError>;
 
-    async fn insert(&self, lok: Lok) -> u32;
+    /// Inserts a new lok and returns its id.
+    async fn insert(&self, lok: Lok) -> Result<u32, DatabaseError>;
 
-    async fn get(&self, id: u32) -> Option<Lok>;
+    /// Returns the lok with the given id or ```DatabaseError::NotFound```.
+    async fn get(&self, id: u32) -> Result<Lok, DatabaseError>;
 
-    async fn update(&self, id: u32, new_lok: &Lok);
+    /// Overwrites the lok with the given id.
+    async fn update(&self, id: u32, new_lok: &Lok) -> Result<(), DatabaseError>;
 
-    async fn remove(&self, id: u32);
+    /// Removes the lok with the given id.
+    async fn remove(&self, id: u32) -> Result<(), DatabaseError>;
 
-    async fn get_all_previews(&self) -> Vec<PreviewLok>;
+    async fn get_all_previews(&self) -> Result<Vec<PreviewLok>, DatabaseError>;
+
+    /// Returns the page of filtered and sorted previews the query selects.
+    async fn query_previews(&self, query: &PreviewQuery) -> Result<PreviewPage, DatabaseError>;
+
+    /// Returns the number of loks that use the image with the given stored path.
+    async fn count_image_references(&self, image_path: &str) -> Result<u32, DatabaseError>;
+
+    /// Releases the underlying storage, so that its files can be replaced.
+    /// The backend must not be used afterwards.
+    async fn close(&self);
 }
\ No newline at end of file
@@ -0,0 +1,174 @@
+use crate::app::backend::database::lok::Lok;
+use crate::app::backend::database::preview_lok::PreviewLok;
+use crate::app::backend::database::preview_query::{PreviewPage, PreviewQuery};
+use crate::app::backend::database::sqlite_db::SQLiteDB;
+use crate::app::backend::database::DatabaseError;
+use crate::app::backend::json_backend::{JsonBackend, JSON_URL_SCHEME};
+use crate::app::backend::memory_backend::{MemoryBackend, MEMORY_DB_URL};
+use crate::app::backend::sqlite_backend::SQLiteBackend;
+use crate::app::backend::Backend;
+use std::path::{Path, PathBuf};
+
+/// File extension of collections stored with the JSON backend.
+pub const JSON_FILE_EXTENSION: &str = "json";
+/// File extension of collections stored with the SQLite backend.
+pub const SQLITE_FILE_EXTENSION: &str = "db";
+
+/// Backend that is selected at runtime by the scheme of the database url.
+/// ```memory://``` selects the in-memory backend, ```json://``` the JSON backend.
+/// Every other url is opened with SQLite.
+#[derive(Clone, Debug)]
+pub enum AnyBackend {
+    SQLite(SQLiteBackend),
+    Json(JsonBackend),
+    Memory(MemoryBackend),
+}
+
+impl AnyBackend {
+    /// Returns the url for a collection file, the backend is chosen by the file extension.
+    pub fn url_from_path(path: &Path) -> String {
+        let is_json = path.extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(JSON_FILE_EXTENSION));
+
+        if is_json {
+            JsonBackend::url_from_path(path)
+        } else {
+            SQLiteDB::url_from_path(path)
+        }
+    }
+
+    /// Returns true, if the url is opened with SQLite.
+    pub fn is_sqlite_url(db_url: &str) -> bool {
+        !db_url.starts_with(MEMORY_DB_URL) && !db_url.starts_with(JSON_URL_SCHEME)
+    }
+
+    /// Returns the path of the collection file an url points to.
+    pub fn path_from_url(db_url: &str) -> PathBuf {
+        if db_url.starts_with(JSON_URL_SCHEME) {
+            JsonBackend::path_from_url(db_url)
+        } else {
+            SQLiteDB::path_from_url(db_url)
+        }
+    }
+}
+
+impl Default for AnyBackend {
+    fn default() -> Self {
+        AnyBackend::Memory(MemoryBackend::default())
+    }
+}
+
+impl Backend for AnyBackend {
+    async fn build(db_url: &str) -> Result<Self, DatabaseError> {
+        if db_url.starts_with(MEMORY_DB_URL) {
+            Ok(AnyBackend::Memory(MemoryBackend::build(db_url).await?))
+        } else if db_url.starts_with(JSON_URL_SCHEME) {
+            Ok(AnyBackend::Json(JsonBackend::build(db_url).await?))
+        } else {
+            Ok(AnyBackend::SQLite(SQLiteBackend::build(db_url).await?))
+        }
+    }
+
+    async fn insert(&self, lok: Lok) -> Result<u32, DatabaseError> {
+        match self {
+            AnyBackend::SQLite(backend) => backend.insert(lok).await,
+            AnyBackend::Json(backend) => backend.insert(lok).await,
+            AnyBackend::Memory(backend) => backend.insert(lok).await,
+        }
+    }
+
+    async fn get(&self, id: u32) -> Result<Lok, DatabaseError> {
+        match self {
+            AnyBackend::SQLite(backend) => backend.get(id).await,
+            AnyBackend::Json(backend) => backend.get(id).await,
+            AnyBackend::Memory(backend) => backend.get(id).await,
+        }
+    }
+
+    async fn update(&self, id: u32, new_lok: &Lok) -> Result<(), DatabaseError> {
+        match self {
+            AnyBackend::SQLite(backend) => backend.update(id, new_lok).await,
+            AnyBackend::Json(backend) => backend.update(id, new_lok).await,
+            AnyBackend::Memory(backend) => backend.update(id, new_lok).await,
+        }
+    }
+
+    async fn remove(&self, id: u32) -> Result<(), DatabaseError> {
+        match self {
+            AnyBackend::SQLite(backend) => backend.remove(id).await,
+            AnyBackend::Json(backend) => backend.remove(id).await,
+            AnyBackend::Memory(backend) => backend.remove(id).await,
+        }
+    }
+
+    async fn get_all_previews(&self) -> Result<Vec<PreviewLok>, DatabaseError> {
+        match self {
+            AnyBackend::SQLite(backend) => backend.get_all_previews().await,
+            AnyBackend::Json(backend) => backend.get_all_previews().await,
+            AnyBackend::Memory(backend) => backend.get_all_previews().await,
+        }
+    }
+
+    async fn query_previews(&self, query: &PreviewQuery) -> Result<PreviewPage, DatabaseError> {
+        match self {
+            AnyBackend::SQLite(backend) => backend.query_previews(query).await,
+            AnyBackend::Json(backend) => backend.query_previews(query).await,
+            AnyBackend::Memory(backend) => backend.query_previews(query).await,
+        }
+    }
+
+    async fn count_image_references(&self, image_path: &str) -> Result<u32, DatabaseError> {
+        match self {
+            AnyBackend::SQLite(backend) => backend.count_image_references(image_path).await,
+            AnyBackend::Json(backend) => backend.count_image_references(image_path).await,
+            AnyBackend::Memory(backend) => backend.count_image_references(image_path).await,
+        }
+    }
+
+    async fn close(&self) {
+        match self {
+            AnyBackend::SQLite(backend) => backend.close().await,
+            AnyBackend::Json(backend) => backend.close().await,
+            AnyBackend::Memory(backend) => backend.close().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod any_backend_tests {
+    use super::*;
+    use crate::app::backend::test;
+    use async_std::task;
+
+    #[test]
+    fn memory_url_selects_memory_backend() {
+        let backend = task::block_on(AnyBackend::build(MEMORY_DB_URL)).unwrap();
+
+        assert!(matches!(backend, AnyBackend::Memory(_)));
+    }
+
+    #[test]
+    fn sqlite_url_selects_sqlite_backend() {
+        test::util::remove_test_db(26);
+
+        let backend = task::block_on(AnyBackend::build("sqlite://test/test26.db")).unwrap();
+
+        assert!(matches!(backend, AnyBackend::SQLite(_)));
+    }
+
+    #[test]
+    fn json_url_selects_json_backend() {
+        test::util::remove_test_json(7);
+
+        let backend = task::block_on(AnyBackend::build("json://test/test7.json")).unwrap();
+
+        assert!(matches!(backend, AnyBackend::Json(_)));
+    }
+
+    #[test]
+    fn url_is_chosen_by_file_extension() {
+        assert_eq!(AnyBackend::url_from_path(Path::new("data/lokbuch.json")), "json://data/lokbuch.json");
+        assert_eq!(AnyBackend::url_from_path(Path::new("data/lokbuch.db")), "sqlite://data/lokbuch.db");
+        assert_eq!(AnyBackend::path_from_url("json://data/lokbuch.json"), PathBuf::from("data/lokbuch.json"));
+    }
+}
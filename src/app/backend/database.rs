@@ -1,26 +1,102 @@
 pub mod lok;
 pub mod preview_lok;
+pub mod preview_query;
+pub mod search;
+pub mod search_query;
 pub mod sqlite_db;
 
-use sqlx::migrate::MigrateDatabase;
-use sqlx::{Error, Pool, Sqlite, SqlitePool};
+use sqlx::error::ErrorKind;
+use sqlx::Pool;
+use std::fmt::{Display, Formatter};
 
-const DB_URL: &str = "sqlite://lokbuch.db";
+// primary SQLite result codes, see https://www.sqlite.org/rescode.html
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
+const SQLITE_IOERR: i32 = 10;
+const SQLITE_CORRUPT: i32 = 11;
+const SQLITE_CANTOPEN: i32 = 14;
+const SQLITE_NOTADB: i32 = 26;
 
 /// Represents an error upon database operation failure.
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum DatabaseError {
+    /// The database could not be opened or connected to.
     ConnectionError,
+    /// The requested entry does not exist.
+    NotFound,
+    /// The statement was rejected, because it would violate a constraint of the schema.
+    ConstraintViolation(String),
+    /// The database file could not be read or written.
+    Io(String),
+    /// Applying the schema migrations failed.
+    Migration(String),
+    /// The database was migrated by a newer build, its schema version is unknown to this build.
+    NewerSchema(i64),
+    /// The database is locked by another connection.
+    Busy,
+    /// Any other failure.
     GeneralError(String),
-    SpecificError(String),
+}
+
+impl Display for DatabaseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DatabaseError::ConnectionError => write!(f, "Could not connect to the database"),
+            DatabaseError::NotFound => write!(f, "Entry not found"),
+            DatabaseError::ConstraintViolation(message) => write!(f, "Constraint violation: {}", message),
+            DatabaseError::Io(message) => write!(f, "I/O error: {}", message),
+            DatabaseError::Migration(message) => write!(f, "Migration failed: {}", message),
+            DatabaseError::NewerSchema(version) => write!(f, "Database schema version {} is newer than this build supports", version),
+            DatabaseError::Busy => write!(f, "Database is busy"),
+            DatabaseError::GeneralError(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+impl From<sqlx::Error> for DatabaseError {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::RowNotFound => DatabaseError::NotFound,
+            sqlx::Error::Io(err) => DatabaseError::Io(err.to_string()),
+            sqlx::Error::PoolTimedOut => DatabaseError::Busy,
+            sqlx::Error::PoolClosed | sqlx::Error::Configuration(_) => DatabaseError::ConnectionError,
+            sqlx::Error::Migrate(err) => DatabaseError::Migration(err.to_string()),
+            sqlx::Error::Database(err) => {
+                match err.kind() {
+                    ErrorKind::UniqueViolation
+                    | ErrorKind::ForeignKeyViolation
+                    | ErrorKind::NotNullViolation
+                    | ErrorKind::CheckViolation => {
+                        return DatabaseError::ConstraintViolation(err.message().to_owned());
+                    }
+                    _ => {}
+                }
+
+                // SQLite extended result codes carry the primary result code in the lowest byte
+                let primary_code = err.code()
+                    .and_then(|code| code.parse::<i32>().ok())
+                    .map(|code| code & 0xff);
+
+                match primary_code {
+                    Some(SQLITE_BUSY) | Some(SQLITE_LOCKED) => DatabaseError::Busy,
+                    Some(SQLITE_IOERR) | Some(SQLITE_CORRUPT) | Some(SQLITE_CANTOPEN) | Some(SQLITE_NOTADB) => {
+                        DatabaseError::Io(err.message().to_owned())
+                    }
+                    _ => DatabaseError::GeneralError(err.message().to_owned()),
+                }
+            }
+            err => DatabaseError::GeneralError(err.to_string()),
+        }
+    }
 }
 
 pub(crate) trait Database {
     /// Type of the driver, that is a database of sqlx
     type DRV: sqlx::Database;
 
-    /// Creates the database.
-    /// Does not connect to database.
+    /// Creates the database if it does not exist and brings its schema up to date.
     /// Is called before every other method.
     async fn build(db_url: &str) -> Result<Self, DatabaseError>
     where
@@ -29,37 +105,3 @@ pub(crate) trait Database {
     /// Connects to database.
     async fn connect(&mut self) -> Result<Pool<Self::DRV>, DatabaseError>;
 }
-
-pub async fn create_database() {
-    if !Sqlite::database_exists(DB_URL).await.unwrap_or(false) {
-        println!("Creating database {}", DB_URL);
-        match Sqlite::create_database(DB_URL).await {
-            Ok(_) => { println!("Database successfully created"); }
-            Err(err) => { panic!("Database creation failed: {}", err) }
-        }
-    } else {
-        println!("SQLite database already exists");
-    }
-}
-
-pub async fn connect() -> Result<Pool<Sqlite>, Error> {
-    SqlitePool::connect(DB_URL).await
-}
-
-pub async fn migrate(db: &Pool<Sqlite>) {
-    let crate_dir = std::env::current_dir().unwrap();
-    let migrations = std::path::Path::new(&crate_dir).join("../../../migrations");
-
-    let migration_results = sqlx::migrate::Migrator::new(migrations)
-        .await
-        .unwrap()
-        .run(db)
-        .await;
-
-    match migration_results {
-        Ok(_) => { println!("Migration success") }
-        Err(err) => { panic!("error: {}", err) }
-    }
-
-    println!("migration: {:?}", migration_results);
-}
\ No newline at end of file
@@ -1,7 +1,15 @@
 use crate::app::ui;
 use crate::database::preview_lok::PreviewLok;
+use crate::database::search;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
 
-#[derive(Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+/// Addresses a DCC decoder can have, 0 is reserved for broadcasts.
+pub const ADDRESS_RANGE: RangeInclusive<i32> = 1..=10239;
+/// The LOKmaus display shows at most five characters.
+pub const MAX_LOKMAUS_NAME_LENGTH: usize = 5;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub struct Lok {
     pub name: String,
     pub address: Option<i32>,
@@ -9,19 +17,72 @@ pub struct Lok {
     pub producer: Option<String>,
     pub management: Option<String>,
     pub has_decoder: bool,
-    pub image_path: Option<String>,
+    /// The photos of the lok in the order they are shown.
+    #[serde(default)]
+    pub images: Vec<LokImage>,
+}
+
+/// A photo of a lok.
+/// Exactly one photo of a lok is its primary image, it is shown wherever only one image fits.
+#[derive(Serialize, Deserialize, Clone, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct LokImage {
+    /// The stored path of the image file.
+    pub path: String,
+    #[serde(default)]
+    pub caption: Option<String>,
+    #[serde(default)]
+    pub is_primary: bool,
+    /// The stored path of the uncropped image, if the image was cropped in the image editor.
+    #[serde(default)]
+    pub original: Option<String>,
+}
+
+impl LokImage {
+    pub fn new(path: String) -> LokImage {
+        LokImage {
+            path,
+            caption: None,
+            is_primary: false,
+            original: None,
+        }
+    }
+
+    /// Returns the stored paths of the image and its original.
+    pub fn stored_paths(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.path).chain(self.original.as_ref())
+    }
+}
+
+/// A row of the lok_images table.
+#[derive(sqlx::FromRow, Clone, Debug, Default)]
+pub struct RawLokImageData {
+    path: String,
+    caption: Option<String>,
+    is_primary: bool,
+    original_path: Option<String>,
+}
+
+impl RawLokImageData {
+    pub fn as_lok_image(&self) -> LokImage {
+        LokImage {
+            path: self.path.clone(),
+            caption: self.caption.clone(),
+            is_primary: self.is_primary,
+            original: self.original_path.clone(),
+        }
+    }
 }
 
+/// A row of the loks table.
+/// Missing values are stored as NULL.
 #[derive(sqlx::FromRow, Clone, Debug, Default)]
 pub struct RawLokData {
-    id: i32,
     name: String,
-    address: i32,
-    lokmaus_name: String,
-    producer: String,
-    management: String,
+    address: Option<i32>,
+    lokmaus_name: Option<String>,
+    producer: Option<String>,
+    management: Option<String>,
     has_decoder: bool,
-    image_path: String,
 }
 
 impl Lok {
@@ -32,7 +93,7 @@ impl Lok {
         producer: Option<String>,
         management: Option<String>,
         has_decoder: bool,
-        image_path: Option<String>) -> Lok {
+        images: Vec<LokImage>) -> Lok {
         Lok {
             name,
             address,
@@ -40,69 +101,53 @@ impl Lok {
             producer,
             management,
             has_decoder,
-            image_path,
+            images,
         }
     }
 
-    pub fn new_from_raw_lok_data(raw_lok_data: &RawLokData) -> Lok {
+    pub fn new_from_raw_lok_data(raw_lok_data: &RawLokData, raw_images: &[RawLokImageData]) -> Lok {
         Lok::new(
             raw_lok_data.name.clone(),
-            if raw_lok_data.address < 0 { Some(-1) } else { Some(raw_lok_data.address) },
-            if raw_lok_data.lokmaus_name.is_empty() { None } else { Some(raw_lok_data.lokmaus_name.clone()) },
-            if raw_lok_data.producer.is_empty() { None } else { Some(raw_lok_data.producer.clone()) },
-            if raw_lok_data.management.is_empty() { None } else { Some(raw_lok_data.management.clone()) },
-            raw_lok_data.has_decoder.clone(),
-            if raw_lok_data.image_path.is_empty() { None } else { Some(raw_lok_data.image_path.clone()) },
+            raw_lok_data.address,
+            raw_lok_data.lokmaus_name.clone(),
+            raw_lok_data.producer.clone(),
+            raw_lok_data.management.clone(),
+            raw_lok_data.has_decoder,
+            raw_images.iter().map(RawLokImageData::as_lok_image).collect(),
         )
     }
 
-    pub fn new_from_raw_data(name: String, address: i32, lokmaus_name: String, producer: String, management: String, has_decoder: bool, image_path: String) -> Lok {
-        Lok::new_from_raw_lok_data(&RawLokData {
+    /// Builds a Lok from user input.
+    /// Empty texts are treated as missing values, captions included.
+    pub fn new_from_raw_data(name: String, address: Option<i32>, lokmaus_name: String, producer: String, management: String, has_decoder: bool, images: Vec<LokImage>) -> Lok {
+        let images = images.into_iter()
+            .map(|image| LokImage { caption: image.caption.and_then(non_empty), ..image })
+            .collect();
+
+        Lok::new(
             name,
             address,
-            lokmaus_name,
-            producer,
-            management,
+            non_empty(lokmaus_name),
+            non_empty(producer),
+            non_empty(management),
             has_decoder,
-            image_path,
-            ..Default::default()
-        })
-    }
-
-    fn as_raw_lok_data(&self) -> RawLokData {
-        RawLokData {
-            name: self.name.clone(),
-            address: if let Some(adress) = self.address {
-                adress
-            } else { -1 },
-            lokmaus_name: if let Some(lokmaus_name) = self.lokmaus_name.clone() {
-                lokmaus_name
-            } else { String::new() },
-            producer: if let Some(producer) = self.producer.clone() {
-                producer
-            } else { String::new() },
-            management: if let Some(management) = self.management.clone() {
-                management
-            } else { String::new() },
-            has_decoder: self.has_decoder.clone(),
-            image_path: if let Some(image_path) = self.image_path.clone() {
-                image_path
-            } else { String::new() },
-            ..RawLokData::default()
-        }
+            images,
+        )
     }
 
     pub fn as_preview_lok(&self, id: u32) -> PreviewLok {
-        PreviewLok::new(id, self.address.clone(), Some(self.name.clone()), self.lokmaus_name.clone())
+        PreviewLok::new(id, self.address, Some(self.name.clone()), self.lokmaus_name.clone())
+            .with_details(self.producer.clone(), self.management.clone(), self.has_decoder)
+    }
+
+    /// Returns the text the lok is found by in the search index of the database.
+    pub fn get_search_text(&self) -> String {
+        search::index_text(&self.as_preview_lok(0).search_fields())
     }
 
     pub fn get_address_pretty(&self) -> String {
         if let Some(address) = self.address {
-            if address < 0 {
-                ui::NO_DATA_AVAILABLE_TEXT.to_string()
-            } else {
-                address.to_string()
-            }
+            address.to_string()
         } else {
             ui::NO_DATA_AVAILABLE_TEXT.to_string()
         }
@@ -136,7 +181,23 @@ impl Lok {
         self.has_decoder
     }
 
-    pub fn get_image_path(&self) -> String {
-        if let Some(image_path) = self.image_path.clone() { image_path } else { String::new() }
+    /// Returns the index of the image marked as primary, or of the first image if none is marked.
+    pub fn primary_image_index(&self) -> usize {
+        self.images.iter().position(|image| image.is_primary).unwrap_or(0)
+    }
+
+    /// Returns the stored paths of all images and their originals.
+    pub fn image_paths(&self) -> Vec<String> {
+        self.images.iter().flat_map(LokImage::stored_paths).cloned().collect()
     }
+
+    /// Returns true, if one of the images or their originals is stored at image_path.
+    pub fn uses_image(&self, image_path: &str) -> bool {
+        self.images.iter().flat_map(LokImage::stored_paths).any(|path| path == image_path)
+    }
+}
+
+/// Returns ```None``` for an empty text.
+fn non_empty(text: String) -> Option<String> {
+    if text.is_empty() { None } else { Some(text) }
 }
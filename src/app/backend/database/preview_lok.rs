@@ -1,3 +1,4 @@
+use crate::app::backend::database::search::{SearchField, SearchMatch};
 use crate::app::ui;
 use std::cmp::Ordering;
 
@@ -7,44 +8,56 @@ pub struct PreviewLok {
     address: Option<i32>,
     name: Option<String>,
     lokmaus_name: Option<String>,
-    search_index: String,
+    producer: Option<String>,
+    management: Option<String>,
+    has_decoder: bool,
+    /// Where the search of the list the preview is shown in was found, ```None``` without a search.
+    search_match: Option<SearchMatch>,
 }
 
+/// The preview columns of a row of the loks table.
+/// Missing values are stored as NULL.
 #[derive(sqlx::FromRow)]
 pub struct PreviewData {
     id: i32,
-    address: i32,
+    address: Option<i32>,
     name: String,
-    lokmaus_name: String,
+    lokmaus_name: Option<String>,
+    producer: Option<String>,
+    management: Option<String>,
+    has_decoder: bool,
 }
 
 impl PreviewLok {
     pub(crate) fn new(id: u32, address: Option<i32>, name: Option<String>, lokmaus_name: Option<String>) -> Self {
-        let address_text = address.unwrap_or(-1);
-        let address_text = if address_text < 0 { "".to_string() } else { address_text.to_string() };
         Self {
             id,
             address,
-            name: name.clone(),
-            lokmaus_name: lokmaus_name.clone(),
-            search_index: String::from(
-                format!(
-                    "{} {} {}",
-                    address_text,
-                    name.unwrap_or("".to_string()),
-                    lokmaus_name.unwrap_or("".to_string())
-                )
-            ).to_lowercase(),
+            name,
+            lokmaus_name,
+            producer: None,
+            management: None,
+            has_decoder: false,
+            search_match: None,
         }
     }
 
+    /// Adds the fields that are only searched, not shown in the list.
+    pub(crate) fn with_details(self, producer: Option<String>, management: Option<String>, has_decoder: bool) -> Self {
+        Self { producer, management, has_decoder, ..self }
+    }
+
+    pub(crate) fn with_search_match(self, search_match: SearchMatch) -> Self {
+        Self { search_match: Some(search_match), ..self }
+    }
+
     pub fn new_from_raw_preview_data(data: &PreviewData) -> Self {
         PreviewLok::new(
             data.id as u32,
-            if data.address < 0 { None } else { Some(data.address) },
+            data.address,
             if data.name.is_empty() { None } else { Some(data.name.clone()) },
-            if data.lokmaus_name.is_empty() { None } else { Some(data.lokmaus_name.clone()) },
-        )
+            data.lokmaus_name.clone(),
+        ).with_details(data.producer.clone(), data.management.clone(), data.has_decoder)
     }
 
     pub fn get_id(&self) -> u32 {
@@ -65,11 +78,7 @@ impl PreviewLok {
 
     pub fn get_address_pretty(&self) -> String {
         if let Some(address) = self.address {
-            if address < 0 {
-                ui::NO_DATA_AVAILABLE_TEXT.to_string()
-            } else {
-                address.to_string()
-            }
+            address.to_string()
         } else {
             ui::NO_DATA_AVAILABLE_TEXT.to_string()
         }
@@ -91,8 +100,33 @@ impl PreviewLok {
         }
     }
 
-    pub fn get_search_string(&self) -> String {
-        self.search_index.clone()
+    pub fn get_producer(&self) -> Option<String> {
+        self.producer.clone()
+    }
+
+    pub fn get_management(&self) -> Option<String> {
+        self.management.clone()
+    }
+
+    pub fn get_has_decoder(&self) -> bool {
+        self.has_decoder
+    }
+
+    pub fn get_search_match(&self) -> Option<&SearchMatch> {
+        self.search_match.as_ref()
+    }
+
+    /// Returns the text of every searched field that has a value.
+    pub fn search_fields(&self) -> Vec<(SearchField, String)> {
+        [
+            (SearchField::Address, self.address.map(|address| address.to_string())),
+            (SearchField::Name, self.name.clone()),
+            (SearchField::LokmausName, self.lokmaus_name.clone()),
+            (SearchField::Producer, self.producer.clone()),
+            (SearchField::Management, self.management.clone()),
+        ].into_iter()
+            .filter_map(|(field, text)| text.map(|text| (field, text)))
+            .collect()
     }
 }
 
@@ -108,10 +142,11 @@ impl PartialOrd<Self> for PreviewLok {
     }
 }
 
+/// Sorts by address first, locos without an address come first.
+/// Locos with the same address are sorted by name.
 impl Ord for PreviewLok {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.address != None || other.address != None {
-            self.address.cmp(&other.address)
-        } else { self.name.cmp(&other.name) }
+        self.address.cmp(&other.address)
+            .then_with(|| self.name.cmp(&other.name))
     }
 }
\ No newline at end of file
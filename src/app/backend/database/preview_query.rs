@@ -0,0 +1,206 @@
+use crate::app::backend::database::preview_lok::PreviewLok;
+use crate::app::backend::database::search::{Search, SearchMatch};
+use crate::app::backend::database::search_query::{SearchQuery, SearchQueryError};
+use std::cmp::Ordering;
+
+/// Selects the loks a preview query returns.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum PreviewFilter {
+    #[default]
+    All,
+    /// Loks that contain every word of the search in one of their fields, typos are tolerated.
+    Text(Search),
+    /// Loks that match every term of a search with qualifiers.
+    Query(SearchQuery),
+}
+
+/// The column previews are sorted by.
+/// Missing values come first, previews with equal values are sorted by address, name and id.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PreviewSort {
+    #[default]
+    Address,
+    Name,
+    LokmausName,
+    /// The best fitting loks of a search come first, without a search it sorts like ```Address```.
+    Relevance,
+}
+
+/// A filtered, sorted page of previews, as it is run by the backend.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PreviewQuery {
+    pub filter: PreviewFilter,
+    pub sort: PreviewSort,
+    /// Number of matching previews that are skipped.
+    pub offset: u32,
+    /// Maximum number of returned previews, ```None``` returns every match after the offset.
+    pub limit: Option<u32>,
+}
+
+/// The result of a preview query.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PreviewPage {
+    pub previews: Vec<PreviewLok>,
+    /// Number of loks matching the filter, regardless of offset and limit.
+    pub total: u32,
+}
+
+impl PreviewFilter {
+    /// Returns the filter for the text of the search input, an empty input shows every lok.
+    /// Plain text is searched in every field, qualifiers, negations and phrases make it a ```SearchQuery```.
+    pub fn from_search_input(search_input: &str) -> Result<Self, SearchQueryError> {
+        let search = search_input.trim();
+
+        if search.is_empty() {
+            return Ok(PreviewFilter::All);
+        }
+
+        let query = SearchQuery::parse(search)?;
+
+        if query.is_plain() {
+            Ok(PreviewFilter::Text(Search::new(search)))
+        } else {
+            Ok(PreviewFilter::Query(query))
+        }
+    }
+
+    /// Returns the sort the results of the filter are shown in first.
+    pub fn default_sort(&self) -> PreviewSort {
+        match self {
+            PreviewFilter::All => PreviewSort::Address,
+            PreviewFilter::Text(_) | PreviewFilter::Query(_) => PreviewSort::Relevance,
+        }
+    }
+
+    fn find(&self, preview: &PreviewLok) -> Option<SearchMatch> {
+        match self {
+            PreviewFilter::All => Some(SearchMatch::default()),
+            PreviewFilter::Text(search) => search.find(&preview.search_fields()),
+            PreviewFilter::Query(query) => query.find(preview),
+        }
+    }
+
+    pub fn matches(&self, preview: &PreviewLok) -> bool {
+        self.find(preview).is_some()
+    }
+
+    /// Returns the preview with the place the search was found in, ```None``` if it does not match.
+    /// The previews of ```All``` are returned unchanged.
+    pub fn select(&self, preview: PreviewLok) -> Option<PreviewLok> {
+        match self {
+            PreviewFilter::All => Some(preview),
+            PreviewFilter::Text(_) | PreviewFilter::Query(_) => {
+                let search_match = self.find(&preview)?;
+                Some(preview.with_search_match(search_match))
+            }
+        }
+    }
+}
+
+impl PreviewSort {
+    /// Compares two previews in this order.
+    /// The id decides between equal previews, so every preview has exactly one place in a sorted list.
+    pub fn compare(&self, a: &PreviewLok, b: &PreviewLok) -> Ordering {
+        let by_column = match self {
+            PreviewSort::Address => Ordering::Equal,
+            PreviewSort::Name => a.get_name().cmp(&b.get_name()),
+            PreviewSort::LokmausName => a.get_lokmaus_name().cmp(&b.get_lokmaus_name()),
+            PreviewSort::Relevance => score(b).cmp(&score(a)),
+        };
+
+        by_column
+            .then_with(|| a.cmp(b))
+            .then_with(|| a.get_id().cmp(&b.get_id()))
+    }
+}
+
+fn score(preview: &PreviewLok) -> u32 {
+    preview.get_search_match().map_or(0, |search_match| search_match.score)
+}
+
+impl PreviewQuery {
+    pub fn new(filter: PreviewFilter, sort: PreviewSort) -> Self {
+        PreviewQuery { filter, sort, offset: 0, limit: None }
+    }
+
+    /// Returns the same query for another page of its results.
+    pub fn paged(&self, offset: u32, limit: u32) -> Self {
+        PreviewQuery { offset, limit: Some(limit), ..self.clone() }
+    }
+
+    /// Returns true, if both queries select and sort the same previews, regardless of the page.
+    pub fn is_same_search(&self, other: &PreviewQuery) -> bool {
+        self.filter == other.filter && self.sort == other.sort
+    }
+
+    /// Runs the query on previews in any order.
+    /// Used by the backends that have no query engine.
+    pub fn apply(&self, previews: impl IntoIterator<Item = PreviewLok>) -> PreviewPage {
+        let mut matching: Vec<PreviewLok> = previews.into_iter()
+            .filter_map(|preview| self.filter.select(preview))
+            .collect();
+        matching.sort_unstable_by(|a, b| self.sort.compare(a, b));
+
+        let total = matching.len() as u32;
+        let previews = matching.into_iter()
+            .skip(self.offset as usize)
+            .take(self.limit.map_or(usize::MAX, |limit| limit as usize))
+            .collect();
+
+        PreviewPage { previews, total }
+    }
+}
+
+#[cfg(test)]
+mod preview_query_tests {
+    use crate::app::backend::database::preview_lok::PreviewLok;
+    use crate::app::backend::database::preview_query::{PreviewFilter, PreviewQuery, PreviewSort};
+
+    fn previews() -> Vec<PreviewLok> {
+        vec![
+            PreviewLok::new(1, Some(218), Some("BR 218".to_string()), Some("BR218".to_string())),
+            PreviewLok::new(2, None, Some("Köf II".to_string()), None),
+            PreviewLok::new(3, Some(44), Some("V 100".to_string()), Some("V100".to_string())),
+            PreviewLok::new(4, Some(44), Some("BR 44".to_string()), None),
+        ]
+    }
+
+    fn ids(query: &PreviewQuery) -> Vec<u32> {
+        query.apply(previews()).previews.iter().map(PreviewLok::get_id).collect()
+    }
+
+    #[test]
+    fn previews_are_sorted_by_the_chosen_column() {
+        assert_eq!(ids(&PreviewQuery::new(PreviewFilter::All, PreviewSort::Address)), vec![2, 4, 3, 1]);
+        assert_eq!(ids(&PreviewQuery::new(PreviewFilter::All, PreviewSort::Name)), vec![1, 4, 2, 3]);
+        assert_eq!(ids(&PreviewQuery::new(PreviewFilter::All, PreviewSort::LokmausName)), vec![2, 4, 1, 3]);
+    }
+
+    #[test]
+    fn previews_are_filtered_and_paged() {
+        let query = PreviewQuery::new(PreviewFilter::from_search_input(" br ").unwrap(), PreviewSort::Address);
+
+        assert_eq!(ids(&query), vec![4, 1]);
+        assert_eq!(ids(&query.paged(1, 5)), vec![1]);
+        assert_eq!(query.paged(1, 5).apply(previews()).total, 2);
+
+        assert_eq!(ids(&PreviewQuery::new(PreviewFilter::All, PreviewSort::Address).paged(1, 2)), vec![4, 3]);
+        assert_eq!(PreviewFilter::from_search_input("  ").unwrap(), PreviewFilter::All);
+    }
+
+    #[test]
+    fn best_matches_come_first() {
+        let previews = vec![
+            PreviewLok::new(1, Some(3), Some("Rocco Express".to_string()), None),
+            PreviewLok::new(2, Some(5), Some("Roco".to_string()), None),
+        ];
+        let search = PreviewFilter::from_search_input("roco").unwrap();
+        let ranked: Vec<u32> = PreviewQuery::new(search.clone(), search.default_sort()).apply(previews).previews.iter()
+            .map(PreviewLok::get_id)
+            .collect();
+
+        assert_eq!(ranked, vec![2, 1]);
+        assert_eq!(ids(&PreviewQuery::new(PreviewFilter::from_search_input("kof").unwrap(), PreviewSort::Relevance)), vec![2]);
+        assert_eq!(ids(&PreviewQuery::new(PreviewFilter::All, PreviewSort::Relevance)), vec![2, 4, 3, 1]);
+    }
+}
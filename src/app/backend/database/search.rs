@@ -0,0 +1,296 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// A search word matches a word of a lok despite typos, if the lok word contains more than this share
+/// of the trigrams of the search word.
+const MIN_TRIGRAM_SHARE: f32 = 0.5;
+
+// the score of a search word by how it is found, the scores of all words of a search are added up
+const WORD_SCORE: u32 = 100;
+const PREFIX_SCORE: u32 = 80;
+const SUBSTRING_SCORE: u32 = 60;
+/// The score of a word that is found with typos, multiplied by the share of its trigrams that are found.
+const FUZZY_SCORE: f32 = 50.0;
+/// Added, if a word is found in a field every row of the lok list shows.
+const LISTED_FIELD_BONUS: u32 = 10;
+
+/// The fields of a lok a search looks into.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SearchField {
+    Address,
+    Name,
+    LokmausName,
+    Producer,
+    Management,
+}
+
+/// The part of a field a search word was found in.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Highlight {
+    pub field: SearchField,
+    /// Byte range in the text of the field.
+    pub range: Range<usize>,
+}
+
+/// How well a lok fits a search and where its words were found.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct SearchMatch {
+    /// Better fitting loks have a higher score.
+    pub score: u32,
+    pub highlights: Vec<Highlight>,
+}
+
+/// A search for loks by the words of their fields.
+/// Every word of the search has to be found in a field, either within a word of the field,
+/// or with typos, if both words share enough trigrams.
+/// Case and diacritics are ignored, everything but letters and digits separates words.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Search {
+    words: Vec<SearchWord>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct SearchWord {
+    chars: Vec<char>,
+    trigrams: HashSet<[char; 3]>,
+}
+
+/// A word of a field, lowercase and without diacritics.
+struct Word {
+    chars: Vec<char>,
+    /// The byte offset of every char in the field text, followed by the end of the word.
+    offsets: Vec<usize>,
+}
+
+impl SearchField {
+    /// Returns true for the fields every row of the lok list shows.
+    pub fn is_listed(&self) -> bool {
+        matches!(self, SearchField::Address | SearchField::Name | SearchField::LokmausName)
+    }
+}
+
+impl Search {
+    pub fn new(text: &str) -> Self {
+        let words = split_words(text).into_iter()
+            .map(|word| SearchWord { trigrams: trigrams(&word.chars), chars: word.chars })
+            .collect();
+
+        Search { words }
+    }
+
+    /// Returns the words of the search, lowercase and without diacritics.
+    pub fn words(&self) -> Vec<String> {
+        self.words.iter().map(|word| word.chars.iter().collect()).collect()
+    }
+
+    /// Matches the fields of a lok, ```None``` if a word of the search is not found in any field.
+    /// A search without words matches every lok.
+    pub fn find(&self, fields: &[(SearchField, String)]) -> Option<SearchMatch> {
+        let field_words: Vec<(SearchField, Vec<Word>)> = fields.iter()
+            .map(|(field, text)| (*field, split_words(text)))
+            .collect();
+
+        let mut search_match = SearchMatch::default();
+
+        for search_word in &self.words {
+            let (score, highlight) = field_words.iter()
+                .flat_map(|(field, words)| words.iter().map(move |word| (*field, word)))
+                .filter_map(|(field, word)| search_word.find_in(field, word))
+                .fold(None, |best: Option<(u32, Highlight)>, found| match best {
+                    Some(best) if best.0 >= found.0 => Some(best),
+                    _ => Some(found),
+                })?;
+
+            search_match.score += score;
+            search_match.highlights.push(highlight);
+        }
+
+        Some(search_match)
+    }
+
+    /// Matches the fields of a lok, if the words of the search follow each other in one of them.
+    /// Only whole words are compared, so typos are not tolerated.
+    pub fn find_phrase(&self, fields: &[(SearchField, String)]) -> Option<SearchMatch> {
+        if self.words.is_empty() {
+            return Some(SearchMatch::default());
+        }
+
+        fields.iter().find_map(|(field, text)| {
+            let words = split_words(text);
+            let start = words.windows(self.words.len())
+                .position(|window| window.iter().zip(&self.words).all(|(word, search_word)| word.chars == search_word.chars))?;
+
+            let first = &words[start];
+            let last = &words[start + self.words.len() - 1];
+            let bonus = if field.is_listed() { LISTED_FIELD_BONUS } else { 0 };
+
+            Some(SearchMatch {
+                score: (WORD_SCORE + bonus) * self.words.len() as u32,
+                highlights: vec![Highlight { field: *field, range: first.offsets[0]..last.offsets[last.chars.len()] }],
+            })
+        })
+    }
+}
+
+impl SearchWord {
+    /// Returns the score of the search word in a word of a field and the part of the field it was found in.
+    fn find_in(&self, field: SearchField, word: &Word) -> Option<(u32, Highlight)> {
+        let (score, chars) = if word.chars == self.chars {
+            (WORD_SCORE, 0..word.chars.len())
+        } else if let Some(position) = find_chars(&word.chars, &self.chars) {
+            let score = if position == 0 { PREFIX_SCORE } else { SUBSTRING_SCORE };
+            (score, position..position + self.chars.len())
+        } else {
+            let share = self.trigrams.intersection(&trigrams(&word.chars)).count() as f32 / self.trigrams.len() as f32;
+
+            if share <= MIN_TRIGRAM_SHARE {
+                return None;
+            }
+            ((share * FUZZY_SCORE) as u32, 0..word.chars.len())
+        };
+
+        let bonus = if field.is_listed() { LISTED_FIELD_BONUS } else { 0 };
+        let range = word.offsets[chars.start]..word.offsets[chars.end];
+
+        Some((score + bonus, Highlight { field, range }))
+    }
+}
+
+/// Returns the text of the search index of a lok: the words of its fields,
+/// separated by a space and with a space before the first and after the last word.
+/// Every trigram a search word shares with a word of the lok is also a trigram of this text.
+pub fn index_text(fields: &[(SearchField, String)]) -> String {
+    let words: Vec<String> = fields.iter()
+        .flat_map(|(_, text)| split_words(text))
+        .map(|word| word.chars.into_iter().collect())
+        .collect();
+
+    format!(" {} ", words.join(" "))
+}
+
+/// Returns the trigrams of a word, the word is surrounded by a space, so its start and end have trigrams of their own.
+pub fn word_trigrams(word: &str) -> Vec<String> {
+    let mut trigrams: Vec<String> = trigrams(&word.chars().collect::<Vec<char>>()).into_iter()
+        .map(|trigram| trigram.iter().collect())
+        .collect();
+    trigrams.sort_unstable();
+
+    trigrams
+}
+
+fn trigrams(chars: &[char]) -> HashSet<[char; 3]> {
+    let padded: Vec<char> = std::iter::once(' ')
+        .chain(chars.iter().copied())
+        .chain(std::iter::once(' '))
+        .collect();
+
+    padded.windows(3).map(|window| [window[0], window[1], window[2]]).collect()
+}
+
+fn find_chars(chars: &[char], part: &[char]) -> Option<usize> {
+    chars.windows(part.len()).position(|window| window == part)
+}
+
+fn split_words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current = Word { chars: Vec::new(), offsets: Vec::new() };
+
+    for (offset, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            current.chars.push(fold(c));
+            current.offsets.push(offset);
+        } else if !current.chars.is_empty() {
+            current.offsets.push(offset);
+            words.push(std::mem::replace(&mut current, Word { chars: Vec::new(), offsets: Vec::new() }));
+        }
+    }
+
+    if !current.chars.is_empty() {
+        current.offsets.push(text.len());
+        words.push(current);
+    }
+
+    words
+}
+
+/// Returns the lowercase char without diacritics, every char stays a single char.
+fn fold(c: char) -> char {
+    let lowercase = c.to_lowercase().next().unwrap_or(c);
+
+    match lowercase {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use crate::app::backend::database::search::{index_text, Search, SearchField};
+
+    fn fields() -> Vec<(SearchField, String)> {
+        vec![
+            (SearchField::Address, "218".to_string()),
+            (SearchField::Name, "BR 218 Märklin-Edition".to_string()),
+            (SearchField::Producer, "Roco".to_string()),
+            (SearchField::Management, "ÖBB".to_string()),
+        ]
+    }
+
+    fn score(search: &str) -> Option<u32> {
+        Search::new(search).find(&fields()).map(|search_match| search_match.score)
+    }
+
+    #[test]
+    fn every_word_has_to_be_found() {
+        assert!(score("roco").is_some());
+        assert!(score("oebb").is_none());
+        assert!(score("obb roco br").is_some());
+        assert!(score("br dampf").is_none());
+        assert_eq!(score("--"), Some(0));
+    }
+
+    #[test]
+    fn typos_are_tolerated() {
+        assert!(score("rocco").is_some());
+        assert!(score("marklim").is_some());
+        assert!(score("rxxo").is_none());
+
+        // exact matches rank above words with typos, listed fields above the others
+        assert!(score("marklin") > score("marklim"));
+        assert!(score("218") > score("roco"));
+    }
+
+    #[test]
+    fn highlights_point_into_the_fields() {
+        let search_match = Search::new("märk 18").find(&fields()).unwrap();
+        let name = &fields()[1].1;
+
+        assert_eq!(search_match.highlights.len(), 2);
+        assert_eq!(&name[search_match.highlights[0].range.clone()], "Märk");
+        assert_eq!(search_match.highlights[1].field, SearchField::Address);
+        assert_eq!(search_match.highlights[1].range, 1..3);
+    }
+
+    #[test]
+    fn phrases_are_found_as_whole_words() {
+        let search_match = Search::new("br 218").find_phrase(&fields()).unwrap();
+
+        assert_eq!(search_match.highlights[0].field, SearchField::Name);
+        assert_eq!(search_match.highlights[0].range, 0..6);
+        assert!(Search::new("r 218").find_phrase(&fields()).is_none());
+        assert!(Search::new("218 br").find_phrase(&fields()).is_none());
+    }
+
+    #[test]
+    fn index_text_contains_every_word() {
+        assert_eq!(index_text(&fields()), " 218 br 218 marklin edition roco obb ");
+        assert_eq!(Search::new(" Köf-II ").words(), vec!["kof".to_string(), "ii".to_string()]);
+    }
+}
@@ -0,0 +1,354 @@
+use crate::app::backend::database::preview_lok::PreviewLok;
+use crate::app::backend::database::search::{Highlight, Search, SearchField, SearchMatch};
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+
+/// The qualifiers a term can start with, they are followed by a colon and the value.
+const QUALIFIERS: [(&str, Qualifier); 9] = [
+    ("addr", Qualifier::Address),
+    ("address", Qualifier::Address),
+    ("name", Qualifier::Field(SearchField::Name)),
+    ("lm", Qualifier::Field(SearchField::LokmausName)),
+    ("lokmaus", Qualifier::Field(SearchField::LokmausName)),
+    ("producer", Qualifier::Field(SearchField::Producer)),
+    ("management", Qualifier::Field(SearchField::Management)),
+    ("digital", Qualifier::Digital),
+    ("decoder", Qualifier::Digital),
+];
+
+const YES: [&str; 4] = ["yes", "ja", "true", "1"];
+const NO: [&str; 4] = ["no", "nein", "false", "0"];
+
+#[derive(Clone, Copy)]
+enum Qualifier {
+    Address,
+    Digital,
+    Field(SearchField),
+}
+
+/// A search typed into the search input, e.g. ```producer:roco digital:yes addr:100..200 management:"ÖBB"```.
+/// It consists of terms separated by whitespace, a lok has to match every term:
+/// - ```word``` is searched in every field like a plain search, typos are tolerated
+/// - ```"some words"``` have to follow each other in a field
+/// - ```field:value``` restricts a word or a quoted phrase to a field, other words with a colon are searched as text
+/// - ```addr:218```, ```addr:100..200```, ```addr:..99``` and ```addr:1000..``` select addresses
+/// - ```digital:yes``` and ```digital:no``` select loks with or without decoder
+/// - ```-term``` excludes the loks that match the term
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchQuery {
+    pub terms: Vec<SearchTerm>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchTerm {
+    /// Excludes the loks that match the condition.
+    pub negated: bool,
+    pub condition: SearchCondition,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SearchCondition {
+    /// The words are found in the field, or in any field without one, typos are tolerated.
+    Words(Option<SearchField>, Search),
+    /// The words follow each other in the field, or in any field without one.
+    Phrase(Option<SearchField>, Search),
+    /// Loks without address are never within the range.
+    Address(RangeInclusive<i32>),
+    /// Whether the lok has a decoder.
+    Digital(bool),
+}
+
+/// Describes why a search could not be parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchQueryError {
+    /// A qualifier, a negation or the quotes of a phrase contain no value.
+    MissingValue(String),
+    /// The value of ```addr``` is neither an address nor a range of addresses.
+    InvalidAddress(String),
+    /// The value of ```digital``` is neither yes nor no.
+    InvalidDigital(String),
+    /// A quoted phrase is not closed.
+    UnclosedQuote,
+}
+
+/// The value of a term, as it was typed.
+enum Value {
+    Bare(String),
+    Quoted(String),
+}
+
+impl Display for SearchQueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchQueryError::MissingValue(term) => write!(f, "Missing value after {}", term),
+            SearchQueryError::InvalidAddress(value) => write!(f, "Invalid address: {}", value),
+            SearchQueryError::InvalidDigital(value) => write!(f, "Invalid value for digital: {}", value),
+            SearchQueryError::UnclosedQuote => write!(f, "Unclosed quote"),
+        }
+    }
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Result<Self, SearchQueryError> {
+        let mut chars = input.chars().peekable();
+        let mut terms = Vec::new();
+
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            if chars.peek().is_none() {
+                break;
+            }
+
+            let negated = chars.next_if_eq(&'-').is_some();
+
+            if chars.peek() == Some(&'"') {
+                let condition = SearchQuery::text_condition(None, read_value(&mut chars)?, negated, "\"\"")?;
+                terms.push(SearchTerm { negated, condition });
+                continue;
+            }
+
+            // a known field followed by a colon is a qualifier, other colons belong to the word like in "br:218"
+            let mut word = String::new();
+            let mut qualifier = None;
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                if c == ':' {
+                    if let Some(found) = find_qualifier(&word) {
+                        qualifier = Some(found);
+                        break;
+                    }
+                }
+                word.push(c);
+            }
+
+            let condition = match qualifier {
+                Some(found) => SearchQuery::qualified_condition(&word, found, read_value(&mut chars)?, negated)?,
+                None => SearchQuery::text_condition(None, Value::Bare(word), negated, "-")?,
+            };
+            terms.push(SearchTerm { negated, condition });
+        }
+
+        Ok(SearchQuery { terms })
+    }
+
+    /// Returns true, if the query is plain text, that is searched like without a query language.
+    pub fn is_plain(&self) -> bool {
+        self.terms.iter().all(|term| !term.negated && matches!(term.condition, SearchCondition::Words(None, _)))
+    }
+
+    /// Matches a lok against every term, ```None``` if a term does not fit.
+    /// The score and highlights are the ones of the terms that are not negated.
+    pub fn find(&self, preview: &PreviewLok) -> Option<SearchMatch> {
+        let fields = preview.search_fields();
+        let mut search_match = SearchMatch::default();
+
+        for term in &self.terms {
+            match (term.condition.find(preview, &fields), term.negated) {
+                (Some(found), false) => {
+                    search_match.score += found.score;
+                    search_match.highlights.extend(found.highlights);
+                }
+                (None, true) => {}
+                _ => return None,
+            }
+        }
+
+        Some(search_match)
+    }
+
+    fn qualified_condition(name: &str, qualifier: Qualifier, value: Value, negated: bool) -> Result<SearchCondition, SearchQueryError> {
+        let text = match &value {
+            Value::Bare(text) | Value::Quoted(text) => text.trim().to_string(),
+        };
+
+        if text.is_empty() {
+            return Err(SearchQueryError::MissingValue(format!("{}:", name)));
+        }
+
+        match qualifier {
+            Qualifier::Address => parse_address_range(&text)
+                .map(SearchCondition::Address)
+                .ok_or(SearchQueryError::InvalidAddress(text)),
+            Qualifier::Digital => {
+                let lowercase = text.to_lowercase();
+
+                if YES.contains(&lowercase.as_str()) {
+                    Ok(SearchCondition::Digital(true))
+                } else if NO.contains(&lowercase.as_str()) {
+                    Ok(SearchCondition::Digital(false))
+                } else {
+                    Err(SearchQueryError::InvalidDigital(text))
+                }
+            }
+            Qualifier::Field(field) => SearchQuery::text_condition(Some(field), value, negated, &format!("{}:", name)),
+        }
+    }
+
+    /// Returns the condition of words or a phrase.
+    /// Only plain words may contain no letters or digits, they match every lok like in a plain search.
+    fn text_condition(field: Option<SearchField>, value: Value, negated: bool, term: &str) -> Result<SearchCondition, SearchQueryError> {
+        let is_plain = field.is_none() && !negated;
+
+        let condition = match value {
+            Value::Bare(text) => SearchCondition::Words(field, Search::new(&text)),
+            Value::Quoted(text) => SearchCondition::Phrase(field, Search::new(&text)),
+        };
+
+        match &condition {
+            SearchCondition::Words(_, search) if search.words().is_empty() && !is_plain => Err(SearchQueryError::MissingValue(term.to_string())),
+            SearchCondition::Phrase(_, search) if search.words().is_empty() => Err(SearchQueryError::MissingValue(term.to_string())),
+            _ => Ok(condition),
+        }
+    }
+}
+
+impl SearchCondition {
+    fn find(&self, preview: &PreviewLok, fields: &[(SearchField, String)]) -> Option<SearchMatch> {
+        match self {
+            SearchCondition::Words(field, search) => search.find(&restricted(fields, *field)),
+            SearchCondition::Phrase(field, search) => search.find_phrase(&restricted(fields, *field)),
+            SearchCondition::Address(range) => {
+                let address = preview.get_address().filter(|address| range.contains(address))?;
+
+                Some(SearchMatch {
+                    score: 0,
+                    highlights: vec![Highlight { field: SearchField::Address, range: 0..address.to_string().len() }],
+                })
+            }
+            SearchCondition::Digital(has_decoder) => (preview.get_has_decoder() == *has_decoder).then(SearchMatch::default),
+        }
+    }
+}
+
+/// Returns the fields a term looks into.
+fn restricted(fields: &[(SearchField, String)], field: Option<SearchField>) -> Vec<(SearchField, String)> {
+    fields.iter()
+        .filter(|(other, _)| field.is_none_or(|field| field == *other))
+        .cloned()
+        .collect()
+}
+
+/// Reads a quoted phrase or a value up to the next whitespace.
+fn read_value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Value, SearchQueryError> {
+    if chars.next_if_eq(&'"').is_some() {
+        let mut phrase = String::new();
+
+        loop {
+            match chars.next() {
+                Some('"') => return Ok(Value::Quoted(phrase)),
+                Some(c) => phrase.push(c),
+                None => return Err(SearchQueryError::UnclosedQuote),
+            }
+        }
+    }
+
+    let mut value = String::new();
+    while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+        value.push(c);
+    }
+
+    Ok(Value::Bare(value))
+}
+
+/// Parses an address or a range of addresses, either end of a range may be left open.
+/// Returns the qualifier with the given name, case is ignored.
+fn find_qualifier(name: &str) -> Option<Qualifier> {
+    QUALIFIERS.iter()
+        .find(|(qualifier_name, _)| qualifier_name.eq_ignore_ascii_case(name))
+        .map(|(_, qualifier)| *qualifier)
+}
+
+fn parse_address_range(text: &str) -> Option<RangeInclusive<i32>> {
+    let parse_end = |end: &str, open: i32| -> Option<i32> {
+        if end.is_empty() { Some(open) } else { end.parse().ok() }
+    };
+
+    let range = match text.split_once("..") {
+        Some(("", "")) => return None,
+        Some((start, end)) => parse_end(start, i32::MIN)?..=parse_end(end, i32::MAX)?,
+        None => {
+            let address = text.parse().ok()?;
+            address..=address
+        }
+    };
+
+    (!range.is_empty()).then_some(range)
+}
+
+#[cfg(test)]
+mod search_query_tests {
+    use crate::app::backend::database::preview_lok::PreviewLok;
+    use crate::app::backend::database::search::{Search, SearchField};
+    use crate::app::backend::database::search_query::{SearchCondition, SearchQuery, SearchQueryError};
+
+    fn previews() -> Vec<PreviewLok> {
+        vec![
+            PreviewLok::new(1, Some(118), Some("BR 218".to_string()), None)
+                .with_details(Some("Roco".to_string()), Some("ÖBB".to_string()), true),
+            PreviewLok::new(2, Some(150), Some("V 100".to_string()), None)
+                .with_details(Some("Märklin".to_string()), Some("DB".to_string()), true),
+            PreviewLok::new(3, None, Some("Köf II".to_string()), None)
+                .with_details(Some("Roco".to_string()), Some("DB".to_string()), false),
+        ]
+    }
+
+    fn ids(input: &str) -> Vec<u32> {
+        let query = SearchQuery::parse(input).unwrap();
+
+        previews().into_iter()
+            .filter(|preview| query.find(preview).is_some())
+            .map(|preview| preview.get_id())
+            .collect()
+    }
+
+    #[test]
+    fn terms_are_parsed() {
+        let query = SearchQuery::parse(r#"producer:roco  -digital:no addr:100..200 management:"ÖBB" 12:30"#).unwrap();
+
+        assert_eq!(query.terms.len(), 5);
+        assert!(matches!(query.terms[0].condition, SearchCondition::Words(Some(SearchField::Producer), _)));
+        assert!(query.terms[1].negated);
+        assert_eq!(query.terms[1].condition, SearchCondition::Digital(false));
+        assert_eq!(query.terms[2].condition, SearchCondition::Address(100..=200));
+        assert!(matches!(query.terms[3].condition, SearchCondition::Phrase(Some(SearchField::Management), _)));
+        assert!(matches!(query.terms[4].condition, SearchCondition::Words(None, _)));
+
+        assert!(SearchQuery::parse("br 218 100%").unwrap().is_plain());
+        assert!(SearchQuery::parse("colour:red").unwrap().is_plain());
+        assert!(!SearchQuery::parse("br -218").unwrap().is_plain());
+    }
+
+    #[test]
+    fn unknown_fields_are_searched_as_text() {
+        let query = SearchQuery::parse("br:218 Re:4").unwrap();
+
+        assert!(query.is_plain());
+        assert_eq!(query.terms[0].condition, SearchCondition::Words(None, Search::new("br 218")));
+        assert_eq!(query.terms[1].condition, SearchCondition::Words(None, Search::new("re 4")));
+        assert_eq!(ids("br:218"), vec![1]);
+    }
+
+    #[test]
+    fn invalid_syntax_is_reported() {
+        assert_eq!(SearchQuery::parse("producer: roco"), Err(SearchQueryError::MissingValue("producer:".to_string())));
+        assert_eq!(SearchQuery::parse("br -"), Err(SearchQueryError::MissingValue("-".to_string())));
+        assert_eq!(SearchQuery::parse("addr:200..100"), Err(SearchQueryError::InvalidAddress("200..100".to_string())));
+        assert_eq!(SearchQuery::parse("addr:x"), Err(SearchQueryError::InvalidAddress("x".to_string())));
+        assert_eq!(SearchQuery::parse("digital:maybe"), Err(SearchQueryError::InvalidDigital("maybe".to_string())));
+        assert_eq!(SearchQuery::parse(r#"name:"BR 2"#), Err(SearchQueryError::UnclosedQuote));
+    }
+
+    #[test]
+    fn loks_match_every_term() {
+        assert_eq!(ids("producer:roco"), vec![1, 3]);
+        assert_eq!(ids("producer:roco digital:yes"), vec![1]);
+        assert_eq!(ids("addr:100..200 -management:obb"), vec![2]);
+        assert_eq!(ids("addr:..120"), vec![1]);
+        assert_eq!(ids("-addr:100.."), vec![3]);
+        assert_eq!(ids(r#"management:"ÖBB""#), vec![1]);
+        assert_eq!(ids(r#""br 218""#), vec![1]);
+        assert_eq!(ids(r#"name:"218 br""#), Vec::<u32>::new());
+        assert_eq!(ids("name:rocco"), Vec::<u32>::new());
+        assert_eq!(ids("rocco -köf"), vec![1]);
+    }
+}
@@ -1,6 +1,14 @@
 use crate::database::{Database, DatabaseError};
 use sqlx::migrate::MigrateDatabase;
 use sqlx::{Pool, Sqlite, SqlitePool};
+use sqlx::migrate::Migrator;
+use std::path::{Path, PathBuf};
+
+/// The schema migrations, embedded at compile time.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Files SQLite keeps next to the database file while it is in WAL mode.
+pub const COMPANION_FILE_SUFFIXES: [&str; 2] = ["-wal", "-shm"];
 
 /// SQLite implementation for the database.
 pub struct SQLiteDB {
@@ -9,26 +17,197 @@ pub struct SQLiteDB {
 }
 
 impl SQLiteDB {
-    async fn migrate(connection: Pool<Sqlite>) -> Result<(), DatabaseError> {
-        // sqlite migrations
-        let crate_dir = std::env::current_dir().unwrap();
-        let migrations = std::path::Path::new(&crate_dir).join("../../../../migrations");
-
-        let migration_results = sqlx::migrate::Migrator::new(migrations)
-            .await
-            .unwrap()
-            .run(&connection)
-            .await;
-
-        match migration_results {
+    /// Returns the path of the database file a SQLite url points to.
+    pub fn path_from_url(db_url: &str) -> PathBuf {
+        let path = db_url
+            .strip_prefix("sqlite://")
+            .or_else(|| db_url.strip_prefix("sqlite:"))
+            .unwrap_or(db_url);
+
+        PathBuf::from(path.split('?').next().unwrap_or(path))
+    }
+
+    /// Returns the SQLite url for a database file.
+    pub fn url_from_path(path: &Path) -> String {
+        format!("sqlite://{}", path.display())
+    }
+
+    /// Renames the database file and its WAL files, so that a fresh database can be created in its place.
+    /// The old files are kept next to it with a timestamp.
+    /// Works for every single file collection, for files without WAL files only the file itself is renamed.
+    /// Returns the new path of the database file, or ```None``` if there was no file.
+    pub fn move_aside(path: &Path) -> Result<Option<PathBuf>, DatabaseError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let datetime = chrono::Local::now().format("%Y-%m-%d_%H%M%S").to_string();
+        let moved_path = PathBuf::from(format!("{}.broken-{}", path.display(), datetime));
+
+        std::fs::rename(path, &moved_path).map_err(|err| DatabaseError::Io(err.to_string()))?;
+
+        for suffix in COMPANION_FILE_SUFFIXES {
+            let companion = PathBuf::from(format!("{}{}", path.display(), suffix));
+
+            if companion.exists() {
+                std::fs::rename(&companion, format!("{}{}", moved_path.display(), suffix))
+                    .map_err(|err| DatabaseError::Io(err.to_string()))?;
+            }
+        }
+
+        Ok(Some(moved_path))
+    }
+
+    /// Undoes ```move_aside```: renames the moved database file and its WAL files back to path.
+    pub fn move_back(moved_path: &Path, path: &Path) -> Result<(), DatabaseError> {
+        std::fs::rename(moved_path, path).map_err(|err| DatabaseError::Io(err.to_string()))?;
+
+        for suffix in COMPANION_FILE_SUFFIXES {
+            let companion = PathBuf::from(format!("{}{}", moved_path.display(), suffix));
+
+            if companion.exists() {
+                std::fs::rename(&companion, format!("{}{}", path.display(), suffix))
+                    .map_err(|err| DatabaseError::Io(err.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the database file at path with a copy of the backup.
+    /// The current database file is moved aside beforehand.
+    pub fn restore_from(backup: &Path, path: &Path) -> Result<(), DatabaseError> {
+        SQLiteDB::move_aside(path)?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| DatabaseError::Io(err.to_string()))?;
+        }
+
+        std::fs::copy(backup, path).map_err(|err| DatabaseError::Io(err.to_string()))?;
+
+        Ok(())
+    }
+
+    /// Applies every embedded migration the database is missing.
+    /// Databases that were migrated by a newer build are refused, so an older build cannot corrupt them.
+    async fn migrate(connection: &Pool<Sqlite>) -> Result<(), DatabaseError> {
+        if let Some(version) = SQLiteDB::schema_version(connection).await? {
+            if version > SQLiteDB::supported_schema_version() {
+                return Err(DatabaseError::NewerSchema(version));
+            }
+        }
+
+        match MIGRATOR.run(connection).await {
             Ok(_) => {
                 Ok(())
             }
             Err(err) => {
-                Err(DatabaseError::SpecificError(format!("Error running migrations: {}", err)))
+                Err(DatabaseError::Migration(format!("Error running migrations: {}", err)))
             }
         }
     }
+
+    /// Returns the version of the latest migration applied to the database.
+    /// Returns ```None``` for a database that was never migrated.
+    pub async fn schema_version(connection: &Pool<Sqlite>) -> Result<Option<i64>, DatabaseError> {
+        let migrations_table_exists: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')"
+        )
+            .fetch_one(connection)
+            .await?;
+
+        if !migrations_table_exists {
+            return Ok(None);
+        }
+
+        let version = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations WHERE success = true")
+            .fetch_one(connection)
+            .await?;
+
+        Ok(version)
+    }
+
+    /// Returns the version of the latest migration embedded in this build.
+    pub fn supported_schema_version() -> i64 {
+        MIGRATOR.iter().map(|migration| migration.version).max().unwrap_or(0)
+    }
+
+    /// Writes a consistent copy of the database to target, while the database stays in use.
+    /// The WAL is checkpointed beforehand, so the database file itself is up to date as well.
+    pub async fn snapshot(db_url: &str, target: &Path) -> Result<(), DatabaseError> {
+        let connection = SqlitePool::connect(db_url).await?;
+
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(&connection)
+            .await?;
+
+        sqlx::query("VACUUM INTO ?")
+            .bind(target.display().to_string())
+            .execute(&connection)
+            .await?;
+
+        connection.close().await;
+
+        Ok(())
+    }
+
+    /// Runs SQLite's integrity check and returns the problems it found.
+    /// An empty list means the database is fine.
+    pub async fn integrity_check(connection: &Pool<Sqlite>) -> Result<Vec<String>, DatabaseError> {
+        let messages: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+            .fetch_all(connection)
+            .await?;
+
+        Ok(messages.into_iter().filter(|message| message != "ok").collect())
+    }
+
+    /// Runs SQLite's foreign key check and describes every violation it found.
+    pub async fn foreign_key_check(connection: &Pool<Sqlite>) -> Result<Vec<String>, DatabaseError> {
+        let violations: Vec<(String, Option<i64>, String)> = sqlx::query_as("SELECT \"table\", rowid, parent FROM pragma_foreign_key_check")
+            .fetch_all(connection)
+            .await?;
+
+        Ok(violations.into_iter()
+            .map(|(table, rowid, parent)| match rowid {
+                Some(rowid) => format!("{} {} -> {}", table, rowid, parent),
+                None => format!("{} -> {}", table, parent),
+            })
+            .collect())
+    }
+
+    /// Rebuilds every index, which repairs indexes that do not match their table anymore.
+    /// The search index is rebuilt from the search texts of the loks.
+    pub async fn reindex(connection: &Pool<Sqlite>) -> Result<(), DatabaseError> {
+        sqlx::query("REINDEX").execute(connection).await?;
+        sqlx::query("INSERT INTO lok_search (lok_search) VALUES ('rebuild')").execute(connection).await?;
+
+        Ok(())
+    }
+
+    /// Rebuilds the database file, so that it does not use more space than needed.
+    pub async fn vacuum(connection: &Pool<Sqlite>) -> Result<(), DatabaseError> {
+        sqlx::query("VACUUM").execute(connection).await?;
+
+        Ok(())
+    }
+
+    /// Returns the stored path of every image a lok refers to.
+    pub async fn image_paths(connection: &Pool<Sqlite>) -> Result<Vec<String>, DatabaseError> {
+        let paths = sqlx::query_scalar("SELECT path FROM lok_images UNION SELECT original_path FROM lok_images WHERE original_path IS NOT NULL ORDER BY 1")
+            .fetch_all(connection)
+            .await?;
+
+        Ok(paths)
+    }
+
+    pub fn connection(&self) -> &Pool<Sqlite> {
+        &self.connection
+    }
+
+    /// Closes every connection, so that the database file can be replaced.
+    pub async fn close(self) {
+        self.connection.close().await;
+    }
 }
 
 impl Database for SQLiteDB {
@@ -37,34 +216,21 @@ impl Database for SQLiteDB {
     async fn build(db_url: &str) -> Result<Self, DatabaseError> {
         if !Sqlite::database_exists(db_url).await.unwrap_or(false) {
             println!("Creating database {}", db_url);
-            match Sqlite::create_database(db_url).await {
-                Ok(_) => {
-                    let connection = Pool::connect(db_url).await;
-
-                    if connection.is_ok() {
-                        let connection = connection.unwrap();
-                        SQLiteDB::migrate(connection.clone()).await?;
-
-                        Ok(SQLiteDB {
-                            connection: connection.clone(),
-                            db_url: db_url.to_owned(),
-                        })
-                    } else {
-                        Err(DatabaseError::ConnectionError)
-                    }
-                }
-                Err(err) => {
-                    Err(DatabaseError::GeneralError(format!("Failed to create SQLite database: {}", err)))
-                }
-            }
+
+            Sqlite::create_database(db_url).await
+                .map_err(|err| DatabaseError::GeneralError(format!("Failed to create SQLite database: {}", err)))?;
         } else {
             println!("SQLite database already exists");
-
-            Ok(SQLiteDB {
-                connection: Pool::connect_lazy(db_url).unwrap(),
-                db_url: db_url.to_owned(),
-            })
         }
+
+        let connection = Pool::connect(db_url).await?;
+
+        SQLiteDB::migrate(&connection).await?;
+
+        Ok(SQLiteDB {
+            connection,
+            db_url: db_url.to_owned(),
+        })
     }
 
     async fn connect(&mut self) -> Result<Pool<Sqlite>, DatabaseError>
@@ -86,8 +252,13 @@ mod sqlite_db_tests {
     use super::*;
     use crate::app::backend::test;
     use async_std::task;
+    use sqlx::migrate::Migrate;
+    use sqlx::{Connection, SqliteConnection};
     use std::time::Duration;
 
+    const NULL_VALUES_MIGRATION: i64 = 20261018120000;
+    const IMAGE_GALLERY_MIGRATION: i64 = 20261018140000;
+
     #[test]
     fn build_db_test() {
         test::util::remove_test_db(0);
@@ -105,4 +276,167 @@ mod sqlite_db_tests {
 
         assert!(result.is_ok())
     }
+
+    #[test]
+    fn path_and_url_conversion_works() {
+        let path = SQLiteDB::path_from_url("sqlite://data/lokbuch.db");
+
+        assert_eq!(path, PathBuf::from("data/lokbuch.db"));
+        assert_eq!(SQLiteDB::url_from_path(&path), "sqlite://data/lokbuch.db");
+    }
+
+    #[test]
+    fn move_aside_and_restore_works() {
+        let _db = test::util::build_db(20);
+        let path = SQLiteDB::path_from_url("sqlite://test/test20.db");
+
+        let moved = SQLiteDB::move_aside(&path).unwrap().unwrap();
+
+        assert!(!path.exists());
+        assert!(moved.exists());
+
+        SQLiteDB::restore_from(&moved, &path).unwrap();
+
+        assert!(path.exists());
+
+        std::fs::remove_file(moved).ok();
+    }
+
+    #[test]
+    fn migrations_are_recorded() {
+        let db = test::util::build_db(21);
+
+        let version = task::block_on(SQLiteDB::schema_version(&db.connection)).unwrap();
+
+        assert_eq!(version, Some(SQLiteDB::supported_schema_version()));
+    }
+
+    #[test]
+    fn existing_db_is_migrated_again() {
+        let _db = test::util::build_db(22);
+
+        let result = task::block_on(SQLiteDB::build("sqlite://test/test22.db"));
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn newer_schema_is_refused() {
+        let db = test::util::build_db(23);
+        let newer_version = SQLiteDB::supported_schema_version() + 1;
+
+        task::block_on(
+            sqlx::query("INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) VALUES (?, 'future', true, x'00', 0)")
+                .bind(newer_version)
+                .execute(&db.connection)
+        ).unwrap();
+
+        let result = task::block_on(SQLiteDB::build("sqlite://test/test23.db"));
+
+        assert!(matches!(result, Err(DatabaseError::NewerSchema(version)) if version == newer_version));
+    }
+
+    #[test]
+    fn sentinel_values_are_migrated_to_null() {
+        test::util::remove_test_db(25);
+        std::thread::sleep(Duration::from_millis(test::util::AFTER_DELETE_WAIT_TIME));
+        let db_url = "sqlite://test/test25.db";
+
+        // builds a database with the schema before missing values were stored as NULL
+        task::block_on(async {
+            Sqlite::create_database(db_url).await.unwrap();
+
+            let mut connection = SqliteConnection::connect(db_url).await.unwrap();
+            connection.ensure_migrations_table().await.unwrap();
+
+            for migration in MIGRATOR.iter().filter(|migration| migration.version < NULL_VALUES_MIGRATION) {
+                connection.apply(migration).await.unwrap();
+            }
+
+            sqlx::query("INSERT INTO loks (name, address, lokmaus_name, producer, management, has_decoder, image_path) VALUES ('OLD', -1, '', '', '', false, '')")
+                .execute(&mut connection)
+                .await
+                .unwrap();
+        });
+
+        let db = task::block_on(SQLiteDB::build(db_url)).unwrap();
+
+        let (address, lokmaus_name): (Option<i32>, Option<String>) = task::block_on(
+            sqlx::query_as("SELECT address, lokmaus_name FROM loks WHERE name = 'OLD'")
+                .fetch_one(&db.connection)
+        ).unwrap();
+        let images: i64 = task::block_on(
+            sqlx::query_scalar("SELECT COUNT(*) FROM lok_images")
+                .fetch_one(&db.connection)
+        ).unwrap();
+
+        assert_eq!(address, None);
+        assert_eq!(lokmaus_name, None);
+        // an empty image path was no image at all
+        assert_eq!(images, 0);
+    }
+
+    #[test]
+    fn image_paths_are_moved_to_the_gallery() {
+        test::util::remove_test_db(29);
+        std::thread::sleep(Duration::from_millis(test::util::AFTER_DELETE_WAIT_TIME));
+        let db_url = "sqlite://test/test29.db";
+
+        // builds a database with the schema before a lok could have more than one image
+        task::block_on(async {
+            Sqlite::create_database(db_url).await.unwrap();
+
+            let mut connection = SqliteConnection::connect(db_url).await.unwrap();
+            connection.ensure_migrations_table().await.unwrap();
+
+            for migration in MIGRATOR.iter().filter(|migration| migration.version < IMAGE_GALLERY_MIGRATION) {
+                connection.apply(migration).await.unwrap();
+            }
+
+            sqlx::query("INSERT INTO loks (name, has_decoder, image_path) VALUES ('OLD', false, 'images/old.png')")
+                .execute(&mut connection)
+                .await
+                .unwrap();
+        });
+
+        let db = task::block_on(SQLiteDB::build(db_url)).unwrap();
+
+        let (path, position, is_primary): (String, i64, bool) = task::block_on(
+            sqlx::query_as("SELECT path, position, is_primary FROM lok_images")
+                .fetch_one(&db.connection)
+        ).unwrap();
+        let ref_count: i64 = task::block_on(
+            sqlx::query_scalar("SELECT ref_count FROM images WHERE path = 'images/old.png'")
+                .fetch_one(&db.connection)
+        ).unwrap();
+
+        assert_eq!((path.as_str(), position, is_primary), ("images/old.png", 0, true));
+        assert_eq!(ref_count, 1);
+    }
+
+    #[test]
+    fn snapshot_is_consistent() {
+        let db = test::util::build_db(27);
+        let target = PathBuf::from("test/test27-snapshot.db");
+        std::fs::remove_file(&target).ok();
+
+        task::block_on(
+            sqlx::query("INSERT INTO loks (id, name, has_decoder) VALUES (1, 'SNAP', false)")
+                .execute(&db.connection)
+        ).unwrap();
+        task::block_on(
+            sqlx::query("INSERT INTO lok_images (lok_id, position, path) VALUES (1, 0, 'images/snap.png')")
+                .execute(&db.connection)
+        ).unwrap();
+
+        task::block_on(SQLiteDB::snapshot("sqlite://test/test27.db", &target)).unwrap();
+
+        let snapshot = task::block_on(SQLiteDB::build("sqlite://test/test27-snapshot.db")).unwrap();
+
+        assert_eq!(task::block_on(SQLiteDB::integrity_check(snapshot.connection())).unwrap(), Vec::<String>::new());
+        assert_eq!(task::block_on(SQLiteDB::image_paths(snapshot.connection())).unwrap(), vec!["images/snap.png".to_string()]);
+
+        task::block_on(snapshot.close());
+        std::fs::remove_file(&target).ok();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,324 @@
+use crate::app::backend::database::lok::{Lok, LokImage};
+use crate::app::backend::database::preview_lok::PreviewLok;
+use crate::app::backend::database::preview_query::{PreviewPage, PreviewQuery};
+use crate::app::backend::database::DatabaseError;
+use crate::app::backend::Backend;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Url scheme that selects the JSON backend.
+pub const JSON_URL_SCHEME: &str = "json://";
+
+/// Version of the file format, is increased on incompatible changes.
+/// Version 2 replaced the single image_path of a lok with a list of images.
+const FORMAT_VERSION: u32 = 2;
+
+/// Backend implementation that stores the collection as a single human-readable JSON document.
+/// Every change rewrites the whole file atomically.
+#[derive(Clone, Debug, Default)]
+pub struct JsonBackend {
+    path: PathBuf,
+    collection: Arc<Mutex<JsonCollection>>,
+}
+
+/// The content of a JSON collection file.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct JsonCollection {
+    format_version: u32,
+    last_id: u32,
+    loks: Vec<JsonLok>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct JsonLok {
+    id: u32,
+    #[serde(flatten)]
+    lok: Lok,
+    /// The only image of a lok in files of version 1, it is moved into the images when read.
+    #[serde(default, skip_serializing)]
+    image_path: Option<String>,
+}
+
+impl Default for JsonCollection {
+    fn default() -> Self {
+        JsonCollection {
+            format_version: FORMAT_VERSION,
+            last_id: 0,
+            loks: Vec::new(),
+        }
+    }
+}
+
+impl JsonCollection {
+    fn find(&self, id: u32) -> Option<usize> {
+        self.loks.iter().position(|json_lok| json_lok.id == id)
+    }
+}
+
+impl JsonBackend {
+    /// Returns the path of the collection file a JSON url points to.
+    pub fn path_from_url(db_url: &str) -> PathBuf {
+        PathBuf::from(db_url.strip_prefix(JSON_URL_SCHEME).unwrap_or(db_url))
+    }
+
+    /// Returns the JSON url for a collection file.
+    pub fn url_from_path(path: &Path) -> String {
+        format!("{}{}", JSON_URL_SCHEME, path.display())
+    }
+
+    fn read(path: &Path) -> Result<JsonCollection, DatabaseError> {
+        let contents = std::fs::read_to_string(path).map_err(|err| DatabaseError::Io(err.to_string()))?;
+
+        let collection: JsonCollection = serde_json::from_str(&contents)
+            .map_err(|err| DatabaseError::GeneralError(format!("Invalid collection file: {}", err)))?;
+
+        if collection.format_version > FORMAT_VERSION {
+            return Err(DatabaseError::NewerSchema(collection.format_version as i64));
+        }
+
+        Ok(JsonBackend::upgrade(collection))
+    }
+
+    /// Upgrades a collection read from an older file, it is written in the current format with the next change.
+    fn upgrade(mut collection: JsonCollection) -> JsonCollection {
+        for json_lok in collection.loks.iter_mut() {
+            if let Some(image_path) = json_lok.image_path.take().filter(|image_path| !image_path.is_empty()) {
+                json_lok.lok.images.insert(0, LokImage { is_primary: true, ..LokImage::new(image_path) });
+            }
+        }
+
+        collection.format_version = FORMAT_VERSION;
+        collection
+    }
+
+    /// Writes the collection to a temporary file next to the target and renames it afterwards.
+    /// This way the file on disk is either the old or the new document, never a partly written one.
+    fn write(path: &Path, collection: &JsonCollection) -> Result<(), DatabaseError> {
+        let json = serde_json::to_string_pretty(collection)
+            .map_err(|err| DatabaseError::GeneralError(err.to_string()))?;
+
+        let temp_path = PathBuf::from(format!("{}.tmp", path.display()));
+
+        let write_temp = || -> std::io::Result<()> {
+            let mut file = std::fs::File::create(&temp_path)?;
+            file.write_all(json.as_bytes())?;
+            file.sync_all()
+        };
+
+        write_temp()
+            .and_then(|_| std::fs::rename(&temp_path, path))
+            .map_err(|err| {
+                std::fs::remove_file(&temp_path).ok();
+                DatabaseError::Io(err.to_string())
+            })
+    }
+
+    /// Applies a change to a copy of the collection and persists it.
+    /// The in-memory collection is only replaced, if the file was written successfully.
+    fn modify<T>(&self, change: impl FnOnce(&mut JsonCollection) -> Result<T, DatabaseError>) -> Result<T, DatabaseError> {
+        let mut collection = self.collection.lock()
+            .map_err(|_| DatabaseError::GeneralError("JSON collection is poisoned".to_string()))?;
+
+        let mut changed = collection.clone();
+        let result = change(&mut changed)?;
+
+        JsonBackend::write(&self.path, &changed)?;
+        *collection = changed;
+
+        Ok(result)
+    }
+
+    fn read_collection<T>(&self, read: impl FnOnce(&JsonCollection) -> T) -> Result<T, DatabaseError> {
+        let collection = self.collection.lock()
+            .map_err(|_| DatabaseError::GeneralError("JSON collection is poisoned".to_string()))?;
+
+        Ok(read(&collection))
+    }
+}
+
+impl Backend for JsonBackend {
+    async fn build(db_url: &str) -> Result<Self, DatabaseError> {
+        let path = JsonBackend::path_from_url(db_url);
+
+        let collection = if path.exists() {
+            JsonBackend::read(&path)?
+        } else {
+            println!("Creating JSON collection {}", path.display());
+
+            let collection = JsonCollection::default();
+            JsonBackend::write(&path, &collection)?;
+            collection
+        };
+
+        Ok(JsonBackend {
+            path,
+            collection: Arc::new(Mutex::new(collection)),
+        })
+    }
+
+    async fn insert(&self, lok: Lok) -> Result<u32, DatabaseError> {
+        self.modify(|collection| {
+            collection.last_id += 1;
+            let id = collection.last_id;
+
+            collection.loks.push(JsonLok { id, lok, image_path: None });
+
+            Ok(id)
+        })
+    }
+
+    async fn get(&self, id: u32) -> Result<Lok, DatabaseError> {
+        self.read_collection(|collection| {
+            collection.find(id).map(|index| collection.loks[index].lok.clone())
+        })?.ok_or(DatabaseError::NotFound)
+    }
+
+    async fn update(&self, id: u32, new_lok: &Lok) -> Result<(), DatabaseError> {
+        self.modify(|collection| {
+            let index = collection.find(id).ok_or(DatabaseError::NotFound)?;
+            collection.loks[index].lok = new_lok.clone();
+
+            Ok(())
+        })
+    }
+
+    async fn remove(&self, id: u32) -> Result<(), DatabaseError> {
+        self.modify(|collection| {
+            let index = collection.find(id).ok_or(DatabaseError::NotFound)?;
+            collection.loks.remove(index);
+
+            Ok(())
+        })
+    }
+
+    async fn get_all_previews(&self) -> Result<Vec<PreviewLok>, DatabaseError> {
+        self.read_collection(|collection| {
+            collection.loks.iter().map(|json_lok| json_lok.lok.as_preview_lok(json_lok.id)).collect()
+        })
+    }
+
+    async fn query_previews(&self, query: &PreviewQuery) -> Result<PreviewPage, DatabaseError> {
+        Ok(query.apply(self.get_all_previews().await?))
+    }
+
+    async fn count_image_references(&self, image_path: &str) -> Result<u32, DatabaseError> {
+        self.read_collection(|collection| {
+            collection.loks.iter().flat_map(|json_lok| &json_lok.lok.images).flat_map(LokImage::stored_paths).filter(|path| *path == image_path).count() as u32
+        })
+    }
+
+    /// Nothing to release, the file is only opened while it is read or written.
+    async fn close(&self) {}
+}
+
+#[cfg(test)]
+mod json_backend_tests {
+    use super::*;
+    use crate::app::backend::resource_manager::{LokChange, LokResourceManager};
+    use crate::app::backend::test;
+    use async_std::task;
+
+    fn build_backend(index: i32) -> (JsonBackend, String) {
+        test::util::remove_test_json(index);
+        let db_url = format!("json://test/test{}.json", index);
+
+        (task::block_on(JsonBackend::build(&db_url)).unwrap(), db_url)
+    }
+
+    #[test]
+    fn build_creates_file() {
+        let (_backend, db_url) = build_backend(1);
+
+        assert!(JsonBackend::path_from_url(&db_url).exists());
+    }
+
+    #[test]
+    fn loks_are_persisted() {
+        let (backend, db_url) = build_backend(2);
+
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+        let analogue_id = task::block_on(backend.insert(test::util::get_analogue_test_lok())).unwrap();
+        task::block_on(backend.update(id, &test::util::get_test_lok_2())).unwrap();
+
+        let reopened = task::block_on(JsonBackend::build(&db_url)).unwrap();
+
+        assert_eq!(task::block_on(reopened.get(id)), Ok(test::util::get_test_lok_2()));
+        assert_eq!(task::block_on(reopened.get(analogue_id)), Ok(test::util::get_analogue_test_lok()));
+    }
+
+    #[test]
+    fn remove_works() {
+        let (backend, _db_url) = build_backend(3);
+
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+
+        task::block_on(backend.remove(id)).unwrap();
+
+        assert_eq!(task::block_on(backend.get(id)), Err(DatabaseError::NotFound));
+        assert_eq!(task::block_on(backend.remove(id)), Err(DatabaseError::NotFound));
+    }
+
+    #[test]
+    fn no_temporary_file_is_left_behind() {
+        let (backend, db_url) = build_backend(4);
+
+        let _id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+
+        let path = JsonBackend::path_from_url(&db_url);
+
+        assert!(!PathBuf::from(format!("{}.tmp", path.display())).exists());
+    }
+
+    #[test]
+    fn invalid_file_is_refused() {
+        test::util::remove_test_json(5);
+        std::fs::create_dir_all("test").unwrap();
+        std::fs::write("test/test5.json", "not a collection").unwrap();
+
+        let result = task::block_on(JsonBackend::build("json://test/test5.json"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn lok_resource_manager_works() {
+        test::util::remove_test_json(6);
+
+        let mut lrm = task::block_on(LokResourceManager::<JsonBackend>::build("json://test/test6.json")).unwrap();
+
+        let change = task::block_on(lrm.handle().add_lok(test::util::get_test_lok_1())).unwrap();
+        let LokChange::Added(id, _) = change else {
+            panic!("adding a lok has to return LokChange::Added");
+        };
+        lrm.apply(change);
+
+        assert_eq!(task::block_on(lrm.get_lok(id)), Ok(test::util::get_test_lok_1()));
+        assert_eq!(lrm.number_of_loks(), 1);
+    }
+
+    #[test]
+    fn image_path_of_version_1_is_upgraded() {
+        test::util::remove_test_json(7);
+        std::fs::create_dir_all("test").unwrap();
+        std::fs::write("test/test7.json", r#"{
+            "format_version": 1,
+            "last_id": 1,
+            "loks": [{ "id": 1, "name": "OLD", "address": null, "lokmaus_name": null, "producer": null, "management": null, "has_decoder": false, "image_path": "images/old.png" }]
+        }"#).unwrap();
+
+        let backend = task::block_on(JsonBackend::build("json://test/test7.json")).unwrap();
+        let lok = task::block_on(backend.get(1)).unwrap();
+
+        assert_eq!(lok.images, vec![LokImage { is_primary: true, ..LokImage::new("images/old.png".to_string()) }]);
+        assert_eq!(task::block_on(backend.count_image_references("images/old.png")), Ok(1));
+
+        // the image is written in the new format with the next change
+        task::block_on(backend.update(1, &lok)).unwrap();
+        let contents = std::fs::read_to_string("test/test7.json").unwrap();
+
+        assert!(!contents.contains("image_path"));
+        assert_eq!(task::block_on(JsonBackend::build("json://test/test7.json")).unwrap().read_collection(|collection| collection.loks[0].lok.clone()), Ok(lok));
+    }
+}
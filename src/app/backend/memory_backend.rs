@@ -0,0 +1,132 @@
+use crate::app::backend::database::lok::{Lok, LokImage};
+use crate::app::backend::database::preview_lok::PreviewLok;
+use crate::app::backend::database::preview_query::{PreviewPage, PreviewQuery};
+use crate::app::backend::database::DatabaseError;
+use crate::app::backend::Backend;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Url that selects the in-memory backend.
+pub const MEMORY_DB_URL: &str = "memory://";
+
+/// Backend implementation that keeps every lok in memory.
+/// Nothing is persisted, it is used for the demo mode and for tests.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    data: Arc<Mutex<MemoryData>>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryData {
+    loks: BTreeMap<u32, Lok>,
+    last_id: u32,
+}
+
+impl MemoryBackend {
+    fn lock(&self) -> Result<MutexGuard<'_, MemoryData>, DatabaseError> {
+        self.data.lock().map_err(|_| DatabaseError::GeneralError("In-memory database is poisoned".to_string()))
+    }
+}
+
+impl Backend for MemoryBackend {
+    async fn build(_db_url: &str) -> Result<Self, DatabaseError> {
+        Ok(MemoryBackend::default())
+    }
+
+    async fn insert(&self, lok: Lok) -> Result<u32, DatabaseError> {
+        let mut data = self.lock()?;
+
+        data.last_id += 1;
+        let id = data.last_id;
+        data.loks.insert(id, lok);
+
+        Ok(id)
+    }
+
+    async fn get(&self, id: u32) -> Result<Lok, DatabaseError> {
+        self.lock()?.loks.get(&id).cloned().ok_or(DatabaseError::NotFound)
+    }
+
+    async fn update(&self, id: u32, new_lok: &Lok) -> Result<(), DatabaseError> {
+        match self.lock()?.loks.get_mut(&id) {
+            Some(lok) => {
+                *lok = new_lok.clone();
+                Ok(())
+            }
+            None => Err(DatabaseError::NotFound),
+        }
+    }
+
+    async fn remove(&self, id: u32) -> Result<(), DatabaseError> {
+        self.lock()?.loks.remove(&id).map(|_| ()).ok_or(DatabaseError::NotFound)
+    }
+
+    async fn get_all_previews(&self) -> Result<Vec<PreviewLok>, DatabaseError> {
+        Ok(self.lock()?.loks.iter().map(|(id, lok)| lok.as_preview_lok(*id)).collect())
+    }
+
+    async fn query_previews(&self, query: &PreviewQuery) -> Result<PreviewPage, DatabaseError> {
+        Ok(query.apply(self.get_all_previews().await?))
+    }
+
+    async fn count_image_references(&self, image_path: &str) -> Result<u32, DatabaseError> {
+        Ok(self.lock()?.loks.values().flat_map(|lok| &lok.images).flat_map(LokImage::stored_paths).filter(|path| *path == image_path).count() as u32)
+    }
+
+    /// Nothing to release, the data is dropped with the last clone.
+    async fn close(&self) {}
+}
+
+#[cfg(test)]
+mod memory_backend_tests {
+    use super::*;
+    use crate::app::backend::test;
+    use async_std::task;
+
+    #[test]
+    fn add_lok_works() {
+        let backend = task::block_on(MemoryBackend::build(MEMORY_DB_URL)).unwrap();
+
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+
+        assert_eq!(id, 1);
+    }
+
+    #[test]
+    fn get_update_and_remove_works() {
+        let backend = task::block_on(MemoryBackend::build(MEMORY_DB_URL)).unwrap();
+
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+
+        task::block_on(backend.update(id, &test::util::get_test_lok_2())).unwrap();
+
+        assert_eq!(task::block_on(backend.get(id)), Ok(test::util::get_test_lok_2()));
+
+        task::block_on(backend.remove(id)).unwrap();
+
+        assert_eq!(task::block_on(backend.get(id)), Err(DatabaseError::NotFound));
+        assert_eq!(task::block_on(backend.remove(id)), Err(DatabaseError::NotFound));
+    }
+
+    #[test]
+    fn ids_are_not_reused() {
+        let backend = task::block_on(MemoryBackend::build(MEMORY_DB_URL)).unwrap();
+
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+        task::block_on(backend.remove(id)).unwrap();
+
+        let new_id = task::block_on(backend.insert(test::util::get_test_lok_2())).unwrap();
+
+        assert_ne!(id, new_id);
+    }
+
+    #[test]
+    fn builds_are_isolated() {
+        let backend_1 = task::block_on(MemoryBackend::build(MEMORY_DB_URL)).unwrap();
+        let backend_2 = task::block_on(MemoryBackend::build(MEMORY_DB_URL)).unwrap();
+
+        let _id = task::block_on(backend_1.insert(test::util::get_test_lok_1())).unwrap();
+
+        assert!(task::block_on(backend_2.get_all_previews()).unwrap().is_empty());
+    }
+}
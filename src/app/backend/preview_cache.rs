@@ -0,0 +1,209 @@
+use crate::database::preview_lok::PreviewLok;
+use crate::database::preview_query::PreviewSort;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The loaded previews of a search, kept in the order of the search and indexed by id.
+/// Finding a preview is a hash lookup plus a binary search, so adding, updating and removing
+/// a lok does not sort the whole list again.
+/// The sorted list is shared, handing it to the UI only clones an ```Arc```.
+/// Changing the cache while a view is held copies the list once.
+#[derive(Clone, Debug, Default)]
+pub struct PreviewCache {
+    sort: PreviewSort,
+    sorted: Arc<Vec<PreviewLok>>,
+    by_id: HashMap<u32, PreviewLok>,
+}
+
+impl PreviewCache {
+    /// Builds the cache from previews in any order.
+    /// A later preview replaces an earlier one with the same id.
+    pub fn from_previews(previews: Vec<PreviewLok>, sort: PreviewSort) -> Self {
+        let by_id: HashMap<u32, PreviewLok> = previews.into_iter()
+            .map(|preview| (preview.get_id(), preview))
+            .collect();
+
+        let mut sorted: Vec<PreviewLok> = by_id.values().cloned().collect();
+        sorted.sort_unstable_by(|a, b| sort.compare(a, b));
+
+        PreviewCache { sort, sorted: Arc::new(sorted), by_id }
+    }
+
+    /// Adds a preview at its place in the display order.
+    /// An existing preview with the same id is replaced.
+    pub fn insert(&mut self, preview: PreviewLok) {
+        self.remove(preview.get_id());
+
+        let sorted = Arc::make_mut(&mut self.sorted);
+        let index = sorted.binary_search_by(|other| self.sort.compare(other, &preview)).unwrap_or_else(|index| index);
+        sorted.insert(index, preview.clone());
+
+        self.by_id.insert(preview.get_id(), preview);
+    }
+
+    /// Removes the preview of a lok and returns it, ```None``` if the id does not exist.
+    pub fn remove(&mut self, id: u32) -> Option<PreviewLok> {
+        let preview = self.by_id.remove(&id)?;
+
+        if let Ok(index) = self.sorted.binary_search_by(|other| self.sort.compare(other, &preview)) {
+            Arc::make_mut(&mut self.sorted).remove(index);
+        }
+
+        Some(preview)
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.by_id.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.sorted.len()
+    }
+
+    /// Returns the preview that comes last in the order of the search.
+    pub fn last(&self) -> Option<&PreviewLok> {
+        self.sorted.last()
+    }
+
+    /// Returns the previews in the order of the search, without copying them.
+    pub fn view(&self) -> Arc<Vec<PreviewLok>> {
+        Arc::clone(&self.sorted)
+    }
+}
+
+#[cfg(test)]
+mod preview_cache_tests {
+    use crate::app::backend::preview_cache::PreviewCache;
+    use crate::database::preview_lok::PreviewLok;
+    use crate::database::preview_query::PreviewSort;
+
+    fn preview(id: u32, address: Option<i32>, name: &str) -> PreviewLok {
+        PreviewLok::new(id, address, Some(name.to_string()), None)
+    }
+
+    fn ids(cache: &PreviewCache) -> Vec<u32> {
+        cache.view().iter().map(PreviewLok::get_id).collect()
+    }
+
+    #[test]
+    fn previews_are_kept_in_display_order() {
+        let mut cache = PreviewCache::from_previews(vec![
+            preview(1, Some(218), "BR 218"),
+            preview(2, None, "Köf"),
+            preview(3, Some(44), "V 100"),
+        ], PreviewSort::Address);
+
+        assert_eq!(ids(&cache), vec![2, 3, 1]);
+
+        // equal previews are ordered by id
+        cache.insert(preview(5, Some(44), "V 100"));
+        cache.insert(preview(4, Some(44), "V 100"));
+
+        assert_eq!(ids(&cache), vec![2, 3, 4, 5, 1]);
+    }
+
+    #[test]
+    fn previews_are_replaced_and_removed_by_id() {
+        let mut cache = PreviewCache::from_previews(vec![
+            preview(1, Some(218), "BR 218"),
+            preview(2, Some(44), "V 100"),
+            preview(3, Some(44), "V 100"),
+        ], PreviewSort::Address);
+
+        cache.insert(preview(1, Some(3), "BR 218"));
+
+        assert_eq!(ids(&cache), vec![1, 2, 3]);
+        assert_eq!(cache.view()[0].get_address(), Some(3));
+
+        assert_eq!(cache.remove(3).map(|preview| preview.get_id()), Some(3));
+        assert_eq!(cache.remove(3), None);
+
+        assert_eq!(ids(&cache), vec![1, 2]);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn views_are_not_changed_afterwards() {
+        let mut cache = PreviewCache::from_previews(vec![preview(1, Some(218), "BR 218")], PreviewSort::Name);
+
+        let view = cache.view();
+        cache.insert(preview(2, Some(44), "V 100"));
+
+        assert_eq!(view.len(), 1);
+        assert_eq!(cache.view().len(), 2);
+    }
+}
+
+/// Timings of the preview cache and the home page list with large collections.
+/// Run with ```cargo test --release preview_cache_benchmarks -- --ignored --nocapture```.
+#[cfg(test)]
+mod preview_cache_benchmarks {
+    use crate::app::backend::preview_cache::PreviewCache;
+    use crate::app::message::Message;
+    use crate::app::ui::widgets::preview_widget;
+    use crate::database::preview_lok::PreviewLok;
+    use crate::database::preview_query::PreviewSort;
+    use iced::widget::{column, keyed_column};
+    use iced::Element;
+    use std::time::{Duration, Instant};
+
+    const SIZES: [u32; 3] = [100, 1_000, 10_000];
+    const OPERATIONS: u32 = 1_000;
+
+    fn previews(count: u32) -> Vec<PreviewLok> {
+        (1..=count)
+            .map(|id| PreviewLok::new(id, Some((id * 7919 % 10_000) as i32), Some(format!("Lok {}", id)), None))
+            .collect()
+    }
+
+    fn report(name: &str, size: u32, elapsed: Duration, operations: u32) {
+        println!("{:<8} {:>6} loks: {:>10.2?} per operation", name, size, elapsed / operations);
+    }
+
+    #[test]
+    #[ignore]
+    fn add_update_remove() {
+        for size in SIZES {
+            let mut cache = PreviewCache::from_previews(previews(size), PreviewSort::Address);
+
+            let start = Instant::now();
+            for id in size + 1..=size + OPERATIONS {
+                cache.insert(PreviewLok::new(id, Some((id % 10_000) as i32), Some(format!("Lok {}", id)), None));
+            }
+            report("add", size, start.elapsed(), OPERATIONS);
+
+            let start = Instant::now();
+            for id in 1..=OPERATIONS {
+                cache.insert(PreviewLok::new(id, Some((id * 31 % 10_000) as i32), Some(format!("Neu {}", id)), None));
+            }
+            report("update", size, start.elapsed(), OPERATIONS);
+
+            let start = Instant::now();
+            for id in size + 1..=size + OPERATIONS {
+                cache.remove(id);
+            }
+            report("remove", size, start.elapsed(), OPERATIONS);
+
+            assert_eq!(cache.len(), size as usize);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn render_list() {
+        for size in SIZES {
+            let cache = PreviewCache::from_previews(previews(size), PreviewSort::Address);
+            let renders = 10;
+
+            let start = Instant::now();
+            for _ in 0..renders {
+                let view = cache.view();
+                let list: Element<'_, Message> = keyed_column(
+                    view.iter().map(|preview| (preview.get_id(), column![preview_widget(preview)].into()))
+                ).into();
+                drop(list);
+            }
+            report("render", size, start.elapsed(), renders);
+        }
+    }
+}
@@ -1,149 +1,373 @@
+use crate::app::backend::preview_cache::PreviewCache;
 use crate::app::backend::Backend;
-use crate::database::lok::Lok;
+use crate::app::data_location::DataLocation;
+use crate::app::snapshots;
+use crate::database::lok::{Lok, LokImage};
 use crate::database::preview_lok::PreviewLok;
+use crate::database::preview_query::{PreviewFilter, PreviewPage, PreviewQuery, PreviewSort};
 use crate::database::DatabaseError;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{Debug, Formatter};
+use std::fs;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Number of previews that are loaded at once.
+pub const PREVIEW_PAGE_SIZE: u32 = 200;
 
 /// The LokResourceManager is responsible for direct interaction with the data.
 /// Manages the database, the cache and the preview cache.
+/// Only the previews of the current search are cached, and only the pages of it that were loaded.
+/// If it owns the image files of the collection, it deletes them as soon as no lok uses them anymore,
+/// unless the collection has snapshots, that may still use them.
 #[derive(Clone)]
 pub struct LokResourceManager<BE: Backend> {
-    backend: BE,
+    handle: BackendHandle<BE>,
     cache: HashMap<u32, Lok>,
-    preview_cache: Vec<PreviewLok>,
-    search_results: Vec<PreviewLok>,
+    /// The filter and sort of the cached previews, offset and limit are not used.
+    search: PreviewQuery,
+    preview_cache: PreviewCache,
+    /// Number of loks matching the search, loaded or not.
+    number_of_matches: u32,
+    number_of_loks: u32,
+}
+
+/// Shares the backend and the image files of an LRM without its caches,
+/// so loks can be changed in the background while the LRM keeps loading previews.
+/// The changes it returns are applied to the LRM with ```LokResourceManager::apply```.
+#[derive(Clone, Debug, Default)]
+pub struct BackendHandle<BE: Backend> {
+    backend: BE,
+    image_location: Option<DataLocation>,
+}
+
+/// A change of a lok, that was written to the backend.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LokChange {
+    Added(u32, Lok),
+    Updated { id: u32, old_lok: Lok, new_lok: Lok },
+    Removed(u32, Lok),
 }
 
 impl<BE: Backend> LokResourceManager<BE>
 {
     /// Builds a LokResourceManager (LRM) for a certain backend.
+    /// The first page of every lok is loaded.
     pub async fn build(db_url: &str) -> Result<Self, DatabaseError> {
         let backend = BE::build(db_url).await?;
 
-        let mut preview_cache = backend.get_all_previews().await;
-        preview_cache.sort_unstable();
+        let mut lrm = LokResourceManager {
+            handle: BackendHandle { backend, image_location: None },
+            ..LokResourceManager::default()
+        };
+        lrm.load_first_page().await?;
 
-        Ok(LokResourceManager {
-            backend: backend.clone(),
-            cache: HashMap::new(),
-            preview_cache,
-            search_results: Vec::new(),
-        })
+        Ok(lrm)
     }
 
-    /// Adds a new lok into the database, to the cache and the preview cache.
-    /// Returns the new loks id.
-    pub async fn add_lok(&mut self, lok: Lok) -> u32 {
-        let id = self.backend.insert(lok.clone()).await;
+    /// Hands the image files of the collection at location over to the LRM.
+    /// Without a location, image files are never deleted.
+    pub fn with_image_location(mut self, location: Option<DataLocation>) -> Self {
+        self.handle.image_location = location;
+        self
+    }
 
-        self.cache.insert(id, lok.clone());
-        self.preview_cache.push(lok.as_preview_lok(id));
-        self.preview_cache.sort();
+    /// Returns a handle to change loks in the background, their changes are applied with ```apply```.
+    pub fn handle(&self) -> BackendHandle<BE> {
+        self.handle.clone()
+    }
 
-        id
+    /// Returns the lok with the given id, from the cache or loaded from the backend.
+    /// Returns ```DatabaseError::NotFound``` if the id does not exist.
+    /// Like ```query_previews``` it runs on a copy of the backend, so it can run in the background.
+    pub fn get_lok(&self, id: u32) -> impl Future<Output = Result<Lok, DatabaseError>> {
+        let cached = self.cache.get(&id).cloned();
+        let handle = self.handle.clone();
+
+        async move {
+            match cached {
+                Some(lok) => Ok(lok),
+                None => handle.get_lok(id).await,
+            }
+        }
     }
 
-    /// Returns a ```Some(Lok)``` if the id exists.
-    /// Returns ```None``` otherwise.
-    pub async fn get_lok(&mut self, id: u32) -> Option<Lok> {
-        if let Some(lok) = self.cache.get(&id) {
-            return Some(lok.clone());
+    /// Applies a change, that was written with a handle, to the cache and the preview cache.
+    /// The pages of previews loaded meanwhile are kept, the changed lok is sorted into them.
+    pub fn apply(&mut self, change: LokChange) {
+        match change {
+            LokChange::Added(id, lok) => {
+                self.number_of_loks += 1;
+                self.preview_added(lok.as_preview_lok(id));
+                self.cache.insert(id, lok);
+            }
+            LokChange::Updated { id, old_lok, new_lok } => {
+                // the lok may move within the search results, into them or out of them
+                self.preview_removed(&old_lok.as_preview_lok(id));
+                self.preview_added(new_lok.as_preview_lok(id));
+                self.cache.insert(id, new_lok);
+            }
+            LokChange::Removed(id, lok) => {
+                self.cache.remove(&id);
+                self.number_of_loks = self.number_of_loks.saturating_sub(1);
+                self.preview_removed(&lok.as_preview_lok(id));
+            }
+        }
+    }
+
+    async fn load_first_page(&mut self) -> Result<(), DatabaseError> {
+        let query = self.search.paged(0, PREVIEW_PAGE_SIZE);
+        let page = self.handle.backend.query_previews(&query).await?;
+
+        if query.filter == PreviewFilter::All {
+            self.number_of_loks = page.total;
         } else {
-            if let Some(lok) = self.backend.get(id).await {
-                self.cache.insert(id, lok.clone());
+            self.number_of_loks = self.handle.backend.query_previews(&PreviewQuery::default().paged(0, 0)).await?.total;
+        }
+
+        self.show_previews(&query, page);
+
+        Ok(())
+    }
+
+    /// Runs a preview query on a copy of the backend, so it can run in the background.
+    /// The LRM itself is not changed, the page is shown with ```show_previews```.
+    pub fn query_previews(&self, query: PreviewQuery) -> impl Future<Output = Result<PreviewPage, DatabaseError>> {
+        let backend = self.handle.backend.clone();
 
-                return Some(lok.clone());
+        async move { backend.query_previews(&query).await }
+    }
+
+    /// Changes the search the previews are loaded for and returns the query of its first page.
+    /// The previews of the former search stay until the first page is shown.
+    pub fn search(&mut self, filter: PreviewFilter, sort: PreviewSort) -> PreviewQuery {
+        self.search = PreviewQuery::new(filter, sort);
+        self.search.paged(0, PREVIEW_PAGE_SIZE)
+    }
+
+    /// Returns the query of the page after the loaded previews, ```None``` if every match is loaded.
+    pub fn next_page_query(&self) -> Option<PreviewQuery> {
+        let loaded = self.preview_cache.len() as u32;
+
+        (loaded < self.number_of_matches).then(|| self.search.paged(loaded, PREVIEW_PAGE_SIZE))
+    }
+
+    /// Shows a page of previews that was loaded in the background.
+    /// A first page replaces the loaded previews, any other page is appended.
+    /// Pages of a former search, or that don't continue the loaded previews, are ignored.
+    pub fn show_previews(&mut self, query: &PreviewQuery, page: PreviewPage) {
+        if !query.is_same_search(&self.search) {
+            return;
+        }
+
+        if query.offset == 0 {
+            self.preview_cache = PreviewCache::from_previews(page.previews, query.sort);
+        } else if query.offset == self.preview_cache.len() as u32 {
+            for preview in page.previews {
+                self.preview_cache.insert(preview);
             }
+        } else {
+            return;
         }
-        None
+
+        self.number_of_matches = page.total;
     }
 
-    /// Removes a lok from the caches and the database.
-    pub async fn remove_lok(&mut self, id: u32) {
-        self.cache.remove(&id);
-        let be_remove = self.backend.remove(id);
+    /// Returns the search the previews are loaded for.
+    pub fn get_search(&self) -> &PreviewQuery {
+        &self.search
+    }
+
+    /// Returns the loaded previews of the current search, in its order.
+    /// The list is shared with the LRM, so this is cheap enough to be called on every view.
+    pub fn get_previews(&self) -> Arc<Vec<PreviewLok>> {
+        self.preview_cache.view()
+    }
 
-        let index = self.find_preview_index(id);
+    /// Returns the number of loks that match the current search, loaded or not.
+    pub fn number_of_matches(&self) -> u32 {
+        self.number_of_matches
+    }
 
-        if let Some(index) = index {
-            let _ = self.preview_cache.remove(index as usize);
-            self.preview_cache.sort();
+    /// Returns the number of saved loks.
+    pub fn number_of_loks(&self) -> u32 {
+        self.number_of_loks
+    }
+
+    /// Counts a new or changed preview to the search results.
+    /// It is only cached, if it is within the loaded part of the results, the others are loaded with their page.
+    fn preview_added(&mut self, preview: PreviewLok) {
+        let Some(preview) = self.search.filter.select(preview) else {
+            return;
+        };
+
+        // a page loaded after the lok was written in the background counts it already
+        if self.preview_cache.contains(preview.get_id()) {
+            self.preview_cache.insert(preview);
+            return;
         }
 
+        let is_loaded = self.preview_cache.len() as u32 >= self.number_of_matches
+            || self.preview_cache.last().is_some_and(|last| self.search.sort.compare(&preview, last).is_lt());
+
+        self.number_of_matches += 1;
 
-        be_remove.await;
+        if is_loaded {
+            self.preview_cache.insert(preview);
+        }
     }
 
-    /// Updates a lok with the new data from new_lok.
-    pub async fn update_lok(&mut self, id: u32, new_lok: Lok) {
-        if self.cache.get(&id).is_some() {
-            self.cache.insert(id, new_lok.clone());
-        } else {
-            self.cache.insert(id, new_lok.clone());
+    /// Removes a removed or changed preview from the search results.
+    fn preview_removed(&mut self, preview: &PreviewLok) {
+        if !self.search.filter.matches(preview) {
+            return;
         }
 
-        let be_update = self.backend.update(id, &new_lok);
+        self.number_of_matches = self.number_of_matches.saturating_sub(1);
+        self.preview_cache.remove(preview.get_id());
+    }
+}
 
-        let index = self.find_preview_index(id);
+impl<BE: Backend> BackendHandle<BE> {
+    /// Loads a lok directly from the backend.
+    /// Returns ```DatabaseError::NotFound``` if the id does not exist.
+    pub async fn get_lok(&self, id: u32) -> Result<Lok, DatabaseError> {
+        self.backend.get(id).await
+    }
 
-        if let Some(index) = index {
-            let _ = self.preview_cache.remove(index as usize);
+    /// Adds a new lok into the database.
+    pub async fn add_lok(&self, lok: Lok) -> Result<LokChange, DatabaseError> {
+        let id = self.backend.insert(lok.clone()).await?;
 
-            self.preview_cache.push(new_lok.clone().as_preview_lok(id));
-            self.preview_cache.sort();
-        }
+        Ok(LokChange::Added(id, lok))
+    }
 
-        be_update.await;
+    /// Removes a lok from the database.
+    /// Its image is deleted, if no other lok uses it.
+    pub async fn remove_lok(&self, id: u32) -> Result<LokChange, DatabaseError> {
+        let lok = self.backend.get(id).await?;
+
+        self.backend.remove(id).await?;
+        self.discard_unused_images(&lok.image_paths()).await;
+
+        Ok(LokChange::Removed(id, lok))
     }
 
-    /// Returns all previews at once
-    pub fn get_all_previews(&self) -> Vec<PreviewLok> {
-        self.preview_cache.clone()
+    /// Updates a lok with the new data from new_lok.
+    /// Removed images are deleted after the update was saved, if no other lok uses them.
+    pub async fn update_lok(&self, id: u32, new_lok: Lok) -> Result<LokChange, DatabaseError> {
+        let old_lok = self.backend.get(id).await?;
+
+        self.backend.update(id, &new_lok).await?;
+
+        let removed_image_paths: Vec<String> = old_lok.image_paths().into_iter()
+            .filter(|image_path| !new_lok.uses_image(image_path))
+            .collect();
+        self.discard_unused_images(&removed_image_paths).await;
+
+        Ok(LokChange::Updated { id, old_lok, new_lok })
     }
 
-    /// Finds the index of a lok in the preview list, which is in different order than the database.
-    /// If the id does not exist, ```None``` is returned
-    fn find_preview_index(&self, id: u32) -> Option<u32> {
-        let mut index = 0;
-        for preview in self.preview_cache.iter() {
-            if preview.get_id() == id {
-                return Some(index);
+    /// Appends stored images to loks, given as pairs of lok id and stored path.
+    /// A lok without images gets its first new image as primary image.
+    /// Stops at the first lok that cannot be updated, the loks before it keep their new images.
+    /// Returns the number of attached images and the changes of their loks, also if attaching stopped.
+    pub async fn attach_images(&self, images: &[(u32, String)]) -> (usize, Vec<LokChange>, Result<(), DatabaseError>) {
+        let mut images_by_lok: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for (id, image_path) in images {
+            images_by_lok.entry(*id).or_default().push(image_path.clone());
+        }
+
+        let mut attached = 0;
+        let mut changes = Vec::new();
+
+        for (id, image_paths) in images_by_lok {
+            let mut lok = match self.backend.get(id).await {
+                Ok(lok) => lok,
+                Err(err) => return (attached, changes, Err(err)),
+            };
+
+            let count = image_paths.len();
+            for image_path in image_paths {
+                let is_primary = lok.images.is_empty();
+                lok.images.push(LokImage { is_primary, ..LokImage::new(image_path) });
             }
-            index += 1;
+
+            match self.update_lok(id, lok).await {
+                Ok(change) => changes.push(change),
+                Err(err) => return (attached, changes, Err(err)),
+            }
+            attached += count;
         }
 
-        None
+        (attached, changes, Ok(()))
     }
 
-    /// Returns the number of saved loks.
-    pub fn number_of_loks(&self) -> u32 {
-        self.preview_cache.len() as u32
+    /// Deletes the given images from the image directory, unless a lok still uses them.
+    /// Images outside of the image directory are never deleted, they belong to the user.
+    /// While the collection has snapshots, nothing is deleted, restoring one brings back the loks that used the images.
+    /// The images are left to the maintenance, that removes orphaned images.
+    /// A file that cannot be deleted is only left behind, so this does not fail.
+    pub async fn discard_unused_images(&self, image_paths: &[String]) {
+        let Some(location) = &self.image_location else {
+            return;
+        };
+
+        if !snapshots::list(location).is_ok_and(|snapshots| snapshots.is_empty()) {
+            return;
+        }
+
+        for image_path in image_paths {
+            let path = location.image_path(image_path);
+
+            if !path.starts_with(location.images_dir()) || !path.is_file() {
+                continue;
+            }
+
+            match self.backend.count_image_references(image_path).await {
+                Ok(0) => {
+                    if let Err(err) = fs::remove_file(&path) {
+                        println!("Couldn't delete image {}: {}", path.display(), err);
+                    }
+
+                    // the thumbnail is only a cache, it may not exist
+                    let _ = fs::remove_file(location.thumbnail_path(image_path));
+                }
+                Ok(_) => {}
+                Err(err) => println!("Couldn't count the references to image {}: {}", image_path, err),
+            }
+        }
     }
 
-    /// Stores every PreviewLok that matches with the given search string
-    pub fn search_and_store_previews_containing(&mut self, search: String) {
-        self.search_results = self.preview_cache
-            .iter()
-            .filter(|preview_lok| { preview_lok.get_search_string().contains(search.as_str()) })
-            .map(|preview_lok| { preview_lok.clone() })
-            .collect::<Vec<PreviewLok>>()
-            .into();
+    /// Loads every lok directly from the backend.
+    /// An id the backend lists more than once is returned more than once.
+    pub async fn load_all_loks(&self) -> Result<Vec<(u32, Lok)>, DatabaseError> {
+        let mut loks = Vec::new();
+
+        for preview in self.backend.get_all_previews().await? {
+            let id = preview.get_id();
+            loks.push((id, self.backend.get(id).await?));
+        }
+
+        Ok(loks)
     }
 
-    /// Returns every PreviewLok that matches with the previously given search string
-    pub fn get_search_results(&self) -> Vec<PreviewLok> {
-        self.search_results.clone()
+    /// Closes the backend, e.g. before its files are replaced by a backup.
+    /// Every LRM and handle sharing it must not be used afterwards.
+    pub async fn close(&self) {
+        self.backend.close().await;
     }
 }
 
 impl<BE: Backend> Default for LokResourceManager<BE> {
     fn default() -> Self {
         LokResourceManager {
-            backend: BE::default(),
+            handle: BackendHandle::default(),
             cache: HashMap::default(),
-            preview_cache: Vec::default(),
-            search_results: Vec::default(),
+            search: PreviewQuery::default(),
+            preview_cache: PreviewCache::default(),
+            number_of_matches: 0,
+            number_of_loks: 0,
         }
     }
 }
@@ -152,8 +376,9 @@ impl<BE: Backend> Default for LokResourceManager<BE> {
 impl<BE: Backend> Debug for LokResourceManager<BE> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("LokResourceManager")
-            .field("backend", &self.backend)
+            .field("backend", &self.handle.backend)
             .field("cache", &self.cache)
+            .field("search", &self.search)
             .field("preview_cache", &self.preview_cache)
             .finish()
     }
@@ -162,89 +387,116 @@ impl<BE: Backend> Debug for LokResourceManager<BE> {
 #[cfg(test)]
 mod lok_resource_manager_tests {
     use super::*;
+    use crate::app::backend::memory_backend::{MemoryBackend, MEMORY_DB_URL};
     use crate::app::backend::sqlite_backend::SQLiteBackend;
     use crate::app::backend::test;
     use async_std::task;
+    use std::path::PathBuf;
+
+    fn build_lrm() -> LokResourceManager<MemoryBackend> {
+        task::block_on(LokResourceManager::<MemoryBackend>::build(MEMORY_DB_URL)).unwrap()
+    }
+
+    // the changes are written with a handle and applied afterwards, as the pages do in the background
+
+    fn add_lok<BE: Backend>(lrm: &mut LokResourceManager<BE>, lok: Lok) -> Result<u32, DatabaseError> {
+        let change = task::block_on(lrm.handle().add_lok(lok))?;
+        let LokChange::Added(id, _) = change else {
+            panic!("adding a lok has to return LokChange::Added");
+        };
+        lrm.apply(change);
+
+        Ok(id)
+    }
+
+    fn update_lok<BE: Backend>(lrm: &mut LokResourceManager<BE>, id: u32, lok: Lok) -> Result<(), DatabaseError> {
+        let change = task::block_on(lrm.handle().update_lok(id, lok))?;
+        lrm.apply(change);
+
+        Ok(())
+    }
+
+    fn remove_lok<BE: Backend>(lrm: &mut LokResourceManager<BE>, id: u32) -> Result<(), DatabaseError> {
+        let change = task::block_on(lrm.handle().remove_lok(id))?;
+        lrm.apply(change);
+
+        Ok(())
+    }
+
+    /// Loads the first page of the current search again, as the home page does.
+    fn reload<BE: Backend>(lrm: &mut LokResourceManager<BE>) {
+        let search = lrm.get_search().clone();
+        let query = lrm.search(search.filter, search.sort);
+        let page = task::block_on(lrm.query_previews(query.clone())).unwrap();
+        lrm.show_previews(&query, page);
+    }
 
     #[test]
     fn build_works() {
-        test::util::remove_test_db(2);
-        let lrm = task::block_on(LokResourceManager::<SQLiteBackend>::build("sqlite://test/test2.db"));
+        let lrm = task::block_on(LokResourceManager::<MemoryBackend>::build(MEMORY_DB_URL));
 
         assert!(lrm.is_ok());
     }
 
     #[test]
     fn add_lok_on_new_db_works() {
-        test::util::remove_test_db(3);
-
-        let mut lrm = task::block_on(LokResourceManager::<SQLiteBackend>::build("sqlite://test/test3.db")).unwrap();
-
-        let id = task::block_on(lrm.add_lok(test::util::get_test_lok_1()));
+        let mut lrm = build_lrm();
 
-        println!("{}", id);
+        let id = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
 
         assert_eq!(id, 1);
     }
 
     #[test]
     fn add_lok_to_db_cache_and_preview_cache_works() {
-        test::util::remove_test_db(5);
+        let mut lrm = build_lrm();
 
-        let mut lrm = task::block_on(LokResourceManager::<SQLiteBackend>::build("sqlite://test/test5.db")).unwrap();
-
-        let id = task::block_on(lrm.add_lok(test::util::get_test_lok_1()));
+        let id = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
 
         assert_eq!(id, 1);
-        assert!(lrm.cache.len() > 0);
-        assert!(lrm.preview_cache.len() > 0);
+        assert!(!lrm.cache.is_empty());
+        assert_eq!(lrm.get_previews()[0].get_id(), id);
     }
 
     #[test]
     fn get_lok_works() {
-        test::util::remove_test_db(6);
-
-        let mut lrm = task::block_on(LokResourceManager::<SQLiteBackend>::build("sqlite://test/test6.db")).unwrap();
+        let mut lrm = build_lrm();
 
         let result = task::block_on(lrm.get_lok(1));
 
-        assert!(result.is_none());
+        assert_eq!(result, Err(DatabaseError::NotFound));
 
-        let _id = task::block_on(lrm.add_lok(test::util::get_test_lok_1()));
+        let _id = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
 
         let result = task::block_on(lrm.get_lok(1));
 
-        assert!(result.is_some());
+        assert!(result.is_ok());
     }
 
     #[test]
     fn remove_works() {
-        test::util::remove_test_db(7);
-
-        let mut lrm = task::block_on(LokResourceManager::<SQLiteBackend>::build("sqlite://test/test7.db")).unwrap();
+        let mut lrm = build_lrm();
 
-        let id = task::block_on(lrm.add_lok(test::util::get_test_lok_1()));
+        let id = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
 
         let result = task::block_on(lrm.get_lok(id));
 
-        assert!(result.is_some());
+        assert!(result.is_ok());
 
-        task::block_on(lrm.remove_lok(id));
+        remove_lok(&mut lrm, id).unwrap();
 
         let result = task::block_on(lrm.get_lok(id));
 
-        assert!(result.is_none());
+        assert_eq!(result, Err(DatabaseError::NotFound));
     }
 
     #[test]
     fn update_works() {
-        test::util::remove_test_db(8);
+        let mut lrm = build_lrm();
 
-        let mut lrm = task::block_on(LokResourceManager::<SQLiteBackend>::build("sqlite://test/test8.db")).unwrap();
+        let id = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
 
-        let id = task::block_on(lrm.add_lok(test::util::get_test_lok_1()));
-
-        task::block_on(lrm.update_lok(id, test::util::get_test_lok_2()));
+        update_lok(&mut lrm, id, test::util::get_test_lok_2()).unwrap();
 
         let result = task::block_on(lrm.get_lok(id)).unwrap();
 
@@ -256,21 +508,274 @@ mod lok_resource_manager_tests {
         assert_eq!(result.producer.unwrap(), test_lok2.producer.unwrap());
         assert_eq!(result.management.unwrap(), test_lok2.management.unwrap());
         assert_eq!(result.has_decoder, test_lok2.has_decoder);
-        assert_eq!(result.image_path.unwrap(), test_lok2.image_path.unwrap());
+        assert_eq!(result.images, test_lok2.images);
     }
 
     #[test]
-    fn get_all_previews_works() {
-        test::util::remove_test_db(9);
+    fn get_previews_works() {
+        let mut lrm = build_lrm();
+
+        let _id1 = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
+        let _id2 = add_lok(&mut lrm, test::util::get_test_lok_2()).unwrap();
+
+        let previews = lrm.get_previews();
 
-        let mut lrm = task::block_on(LokResourceManager::<SQLiteBackend>::build("sqlite://test/test9.db")).unwrap();
+        assert_eq!(previews[1].get_name_pretty(), String::from("TEST"));
+        assert_eq!(previews[0].get_name_pretty(), String::from("RRRR"));
+    }
 
-        let _id1 = task::block_on(lrm.add_lok(test::util::get_test_lok_1()));
-        let _id2 = task::block_on(lrm.add_lok(test::util::get_test_lok_2()));
+    #[test]
+    fn failed_update_leaves_cache_untouched() {
+        let mut lrm = build_lrm();
 
-        let mut previews = lrm.get_all_previews();
+        let result = update_lok(&mut lrm, 42, test::util::get_test_lok_1());
 
-        assert_eq!(previews.pop().unwrap().get_name_pretty(), String::from("TEST"));
-        assert_eq!(previews.pop().unwrap().get_name_pretty(), String::from("RRRR"));
+        assert_eq!(result, Err(DatabaseError::NotFound));
+        assert!(lrm.cache.is_empty());
+        assert_eq!(lrm.number_of_loks(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unused_images_are_deleted() {
+        let location = DataLocation::new(PathBuf::from("test/resource_manager_images"));
+        let _ = std::fs::remove_dir_all(location.data_dir());
+        location.create_dirs().unwrap();
+
+        for file in ["a.png", "b.png", "c.png"] {
+            std::fs::write(location.image_path(&DataLocation::stored_image_path(file)), file).unwrap();
+        }
+
+        let image = |file: &str| location.image_path(&DataLocation::stored_image_path(file));
+        let lok = |file: &str| Lok::new_from_raw_data("IMG".to_string(), None, "".to_string(), "".to_string(), "".to_string(), false, vec![LokImage::new(DataLocation::stored_image_path(file))]);
+
+        let mut lrm = build_lrm().with_image_location(Some(location.clone()));
+        let id1 = add_lok(&mut lrm, lok("a.png")).unwrap();
+        let id2 = add_lok(&mut lrm, lok("a.png")).unwrap();
+
+        // an unsaved copy is deleted, a saved image is kept
+        task::block_on(lrm.handle().discard_unused_images(&[DataLocation::stored_image_path("a.png"), DataLocation::stored_image_path("c.png")]));
+        assert!(image("a.png").exists());
+        assert!(!image("c.png").exists());
+
+        // the image is still used by the second lok
+        update_lok(&mut lrm, id1, lok("b.png")).unwrap();
+        assert!(image("a.png").exists());
+
+        remove_lok(&mut lrm, id2).unwrap();
+        assert!(!image("a.png").exists());
+
+        remove_lok(&mut lrm, id1).unwrap();
+        assert!(!image("b.png").exists());
+    }
+
+    #[test]
+    fn images_are_kept_while_snapshots_exist() {
+        let location = DataLocation::new(PathBuf::from("test/resource_manager_images_snapshot"));
+        let _ = std::fs::remove_dir_all(location.data_dir());
+        location.create_dirs().unwrap();
+        std::fs::create_dir_all(location.snapshots_dir()).unwrap();
+        std::fs::write(location.snapshots_dir().join("lokbuch-2026-10-18_120000-startup.db"), "").unwrap();
+
+        let image_path = DataLocation::stored_image_path("a.png");
+        std::fs::write(location.image_path(&image_path), "a").unwrap();
+
+        let mut lrm = build_lrm().with_image_location(Some(location.clone()));
+        let id = add_lok(&mut lrm, Lok::new_from_raw_data("IMG".to_string(), None, "".to_string(), "".to_string(), "".to_string(), false, vec![LokImage::new(image_path.clone())])).unwrap();
+
+        // the snapshot still uses the image
+        remove_lok(&mut lrm, id).unwrap();
+        assert!(location.image_path(&image_path).exists());
+    }
+
+    #[test]
+    fn images_are_kept_without_location() {
+        let location = DataLocation::new(PathBuf::from("test/resource_manager_images_kept"));
+        let _ = std::fs::remove_dir_all(location.data_dir());
+        location.create_dirs().unwrap();
+
+        let image_path = DataLocation::stored_image_path("a.png");
+        std::fs::write(location.image_path(&image_path), "a").unwrap();
+
+        let mut lrm = build_lrm();
+        let id = add_lok(&mut lrm, Lok::new_from_raw_data("IMG".to_string(), None, "".to_string(), "".to_string(), "".to_string(), false, vec![LokImage::new(image_path.clone())])).unwrap();
+
+        remove_lok(&mut lrm, id).unwrap();
+
+        assert!(location.image_path(&image_path).exists());
+    }
+
+    #[test]
+    fn demo_keeps_images_of_the_collection() {
+        let location = DataLocation::new(PathBuf::from("test/resource_manager_images_demo"));
+        let _ = std::fs::remove_dir_all(location.data_dir());
+        location.create_dirs().unwrap();
+
+        let image_path = DataLocation::stored_image_path("a.png");
+        std::fs::write(location.image_path(&image_path), "a").unwrap();
+        let lok = Lok::new_from_raw_data("IMG".to_string(), None, "".to_string(), "".to_string(), "".to_string(), false, vec![LokImage::new(image_path.clone())]);
+
+        // the collection refers to the image as well, but the demo cannot know
+        let mut collection = task::block_on(LokResourceManager::<SQLiteBackend>::build(&location.db_url())).unwrap();
+        add_lok(&mut collection, lok.clone()).unwrap();
+
+        let mut demo = build_lrm().with_image_location(location.owned_by(MEMORY_DB_URL));
+        let id = add_lok(&mut demo, lok).unwrap();
+
+        remove_lok(&mut demo, id).unwrap();
+
+        assert!(location.image_path(&image_path).exists());
+        assert!(location.owned_by(&location.db_url()).is_some());
+    }
+
+    #[test]
+    fn images_are_attached_in_one_batch() {
+        let mut lrm = build_lrm();
+        let without_images = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
+        let with_image = add_lok(&mut lrm, test::util::get_test_lok_2()).unwrap();
+
+        let (attached, changes, result) = task::block_on(lrm.handle().attach_images(&[
+            (without_images, "images/a.png".to_string()),
+            (with_image, "images/b.png".to_string()),
+            (without_images, "images/c.png".to_string()),
+        ]));
+
+        assert_eq!((attached, result), (3, Ok(())));
+        for change in changes {
+            lrm.apply(change);
+        }
+
+        let images = task::block_on(lrm.get_lok(without_images)).unwrap().images;
+        assert_eq!(images, vec![
+            LokImage { is_primary: true, ..LokImage::new("images/a.png".to_string()) },
+            LokImage::new("images/c.png".to_string()),
+        ]);
+
+        let images = task::block_on(lrm.get_lok(with_image)).unwrap().images;
+        assert_eq!(images.len(), 2);
+        assert!(images[0].is_primary);
+        assert_eq!(images[1], LokImage::new("images/b.png".to_string()));
+    }
+
+    #[test]
+    fn attached_images_are_counted_when_attaching_stops() {
+        let mut lrm = build_lrm();
+        let id = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
+        let removed = add_lok(&mut lrm, test::util::get_test_lok_2()).unwrap();
+        remove_lok(&mut lrm, removed).unwrap();
+
+        let (attached, changes, result) = task::block_on(lrm.handle().attach_images(&[
+            (id, "images/a.png".to_string()),
+            (removed, "images/b.png".to_string()),
+        ]));
+
+        assert_eq!((attached, result), (1, Err(DatabaseError::NotFound)));
+        assert!(matches!(changes.as_slice(), [LokChange::Updated { id: changed, .. }] if *changed == id));
+        for change in changes {
+            lrm.apply(change);
+        }
+        assert_eq!(task::block_on(lrm.get_lok(id)).unwrap().images.len(), 1);
+    }
+
+    #[test]
+    fn sqlite_backend_works() {
+        test::util::remove_test_db(2);
+
+        let mut lrm = task::block_on(LokResourceManager::<SQLiteBackend>::build("sqlite://test/test2.db")).unwrap();
+
+        let id = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
+
+        assert_eq!(task::block_on(lrm.get_lok(id)), Ok(test::util::get_test_lok_1()));
+    }
+
+    #[test]
+    fn previews_are_loaded_in_pages() {
+        let mut lrm = build_lrm();
+
+        for address in 1..=PREVIEW_PAGE_SIZE + 50 {
+            let lok = Lok::new_from_raw_data(format!("Lok {}", address), Some(address as i32), "".to_string(), "".to_string(), "".to_string(), true, Vec::new());
+            add_lok(&mut lrm, lok).unwrap();
+        }
+
+        reload(&mut lrm);
+
+        assert_eq!(lrm.get_previews().len() as u32, PREVIEW_PAGE_SIZE);
+        assert_eq!(lrm.number_of_matches(), PREVIEW_PAGE_SIZE + 50);
+
+        let query = lrm.next_page_query().unwrap();
+        let page = task::block_on(lrm.query_previews(query.clone())).unwrap();
+        lrm.show_previews(&query, page);
+
+        assert_eq!(lrm.get_previews().len() as u32, PREVIEW_PAGE_SIZE + 50);
+        assert_eq!(lrm.next_page_query(), None);
+
+        // pages of a former search are ignored
+        let former_query = PreviewQuery::default().paged(0, PREVIEW_PAGE_SIZE);
+        let former_page = task::block_on(lrm.query_previews(former_query.clone())).unwrap();
+        let query = lrm.search(PreviewFilter::from_search_input("lok 1").unwrap(), PreviewSort::Name);
+        let page = task::block_on(lrm.query_previews(query.clone())).unwrap();
+        lrm.show_previews(&query, page);
+        lrm.show_previews(&former_query, former_page);
+
+        // every lok with a 1 in its number
+        assert_eq!(lrm.number_of_matches(), 133);
+        assert_eq!(lrm.number_of_loks(), PREVIEW_PAGE_SIZE + 50);
+        assert_eq!(lrm.get_previews()[0].get_name_pretty(), "Lok 1");
+    }
+
+    #[test]
+    fn changed_loks_are_moved_within_the_search_results() {
+        let mut lrm = build_lrm();
+
+        let id = add_lok(&mut lrm, test::util::get_test_lok_1()).unwrap();
+        let id2 = add_lok(&mut lrm, test::util::get_test_lok_2()).unwrap();
+
+        let query = lrm.search(PreviewFilter::from_search_input("test").unwrap(), PreviewSort::Address);
+        let page = task::block_on(lrm.query_previews(query.clone())).unwrap();
+        lrm.show_previews(&query, page);
+
+        assert_eq!(lrm.get_previews().len(), 1);
+
+        // the changed lok leaves the results, the other one enters them
+        update_lok(&mut lrm, id, test::util::get_test_lok_2()).unwrap();
+        update_lok(&mut lrm, id2, test::util::get_test_lok_1()).unwrap();
+
+        assert_eq!(lrm.get_previews()[0].get_id(), id2);
+        assert_eq!(lrm.number_of_matches(), 1);
+
+        remove_lok(&mut lrm, id2).unwrap();
+
+        assert!(lrm.get_previews().is_empty());
+        assert_eq!(lrm.number_of_matches(), 0);
+        assert_eq!(lrm.number_of_loks(), 1);
+    }
+
+    #[test]
+    fn background_changes_keep_pages_loaded_meanwhile() {
+        let mut lrm = build_lrm();
+
+        for address in 1..=PREVIEW_PAGE_SIZE + 50 {
+            let lok = Lok::new_from_raw_data(format!("Lok {}", address), Some(address as i32), "".to_string(), "".to_string(), "".to_string(), true, Vec::new());
+            add_lok(&mut lrm, lok).unwrap();
+        }
+
+        reload(&mut lrm);
+
+        // the lok is saved in the background, while the next page is loaded
+        let handle = lrm.handle();
+        let change = task::block_on(handle.add_lok(test::util::get_test_lok_1())).unwrap();
+        let LokChange::Added(id, _) = change.clone() else {
+            panic!("adding a lok has to return LokChange::Added");
+        };
+
+        let query = lrm.next_page_query().unwrap();
+        let page = task::block_on(lrm.query_previews(query.clone())).unwrap();
+        lrm.show_previews(&query, page);
+
+        lrm.apply(change.clone());
+
+        assert_eq!(lrm.get_previews().len() as u32, PREVIEW_PAGE_SIZE + 51);
+        assert_eq!(lrm.number_of_matches(), PREVIEW_PAGE_SIZE + 51);
+        assert_eq!(lrm.number_of_loks(), PREVIEW_PAGE_SIZE + 51);
+        assert!(lrm.get_previews().iter().any(|preview| preview.get_id() == id));
+    }
+}
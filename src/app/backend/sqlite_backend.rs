@@ -1,9 +1,20 @@
-use crate::app::backend::database::lok::Lok;
-use crate::app::backend::database::preview_lok::PreviewLok;
+use crate::app::backend::database::lok::{Lok, LokImage, RawLokImageData};
+use crate::app::backend::database::preview_lok::{PreviewData, PreviewLok};
+use crate::app::backend::database::preview_query::{PreviewFilter, PreviewPage, PreviewQuery, PreviewSort};
+use crate::app::backend::database::search::{self, Search};
+use crate::app::backend::database::search_query::{SearchCondition, SearchQuery};
 use crate::app::backend::database::sqlite_db::SQLiteDB;
 use crate::app::backend::database::{Database, DatabaseError};
 use crate::app::backend::Backend;
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, Transaction};
+
+/// The columns of a preview.
+const PREVIEW_COLUMNS: &str = "id, address, name, lokmaus_name, producer, management, has_decoder";
+
+/// Search words with fewer chars have no trigram of their own, they are looked up with LIKE.
+const MIN_TRIGRAM_WORD_LENGTH: usize = 3;
+/// Searches with words are matched in memory, at most this many of their candidates are read, the first ones by id.
+const MAX_SEARCH_CANDIDATES: i64 = 2000;
 
 /// Backend implementation for a SQLite database
 #[derive(Clone, Debug)]
@@ -19,79 +30,315 @@ impl Default for SQLiteBackend {
     }
 }
 
+impl SQLiteBackend {
+    /// Inserts the images of a lok, their position is their index.
+    async fn insert_images(transaction: &mut Transaction<'_, Sqlite>, id: u32, images: &[LokImage]) -> Result<(), DatabaseError> {
+        for (position, image) in images.iter().enumerate() {
+            sqlx::query("INSERT INTO lok_images (lok_id, position, path, caption, is_primary, original_path) VALUES (?, ?, ?, ?, ?, ?)")
+                .bind(id)
+                .bind(position as u32)
+                .bind(image.path.clone())
+                .bind(image.caption.clone())
+                .bind(image.is_primary)
+                .bind(image.original.clone())
+                .execute(&mut **transaction)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the WHERE condition that selects the candidates of a search and the values it binds.
+    /// The candidates contain every lok that matches, the search itself decides which of them match.
+    /// A lok matches a word, if it contains the word or shares a trigram with it,
+    /// so the candidates contain one of the trigrams of every long word and every short word.
+    fn search_condition(search: &Search) -> (String, Vec<String>) {
+        let mut conditions = vec!["TRUE".to_string()];
+        let mut values = Vec::new();
+        let mut trigram_groups = Vec::new();
+
+        for word in search.words() {
+            if word.chars().count() < MIN_TRIGRAM_WORD_LENGTH {
+                // search texts only contain letters, digits and spaces, there is nothing to escape
+                conditions.push("search_text LIKE ?".to_string());
+                values.push(format!("%{}%", word));
+            } else {
+                let trigrams: Vec<String> = search::word_trigrams(&word).iter()
+                    .map(|trigram| format!("\"{}\"", trigram))
+                    .collect();
+                trigram_groups.push(format!("({})", trigrams.join(" OR ")));
+            }
+        }
+
+        if !trigram_groups.is_empty() {
+            conditions.push("id IN (SELECT rowid FROM lok_search WHERE lok_search MATCH ?)".to_string());
+            values.push(trigram_groups.join(" AND "));
+        }
+
+        (conditions.join(" AND "), values)
+    }
+
+    /// Returns the WHERE condition that selects the candidates of a query and the values it binds.
+    /// Addresses and decoders are selected exactly, words and phrases by the candidates of their search.
+    /// Negated words and phrases are left to the query, the candidates of their search are not exact.
+    fn query_condition(query: &SearchQuery) -> (String, Vec<String>) {
+        let mut conditions = vec!["TRUE".to_string()];
+        let mut values = Vec::new();
+
+        for term in &query.terms {
+            let negation = if term.negated { "NOT " } else { "" };
+
+            match &term.condition {
+                SearchCondition::Address(range) => {
+                    conditions.push(format!("{}COALESCE(address BETWEEN {} AND {}, FALSE)", negation, range.start(), range.end()));
+                }
+                SearchCondition::Digital(has_decoder) => {
+                    conditions.push(format!("has_decoder = {}", *has_decoder != term.negated));
+                }
+                SearchCondition::Words(_, search) | SearchCondition::Phrase(_, search) if !term.negated => {
+                    let (condition, search_values) = SQLiteBackend::search_condition(search);
+                    conditions.push(condition);
+                    values.extend(search_values);
+                }
+                SearchCondition::Words(..) | SearchCondition::Phrase(..) => {}
+            }
+        }
+
+        (conditions.join(" AND "), values)
+    }
+
+    /// Returns true, if the condition of the filter selects exactly its matches, so they are counted and paged in SQL.
+    /// Words and phrases are only narrowed down to candidates, they are matched by the search.
+    fn is_exact(filter: &PreviewFilter) -> bool {
+        match filter {
+            PreviewFilter::All => true,
+            PreviewFilter::Text(_) => false,
+            PreviewFilter::Query(search_query) => search_query.terms.iter()
+                .all(|term| matches!(term.condition, SearchCondition::Address(_) | SearchCondition::Digital(_))),
+        }
+    }
+
+    /// Writes the search text of loks that have none yet, e.g. loks of a database from before the search index.
+    async fn write_missing_search_texts(database: &Pool<Sqlite>) -> Result<(), DatabaseError> {
+        let data: Vec<PreviewData> = sqlx::query_as(&format!("SELECT {} FROM loks WHERE search_text = ''", PREVIEW_COLUMNS))
+            .fetch_all(database)
+            .await?;
+
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let mut transaction = database.begin().await?;
+
+        for preview in data.iter().map(PreviewLok::new_from_raw_preview_data) {
+            sqlx::query("UPDATE loks SET search_text = ? WHERE id = ?")
+                .bind(search::index_text(&preview.search_fields()))
+                .bind(preview.get_id())
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Returns the ORDER BY clause of a sort, it matches ```PreviewSort::compare```.
+    fn order_by(sort: PreviewSort) -> &'static str {
+        match sort {
+            PreviewSort::Address | PreviewSort::Relevance => "address, name, id",
+            PreviewSort::Name => "name, address, id",
+            PreviewSort::LokmausName => "lokmaus_name, address, name, id",
+        }
+    }
+}
+
 impl Backend for SQLiteBackend {
     async fn build(db_url: &str) -> Result<Self, DatabaseError> {
         let mut db = SQLiteDB::build(db_url).await?;
 
         let conn = db.connect().await?;
+        SQLiteBackend::write_missing_search_texts(&conn).await?;
 
         Ok(Self { database: conn })
     }
 
-    async fn insert(&self, lok: Lok) -> u32 {
-        let result = sqlx::query("INSERT INTO loks (name, address, lokmaus_name, producer, management, has_decoder, image_path) VALUES (?, ?, ?, ?, ?, ?, ?)")
+    async fn insert(&self, lok: Lok) -> Result<u32, DatabaseError> {
+        let mut transaction = self.database.begin().await?;
+
+        let result = sqlx::query("INSERT INTO loks (name, address, lokmaus_name, producer, management, has_decoder, search_text) VALUES (?, ?, ?, ?, ?, ?, ?)")
             .bind(lok.name.clone())
-            .bind(lok.address.clone())
+            .bind(lok.address)
             .bind(lok.lokmaus_name.clone())
             .bind(lok.producer.clone())
             .bind(lok.management.clone())
-            .bind(lok.has_decoder.clone())
-            .bind(lok.image_path.clone())
-            .execute(&self.database.clone())
-            .await
-            .expect("Failed to add Lok to database!");
+            .bind(lok.has_decoder)
+            .bind(lok.get_search_text())
+            .execute(&mut *transaction)
+            .await?;
 
-        result.last_insert_rowid() as u32
+        let id = result.last_insert_rowid() as u32;
+
+        SQLiteBackend::insert_images(&mut transaction, id, &lok.images).await?;
+        transaction.commit().await?;
+
+        Ok(id)
     }
 
-    async fn get(&self, id: u32) -> Option<Lok> {
-        let result = sqlx::query_as("SELECT * FROM loks WHERE id = ? LIMIT 1")
+    async fn get(&self, id: u32) -> Result<Lok, DatabaseError> {
+        let raw_lok = sqlx::query_as("SELECT * FROM loks WHERE id = ? LIMIT 1")
             .bind(id)
             .fetch_one(&self.database)
-            .await;
+            .await?;
 
-        match result {
-            Ok(raw_lok) => {
-                Some(Lok::new_from_raw_lok_data(&raw_lok))
-            }
-            Err(_) => { None }
-        }
+        let raw_images: Vec<RawLokImageData> = sqlx::query_as("SELECT path, caption, is_primary, original_path FROM lok_images WHERE lok_id = ? ORDER BY position")
+            .bind(id)
+            .fetch_all(&self.database)
+            .await?;
+
+        Ok(Lok::new_from_raw_lok_data(&raw_lok, &raw_images))
     }
 
-    async fn update(&self, id: u32, new_lok: &Lok) {
-        let result = sqlx::query("UPDATE loks SET address = ?, name = ?, lokmaus_name = ?, producer = ?, management = ?, has_decoder = ?, image_path = ? WHERE id = ?;")
-            .bind(new_lok.address.clone())
+    async fn update(&self, id: u32, new_lok: &Lok) -> Result<(), DatabaseError> {
+        let mut transaction = self.database.begin().await?;
+
+        let result = sqlx::query("UPDATE loks SET address = ?, name = ?, lokmaus_name = ?, producer = ?, management = ?, has_decoder = ?, search_text = ? WHERE id = ?;")
+            .bind(new_lok.address)
             .bind(new_lok.name.clone())
             .bind(new_lok.lokmaus_name.clone())
             .bind(new_lok.producer.clone())
             .bind(new_lok.management.clone())
-            .bind(new_lok.has_decoder.clone())
-            .bind(new_lok.image_path.clone())
+            .bind(new_lok.has_decoder)
+            .bind(new_lok.get_search_text())
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        sqlx::query("DELETE FROM lok_images WHERE lok_id = ?")
             .bind(id)
-            .execute(&self.database)
-            .await.unwrap();
+            .execute(&mut *transaction)
+            .await?;
 
-        println!("updated lok: {:?}", result)
+        SQLiteBackend::insert_images(&mut transaction, id, &new_lok.images).await?;
+        transaction.commit().await?;
+
+        Ok(())
     }
 
-    async fn remove(&self, id: u32) {
+    async fn remove(&self, id: u32) -> Result<(), DatabaseError> {
+        let mut transaction = self.database.begin().await?;
+
+        sqlx::query("DELETE FROM lok_images WHERE lok_id = ?")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+
         let result = sqlx::query("DELETE FROM loks WHERE id = ?")
             .bind(id)
-            .execute(&self.database)
-            .await.unwrap();
+            .execute(&mut *transaction)
+            .await?;
 
-        println!("Deleted lok: {:?}", result)
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
     }
 
-    async fn get_all_previews(&self) -> Vec<PreviewLok> {
-        let data = sqlx::query_as("select id, address, name, lokmaus_name from loks")
+    async fn get_all_previews(&self) -> Result<Vec<PreviewLok>, DatabaseError> {
+        let data = sqlx::query_as(&format!("SELECT {} FROM loks ORDER BY id", PREVIEW_COLUMNS))
+            .fetch_all(&self.database)
+            .await?;
+
+        Ok(data.iter().map(PreviewLok::new_from_raw_preview_data).collect())
+    }
+
+    async fn query_previews(&self, query: &PreviewQuery) -> Result<PreviewPage, DatabaseError> {
+        let (condition, values) = match &query.filter {
+            PreviewFilter::All => ("TRUE".to_string(), Vec::new()),
+            PreviewFilter::Text(search) => SQLiteBackend::search_condition(search),
+            PreviewFilter::Query(search_query) => SQLiteBackend::query_condition(search_query),
+        };
+
+        if !SQLiteBackend::is_exact(&query.filter) {
+            // words are matched by the search, only a limited number of their candidates is read
+            let statement = format!(
+                "SELECT {} FROM loks WHERE id IN (SELECT id FROM loks WHERE {} ORDER BY id LIMIT ?) ORDER BY {}",
+                PREVIEW_COLUMNS,
+                condition,
+                SQLiteBackend::order_by(query.sort),
+            );
+            let mut candidates_query = sqlx::query_as(&statement);
+            for value in &values {
+                candidates_query = candidates_query.bind(value);
+            }
+            let data: Vec<PreviewData> = candidates_query.bind(MAX_SEARCH_CANDIDATES).fetch_all(&self.database).await?;
+            let candidates = data.iter().map(PreviewLok::new_from_raw_preview_data);
+
+            // only the relevance is ranked by the search, the other sorts are kept from SQL
+            if query.sort == PreviewSort::Relevance {
+                return Ok(query.apply(candidates));
+            }
+
+            let matching: Vec<PreviewLok> = candidates.filter_map(|preview| query.filter.select(preview)).collect();
+            let total = matching.len() as u32;
+            let previews = matching.into_iter()
+                .skip(query.offset as usize)
+                .take(query.limit.map_or(usize::MAX, |limit| limit as usize))
+                .collect();
+
+            return Ok(PreviewPage { previews, total });
+        }
+
+        let count_statement = format!("SELECT COUNT(*) FROM loks WHERE {}", condition);
+        let mut count_query = sqlx::query_scalar(&count_statement);
+        for value in &values {
+            count_query = count_query.bind(value);
+        }
+        let total: i64 = count_query.fetch_one(&self.database).await?;
+
+        // a negative limit returns every row
+        let statement = format!(
+            "SELECT {} FROM loks WHERE {} ORDER BY {} LIMIT ? OFFSET ?",
+            PREVIEW_COLUMNS,
+            condition,
+            SQLiteBackend::order_by(query.sort),
+        );
+        let mut page_query = sqlx::query_as(&statement);
+        for value in &values {
+            page_query = page_query.bind(value);
+        }
+        let data: Vec<PreviewData> = page_query
+            .bind(query.limit.map_or(-1, i64::from))
+            .bind(query.offset)
             .fetch_all(&self.database)
-            .await
-            .unwrap();
+            .await?;
+
+        // the matches of a query without words only get their empty search match
+        Ok(PreviewPage {
+            previews: data.iter().map(PreviewLok::new_from_raw_preview_data).filter_map(|preview| query.filter.select(preview)).collect(),
+            total: total as u32,
+        })
+    }
+
+    async fn count_image_references(&self, image_path: &str) -> Result<u32, DatabaseError> {
+        // the reference count is kept up to date by triggers on the loks table
+        let count: i64 = sqlx::query_scalar("SELECT COALESCE((SELECT ref_count FROM images WHERE path = ?), 0)")
+            .bind(image_path)
+            .fetch_one(&self.database)
+            .await?;
+
+        Ok(count as u32)
+    }
 
-        data.iter().map(|raw_preview| {
-            PreviewLok::new_from_raw_preview_data(raw_preview)
-        }).collect()
+    async fn close(&self) {
+        self.database.close().await;
     }
 }
 
@@ -115,7 +362,7 @@ mod sqlite_backend_tests {
 
         let backend = task::block_on(SQLiteBackend::build("sqlite://test/test13.db")).unwrap();
 
-        let id = task::block_on(backend.insert(test::util::get_test_lok_1()));
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
 
         println!("{}", id);
 
@@ -128,9 +375,11 @@ mod sqlite_backend_tests {
 
         let backend = task::block_on(SQLiteBackend::build("sqlite://test/test15.db")).unwrap();
 
-        let id = task::block_on(backend.insert(test::util::get_test_lok_1()));
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+
+        let result = task::block_on(backend.update(id, &test::util::get_test_lok_2()));
 
-        task::block_on(backend.update(id, &test::util::get_test_lok_2()))
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -139,7 +388,7 @@ mod sqlite_backend_tests {
 
         let backend = task::block_on(SQLiteBackend::build("sqlite://test/test16.db")).unwrap();
 
-        let id = task::block_on(backend.insert(test::util::get_test_lok_1()));
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
 
         let lok = task::block_on(backend.get(id)).unwrap();
 
@@ -152,13 +401,13 @@ mod sqlite_backend_tests {
 
         let backend = task::block_on(SQLiteBackend::build("sqlite://test/test17.db")).unwrap();
 
-        let id = task::block_on(backend.insert(test::util::get_test_lok_1()));
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
 
-        task::block_on(backend.remove(id));
+        task::block_on(backend.remove(id)).unwrap();
 
         let lok = task::block_on(backend.get(id));
 
-        assert_eq!(lok, None)
+        assert_eq!(lok, Err(DatabaseError::NotFound))
     }
 
     #[test]
@@ -167,12 +416,139 @@ mod sqlite_backend_tests {
 
         let backend = task::block_on(SQLiteBackend::build("sqlite://test/test18.db")).unwrap();
 
-        let _id1 = task::block_on(backend.insert(test::util::get_test_lok_1()));
-        let _id2 = task::block_on(backend.insert(test::util::get_test_lok_2()));
+        let _id1 = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+        let _id2 = task::block_on(backend.insert(test::util::get_test_lok_2())).unwrap();
 
-        let mut previews = task::block_on(backend.get_all_previews());
+        let mut previews = task::block_on(backend.get_all_previews()).unwrap();
 
         assert_eq!(previews.pop().unwrap().get_name_pretty(), String::from("RRRR"));
         assert_eq!(previews.pop().unwrap().get_name_pretty(), String::from("TEST"));
     }
+
+    #[test]
+    fn missing_lok_returns_not_found() {
+        test::util::remove_test_db(19);
+
+        let backend = task::block_on(SQLiteBackend::build("sqlite://test/test19.db")).unwrap();
+
+        assert_eq!(task::block_on(backend.get(42)), Err(DatabaseError::NotFound));
+        assert_eq!(task::block_on(backend.update(42, &test::util::get_test_lok_1())), Err(DatabaseError::NotFound));
+        assert_eq!(task::block_on(backend.remove(42)), Err(DatabaseError::NotFound));
+    }
+
+    #[test]
+    fn missing_values_are_stored_as_null() {
+        test::util::remove_test_db(24);
+
+        let backend = task::block_on(SQLiteBackend::build("sqlite://test/test24.db")).unwrap();
+
+        let id = task::block_on(backend.insert(test::util::get_analogue_test_lok())).unwrap();
+
+        let lok = task::block_on(backend.get(id)).unwrap();
+        let preview = task::block_on(backend.get_all_previews()).unwrap().pop().unwrap();
+
+        assert_eq!(lok, test::util::get_analogue_test_lok());
+        assert_eq!(lok.address, None);
+        assert_eq!(preview.get_address(), None);
+        assert_eq!(lok.get_address_pretty(), preview.get_address_pretty());
+    }
+
+    #[test]
+    fn image_references_are_counted() {
+        test::util::remove_test_db(28);
+
+        let backend = task::block_on(SQLiteBackend::build("sqlite://test/test28.db")).unwrap();
+
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+        let id2 = task::block_on(backend.insert(test::util::get_test_lok_2())).unwrap();
+        task::block_on(backend.insert(test::util::get_test_lok_2())).unwrap();
+
+        assert_eq!(task::block_on(backend.count_image_references("somewhere")), Ok(2));
+        assert_eq!(task::block_on(backend.count_image_references("elsewhere")), Ok(0));
+
+        task::block_on(backend.update(id, &test::util::get_test_lok_2())).unwrap();
+        assert_eq!(task::block_on(backend.count_image_references("somewhere")), Ok(3));
+
+        task::block_on(backend.update(id, &test::util::get_test_lok_1())).unwrap();
+        task::block_on(backend.remove(id2)).unwrap();
+        assert_eq!(task::block_on(backend.count_image_references("somewhere")), Ok(1));
+    }
+    #[test]
+    fn originals_of_cropped_images_are_counted() {
+        test::util::remove_test_db(30);
+
+        let backend = task::block_on(SQLiteBackend::build("sqlite://test/test30.db")).unwrap();
+
+        let mut lok = test::util::get_test_lok_2();
+        lok.images[0].original = Some("uncropped".to_string());
+
+        let id = task::block_on(backend.insert(lok.clone())).unwrap();
+
+        assert_eq!(task::block_on(backend.get(id)), Ok(lok));
+        assert_eq!(task::block_on(backend.count_image_references("uncropped")), Ok(1));
+
+        task::block_on(backend.update(id, &test::util::get_test_lok_2())).unwrap();
+        assert_eq!(task::block_on(backend.count_image_references("uncropped")), Ok(0));
+        assert_eq!(task::block_on(backend.count_image_references("somewhere")), Ok(1));
+    }
+
+    #[test]
+    fn previews_are_queried_like_in_memory() {
+        test::util::remove_test_db(31);
+
+        let backend = task::block_on(SQLiteBackend::build("sqlite://test/test31.db")).unwrap();
+
+        for (name, address, lokmaus_name, producer) in [("BR 218", Some(218), "BR218", "Roco"), ("Köf II", None, "", "Märklin"), ("V 100", Some(44), "V100", ""), ("BR 44", Some(44), "", "Rocco"), ("100% Rabatt", Some(7), "A_B", "")] {
+            let lok = Lok::new_from_raw_data(name.to_string(), address, lokmaus_name.to_string(), producer.to_string(), "".to_string(), address.is_some(), Vec::new());
+            task::block_on(backend.insert(lok)).unwrap();
+        }
+
+        let previews = task::block_on(backend.get_all_previews()).unwrap();
+
+        for search in ["", "br", "44", "100%", "a_b", "_", "köf", "KOF", "roco", "marklim", "br 2", "br roco", "xyz",
+            "producer:roco", "-br", "addr:40..250 digital:yes", "-addr:..100", "digital:no", "decoder:ja", "\"br 218\"", "name:br -producer:rocco"] {
+            for sort in [PreviewSort::Address, PreviewSort::Name, PreviewSort::LokmausName, PreviewSort::Relevance] {
+                let query = PreviewQuery::new(PreviewFilter::from_search_input(search).unwrap(), sort);
+
+                for query in [query.clone(), query.paged(1, 2)] {
+                    assert_eq!(task::block_on(backend.query_previews(&query)), Ok(query.apply(previews.clone())), "{:?}", query);
+                }
+            }
+        }
+
+        // the typo still finds both producers
+        let query = PreviewQuery::new(PreviewFilter::from_search_input("rocco").unwrap(), PreviewSort::Relevance);
+        assert_eq!(task::block_on(backend.query_previews(&query)).unwrap().total, 2);
+    }
+
+    #[test]
+    fn search_index_follows_changes() {
+        test::util::remove_test_db(32);
+
+        let backend = task::block_on(SQLiteBackend::build("sqlite://test/test32.db")).unwrap();
+        let search = |text: &str| {
+            let query = PreviewQuery::new(PreviewFilter::from_search_input(text).unwrap(), PreviewSort::Relevance);
+            task::block_on(backend.query_previews(&query)).unwrap().previews.iter().map(PreviewLok::get_id).collect::<Vec<u32>>()
+        };
+
+        let id = task::block_on(backend.insert(test::util::get_test_lok_1())).unwrap();
+        let id2 = task::block_on(backend.insert(test::util::get_test_lok_2())).unwrap();
+
+        assert_eq!(search("kkle"), vec![id2]);
+
+        task::block_on(backend.update(id, &test::util::get_test_lok_2())).unwrap();
+        assert_eq!(search("kkle"), vec![id, id2]);
+
+        task::block_on(backend.remove(id2)).unwrap();
+        assert_eq!(search("kkle"), vec![id]);
+
+        // loks of an older database get their search text when it is opened
+        task::block_on(sqlx::query("UPDATE loks SET search_text = ''").execute(&backend.database)).unwrap();
+        assert_eq!(search("kkle"), Vec::<u32>::new());
+
+        task::block_on(backend.close());
+        let backend = task::block_on(SQLiteBackend::build("sqlite://test/test32.db")).unwrap();
+        let query = PreviewQuery::new(PreviewFilter::from_search_input("kkle").unwrap(), PreviewSort::Relevance);
+        assert_eq!(task::block_on(backend.query_previews(&query)).unwrap().total, 1);
+    }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::app::backend::database::lok::Lok;
+use crate::app::backend::database::lok::{Lok, LokImage};
 use crate::database::sqlite_db::SQLiteDB;
 use crate::database::Database;
 use async_std::task;
@@ -14,6 +14,11 @@ pub fn remove_test_db(index: i32) {
     }
 }
 
+pub fn remove_test_json(index: i32) {
+    std::fs::remove_file(format!("test/test{}.json", index)).ok();
+    std::fs::create_dir_all("test").ok();
+}
+
 pub fn build_db(index: i32) -> SQLiteDB {
     remove_test_db(index);
     std::thread::sleep(Duration::from_millis(AFTER_DELETE_WAIT_TIME));
@@ -21,9 +26,13 @@ pub fn build_db(index: i32) -> SQLiteDB {
 }
 
 pub fn get_test_lok_1() -> Lok {
-    Lok::new_from_raw_data("TEST".to_string(), 114141, "14TE".to_string(), "Roco".to_string(), "ÖBB".to_string(), true, "".to_string())
+    Lok::new_from_raw_data("TEST".to_string(), Some(114141), "14TE".to_string(), "Roco".to_string(), "ÖBB".to_string(), true, Vec::new())
 }
 
 pub fn get_test_lok_2() -> Lok {
-    Lok::new_from_raw_data("RRRR".to_string(), 100002, "ABCD".to_string(), "KKLE".to_string(), "DB".to_string(), false, "somewhere".to_string())
-}
\ No newline at end of file
+    Lok::new_from_raw_data("RRRR".to_string(), Some(100002), "ABCD".to_string(), "KKLE".to_string(), "DB".to_string(), false, vec![LokImage { is_primary: true, ..LokImage::new("somewhere".to_string()) }])
+}
+
+pub fn get_analogue_test_lok() -> Lok {
+    Lok::new_from_raw_data("ANLG".to_string(), None, "".to_string(), "".to_string(), "".to_string(), false, Vec::new())
+}
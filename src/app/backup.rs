@@ -0,0 +1,509 @@
+use crate::app::backend::any_backend::AnyBackend;
+use crate::app::backend::database::sqlite_db::SQLiteDB;
+use crate::app::backend::database::{Database, DatabaseError};
+use crate::app::data_location::DataLocation;
+use crate::app::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// File extension of backup archives.
+pub const BACKUP_FILE_EXTENSION: &str = "zip";
+/// Version of the archive layout, archives of newer versions are refused.
+const BACKUP_FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const DATABASE_FILE_NAME: &str = "lokbuch.db";
+const SETTINGS_FILE_NAME: &str = "settings.json";
+const IMAGES_DIR_PREFIX: &str = "images/";
+
+/// Describes the content of a backup archive.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct Manifest {
+    format_version: u32,
+    app_version: String,
+    schema_version: Option<i64>,
+    created: String,
+    /// Archive paths of every image, relative to the collection directory.
+    images: Vec<String>,
+}
+
+/// Represents an error upon creating or restoring a backup.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BackupError {
+    /// Only SQLite collections can be backed up.
+    Unsupported,
+    InvalidArchive(String),
+    NewerFormat(u32),
+    Database(DatabaseError),
+    Io(String),
+}
+
+impl Display for BackupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackupError::Unsupported => write!(f, "only SQLite collections can be backed up"),
+            BackupError::InvalidArchive(message) => write!(f, "invalid backup archive: {}", message),
+            BackupError::NewerFormat(version) => write!(f, "backup format {} is not supported", version),
+            BackupError::Database(err) => write!(f, "{}", err),
+            BackupError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<std::io::Error> for BackupError {
+    fn from(err: std::io::Error) -> Self {
+        BackupError::Io(err.to_string())
+    }
+}
+
+impl From<DatabaseError> for BackupError {
+    fn from(err: DatabaseError) -> Self {
+        BackupError::Database(err)
+    }
+}
+
+impl From<zip::result::ZipError> for BackupError {
+    fn from(err: zip::result::ZipError) -> Self {
+        match err {
+            zip::result::ZipError::Io(err) => BackupError::Io(err.to_string()),
+            err => BackupError::InvalidArchive(err.to_string()),
+        }
+    }
+}
+
+/// What ended up in a new backup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackupSummary {
+    pub images: usize,
+    /// Images the database refers to, that are missing or lie outside of the collection directory.
+    pub skipped_images: Vec<String>,
+}
+
+/// Returns a file name for a new backup, e.g. ```lokbuch-backup-2026-10-18_120000.zip```.
+pub fn default_file_name() -> String {
+    let datetime = chrono::Local::now().format("%Y-%m-%d_%H%M%S");
+
+    format!("lokbuch-backup-{}.{}", datetime, BACKUP_FILE_EXTENSION)
+}
+
+/// Writes the database at db_url, every image it refers to and the settings into one archive.
+/// The database is copied with SQLite's own means, so the collection can stay open meanwhile.
+/// The archive is written next to the target first and only renamed once it is complete.
+pub async fn create_backup(db_url: &str, location: &DataLocation, settings: &Settings, target: &Path) -> Result<BackupSummary, BackupError> {
+    if !AnyBackend::is_sqlite_url(db_url) {
+        return Err(BackupError::Unsupported);
+    }
+
+    let snapshot_path = sibling_path(target, "snapshot");
+    let archive_path = sibling_path(target, "tmp");
+    remove_file_if_exists(&snapshot_path)?;
+
+    SQLiteDB::snapshot(db_url, &snapshot_path).await?;
+
+    let result = write_archive(&snapshot_path, &archive_path, location, settings).await;
+
+    remove_file_if_exists(&snapshot_path)?;
+
+    match result {
+        Ok(summary) => {
+            std::fs::rename(&archive_path, target)?;
+            Ok(summary)
+        }
+        Err(err) => {
+            remove_file_if_exists(&archive_path)?;
+            Err(err)
+        }
+    }
+}
+
+async fn write_archive(snapshot_path: &Path, archive_path: &Path, location: &DataLocation, settings: &Settings) -> Result<BackupSummary, BackupError> {
+    let snapshot = SQLiteDB::build(&SQLiteDB::url_from_path(snapshot_path)).await?;
+    let schema_version = SQLiteDB::schema_version(snapshot.connection()).await?;
+    let stored_paths = SQLiteDB::image_paths(snapshot.connection()).await?;
+    snapshot.close().await;
+
+    let collection_dir = location.collection_dir();
+    let mut images = BTreeSet::new();
+    let mut skipped_images = Vec::new();
+
+    for stored_path in stored_paths {
+        let path = location.image_path(&stored_path);
+
+        match archive_name(&path, &collection_dir) {
+            Some(name) if path.is_file() => { images.insert(name); }
+            _ => {
+                println!("Image {} is not part of the backup", path.display());
+                skipped_images.push(stored_path);
+            }
+        }
+    }
+
+    let manifest = Manifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        created: chrono::Local::now().to_rfc3339(),
+        images: images.iter().cloned().collect(),
+    };
+
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // images are compressed already
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    let mut writer = ZipWriter::new(File::create(archive_path)?);
+
+    writer.start_file(MANIFEST_FILE_NAME, deflated)?;
+    writer.write_all(&serde_json::to_vec_pretty(&manifest).map_err(|err| BackupError::Io(err.to_string()))?)?;
+
+    writer.start_file(SETTINGS_FILE_NAME, deflated)?;
+    writer.write_all(&serde_json::to_vec_pretty(settings).map_err(|err| BackupError::Io(err.to_string()))?)?;
+
+    writer.start_file(DATABASE_FILE_NAME, deflated)?;
+    std::io::copy(&mut File::open(snapshot_path)?, &mut writer)?;
+
+    for image in &images {
+        writer.start_file(image.as_str(), stored)?;
+        std::io::copy(&mut File::open(collection_dir.join(image))?, &mut writer)?;
+    }
+
+    writer.finish()?.sync_all()?;
+
+    Ok(BackupSummary {
+        images: images.len(),
+        skipped_images,
+    })
+}
+
+/// Replaces the collection at db_path and its images with the content of a backup archive.
+/// The archive is extracted and its database is checked and migrated, before anything of the live collection is touched.
+/// The replaced database and images are kept next to the new ones with a timestamp.
+/// The collection must be closed beforehand.
+/// Returns the settings stored in the archive.
+pub async fn restore_backup(archive_path: &Path, db_path: &Path, location: &DataLocation) -> Result<Option<Settings>, BackupError> {
+    let collection_dir = location.collection_dir();
+    let datetime = chrono::Local::now().format("%Y-%m-%d_%H%M%S").to_string();
+    let staging_dir = collection_dir.join(format!(".restore-{}", datetime));
+
+    std::fs::create_dir_all(&staging_dir)?;
+
+    let staged = stage_archive(archive_path, &staging_dir).await;
+
+    let result = match staged {
+        Ok(settings) => replace_collection(&staging_dir, db_path, location, &datetime).map(|_| settings),
+        Err(err) => Err(err),
+    };
+
+    if let Err(err) = std::fs::remove_dir_all(&staging_dir) {
+        println!("Couldn't remove {}: {}", staging_dir.display(), err);
+    }
+
+    result
+}
+
+/// Extracts the archive into the staging directory and validates its content.
+async fn stage_archive(archive_path: &Path, staging_dir: &Path) -> Result<Option<Settings>, BackupError> {
+    let mut archive = ZipArchive::new(File::open(archive_path)?)?;
+
+    let manifest: Manifest = {
+        let mut file = archive.by_name(MANIFEST_FILE_NAME)
+            .map_err(|_| BackupError::InvalidArchive(format!("{} is missing", MANIFEST_FILE_NAME)))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        serde_json::from_str(&contents).map_err(|err| BackupError::InvalidArchive(err.to_string()))?
+    };
+
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(BackupError::NewerFormat(manifest.format_version));
+    }
+
+    if let Some(version) = manifest.schema_version {
+        if version > SQLiteDB::supported_schema_version() {
+            return Err(BackupError::Database(DatabaseError::NewerSchema(version)));
+        }
+    }
+
+    let mut settings = None;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+
+        let name = file.enclosed_name()
+            .and_then(|name| name.to_str().map(|name| name.replace('\\', "/")))
+            .ok_or_else(|| BackupError::InvalidArchive(format!("unsafe path {}", file.name())))?;
+
+        if file.is_dir() || name == MANIFEST_FILE_NAME {
+            continue;
+        }
+
+        if name == SETTINGS_FILE_NAME {
+            let mut contents = String::new();
+            file.read_to_string(&mut contents)?;
+
+            // settings are optional, a broken settings file does not prevent the restore
+            settings = serde_json::from_str(&contents).ok();
+        } else if name == DATABASE_FILE_NAME || name.starts_with(IMAGES_DIR_PREFIX) {
+            let path = staging_dir.join(&name);
+
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+
+            std::io::copy(&mut file, &mut File::create(path)?)?;
+        } else {
+            println!("Ignoring {} in backup", name);
+        }
+    }
+
+    let staged_db = staging_dir.join(DATABASE_FILE_NAME);
+
+    if !staged_db.is_file() {
+        return Err(BackupError::InvalidArchive(format!("{} is missing", DATABASE_FILE_NAME)));
+    }
+
+    if let Some(image) = manifest.images.iter().find(|image| !staging_dir.join(image).is_file()) {
+        return Err(BackupError::InvalidArchive(format!("{} is missing", image)));
+    }
+
+    // building the database applies the migrations an older backup is missing
+    let database = SQLiteDB::build(&SQLiteDB::url_from_path(&staged_db)).await?;
+    let problems = SQLiteDB::integrity_check(database.connection()).await;
+
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(database.connection())
+        .await
+        .map_err(DatabaseError::from)?;
+    database.close().await;
+
+    let problems = problems?;
+
+    if !problems.is_empty() {
+        return Err(BackupError::InvalidArchive(problems.join(", ")));
+    }
+
+    Ok(settings)
+}
+
+/// Moves the live database and images aside and the staged ones into their place.
+/// The images are replaced first, if anything fails the live images and database are moved back,
+/// so the database never refers to the images of another state.
+fn replace_collection(staging_dir: &Path, db_path: &Path, location: &DataLocation, datetime: &str) -> Result<(), BackupError> {
+    let images_dir = location.images_dir();
+    let old_images_dir = PathBuf::from(format!("{}.old-{}", images_dir.display(), datetime));
+    let staged_images = staging_dir.join(IMAGES_DIR_PREFIX);
+
+    if images_dir.exists() {
+        std::fs::rename(&images_dir, &old_images_dir)?;
+    }
+
+    let result = if staged_images.exists() {
+        move_file(&staged_images, &images_dir)
+    } else {
+        std::fs::create_dir_all(&images_dir).map_err(BackupError::from)
+    };
+
+    if let Err(err) = result.and_then(|_| replace_database(staging_dir, db_path)) {
+        if let Err(err) = std::fs::remove_dir_all(&images_dir) {
+            println!("Couldn't remove {}: {}", images_dir.display(), err);
+        }
+
+        if old_images_dir.exists() {
+            std::fs::rename(&old_images_dir, &images_dir)?;
+        }
+
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Moves the live database aside and the staged one into its place, or the live one back on failure.
+fn replace_database(staging_dir: &Path, db_path: &Path) -> Result<(), BackupError> {
+    let moved = SQLiteDB::move_aside(db_path)?;
+
+    let result = match db_path.parent() {
+        Some(dir) => std::fs::create_dir_all(dir).map_err(BackupError::from),
+        None => Ok(()),
+    }.and_then(|_| move_file(&staging_dir.join(DATABASE_FILE_NAME), db_path));
+
+    if let Err(err) = result {
+        if let Some(moved) = moved {
+            SQLiteDB::move_back(&moved, db_path)?;
+        }
+
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Renames a file, or copies it if it has to change the file system.
+fn move_file(from: &Path, to: &Path) -> Result<(), BackupError> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            move_file(&entry.path(), &to.join(entry.file_name()))?;
+        }
+
+        Ok(())
+    } else {
+        std::fs::copy(from, to)?;
+        Ok(())
+    }
+}
+
+/// Returns the path of an image inside the archive, with forward slashes.
+/// Returns ```None``` for images outside of the collection directory.
+fn archive_name(path: &Path, collection_dir: &Path) -> Option<String> {
+    let relative = path.strip_prefix(collection_dir).ok()?;
+
+    let parts: Vec<&str> = relative.components()
+        .map(|component| match component {
+            std::path::Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<&str>>>()?;
+
+    Some(parts.join("/")).filter(|name| name.starts_with(IMAGES_DIR_PREFIX))
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    PathBuf::from(format!("{}.{}", path.display(), suffix))
+}
+
+fn remove_file_if_exists(path: &Path) -> Result<(), BackupError> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod backup_tests {
+    use crate::app::backend::any_backend::AnyBackend;
+    use crate::app::backend::database::lok::{Lok, LokImage};
+    use crate::app::backend::resource_manager::LokResourceManager;
+    use crate::app::backup::{create_backup, replace_collection, restore_backup, BackupError};
+    use crate::app::data_location::DataLocation;
+    use crate::app::settings::Settings;
+    use async_std::task;
+    use std::path::{Path, PathBuf};
+
+    fn test_location(index: u32) -> DataLocation {
+        let dir = PathBuf::from(format!("test/backup{}", index));
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let location = DataLocation::new(dir);
+        location.create_dirs().unwrap();
+        location
+    }
+
+    fn lok_with_image(name: &str, image_path: &str) -> Lok {
+        Lok::new_from_raw_data(name.to_string(), Some(3), "".to_string(), "".to_string(), "".to_string(), true, vec![LokImage::new(image_path.to_string())])
+    }
+
+    fn open(location: &DataLocation) -> LokResourceManager<AnyBackend> {
+        task::block_on(LokResourceManager::<AnyBackend>::build(&location.db_url())).unwrap()
+    }
+
+    #[test]
+    fn backup_can_be_restored() {
+        let location = test_location(1);
+        let archive = Path::new("test/backup1.zip");
+        std::fs::write(location.image_path("images/first.png"), b"first image").unwrap();
+
+        let lrm = open(&location);
+        task::block_on(lrm.handle().add_lok(lok_with_image("FIRST", "images/first.png"))).unwrap();
+        task::block_on(lrm.handle().add_lok(lok_with_image("GONE", "images/missing.png"))).unwrap();
+
+        let settings = Settings {
+            language: "de".to_string(),
+            ..Settings::default()
+        };
+
+        let summary = task::block_on(create_backup(&location.db_url(), &location, &settings, archive)).unwrap();
+
+        assert_eq!(summary.images, 1);
+        assert_eq!(summary.skipped_images, vec!["images/missing.png".to_string()]);
+
+        // changes after the backup are undone by the restore
+        task::block_on(lrm.handle().add_lok(lok_with_image("LATER", ""))).unwrap();
+        std::fs::remove_file(location.image_path("images/first.png")).unwrap();
+        task::block_on(lrm.handle().close());
+
+        let db_path = AnyBackend::path_from_url(&location.db_url());
+        let restored = task::block_on(restore_backup(archive, &db_path, &location)).unwrap();
+
+        assert_eq!(restored.map(|settings| settings.language), Some("de".to_string()));
+        assert_eq!(std::fs::read(location.image_path("images/first.png")).unwrap(), b"first image");
+
+        let lrm = open(&location);
+        let mut names: Vec<String> = lrm.get_previews().iter().map(|preview| preview.get_name_pretty()).collect();
+        names.sort();
+
+        assert_eq!(names, vec!["FIRST".to_string(), "GONE".to_string()]);
+    }
+
+    #[test]
+    fn invalid_archive_leaves_collection_untouched() {
+        let location = test_location(2);
+        let archive = Path::new("test/backup2.zip");
+        std::fs::write(archive, b"not an archive").unwrap();
+
+        let lrm = open(&location);
+        task::block_on(lrm.handle().add_lok(lok_with_image("KEEP", ""))).unwrap();
+        task::block_on(lrm.handle().close());
+
+        let db_path = AnyBackend::path_from_url(&location.db_url());
+        let result = task::block_on(restore_backup(archive, &db_path, &location));
+
+        assert!(matches!(result, Err(BackupError::InvalidArchive(_))));
+        assert_eq!(open(&location).number_of_loks(), 1);
+    }
+
+    #[test]
+    fn failed_replacement_keeps_collection() {
+        let location = test_location(4);
+        let staging_dir = PathBuf::from("test/backup4/.restore-test");
+        std::fs::write(location.image_path("images/live.png"), b"live image").unwrap();
+        // the staged database is missing, so moving it into place fails after the images were replaced
+        std::fs::create_dir_all(staging_dir.join("images")).unwrap();
+        std::fs::write(staging_dir.join("images/staged.png"), b"staged image").unwrap();
+
+        let lrm = open(&location);
+        task::block_on(lrm.handle().add_lok(lok_with_image("KEEP", "images/live.png"))).unwrap();
+        task::block_on(lrm.handle().close());
+
+        let db_path = AnyBackend::path_from_url(&location.db_url());
+        let result = replace_collection(&staging_dir, &db_path, &location, "test");
+
+        assert!(matches!(result, Err(BackupError::Io(_))));
+        assert_eq!(std::fs::read(location.image_path("images/live.png")).unwrap(), b"live image");
+        assert!(!location.image_path("images/staged.png").exists());
+        assert_eq!(open(&location).number_of_loks(), 1);
+    }
+
+    #[test]
+    fn memory_collection_cannot_be_backed_up() {
+        let location = test_location(3);
+
+        let result = task::block_on(create_backup("memory://", &location, &Settings::default(), Path::new("test/backup3.zip")));
+
+        assert_eq!(result, Err(BackupError::Unsupported));
+    }
+}
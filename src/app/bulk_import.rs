@@ -0,0 +1,141 @@
+use crate::app::backend::database::preview_lok::PreviewLok;
+use std::path::{Path, PathBuf};
+
+/// File extensions of the images a folder is scanned for.
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// An image file of the scanned folder and the lok it will be attached to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportMatch {
+    pub file: PathBuf,
+    /// The suggested lok at first, ```None``` if the image is skipped.
+    pub lok_id: Option<u32>,
+}
+
+/// Returns the image files of a folder, sorted by name.
+/// Sub folders are not scanned.
+pub fn scan(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && has_image_extension(path))
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Suggests a lok for every file.
+pub fn suggest_matches(files: Vec<PathBuf>, previews: &[PreviewLok]) -> Vec<ImportMatch> {
+    files.into_iter()
+        .map(|file| {
+            let lok_id = suggest(&file, previews);
+            ImportMatch { file, lok_id }
+        })
+        .collect()
+}
+
+/// Suggests the lok the file name fits best, ```None``` if no lok or more than one fits equally well.
+/// A file named exactly like a lok or its LOKmaus name fits best, followed by file names that contain
+/// the name of a lok or its LOKmaus name as a word, and file names that contain the address as a word.
+fn suggest(file: &Path, previews: &[PreviewLok]) -> Option<u32> {
+    let stem = file.file_stem()?.to_string_lossy().to_string();
+    let normalized_stem = normalize(&stem);
+    let words: Vec<String> = stem.split(|c: char| !c.is_alphanumeric()).map(normalize).filter(|word| !word.is_empty()).collect();
+
+    let scored: Vec<((u8, usize), u32)> = previews.iter()
+        .filter_map(|preview| {
+            let name = preview.get_name().map(|name| normalize(&name)).filter(|name| !name.is_empty());
+            let lokmaus_name = preview.get_lokmaus_name().map(|name| normalize(&name)).filter(|name| !name.is_empty());
+            let address = preview.get_address().map(|address| address.to_string());
+
+            let score = if name.as_ref() == Some(&normalized_stem) || lokmaus_name.as_ref() == Some(&normalized_stem) {
+                (3, 0)
+            } else if let Some(name) = name.as_ref().filter(|name| normalized_stem.contains(name.as_str())) {
+                // a longer name is more specific, BR2181 beats BR218
+                (2, name.len())
+            } else if lokmaus_name.is_some_and(|lokmaus_name| words.contains(&lokmaus_name)) {
+                (2, 0)
+            } else if address.is_some_and(|address| words.contains(&address)) {
+                (1, 0)
+            } else {
+                return None;
+            };
+
+            Some((score, preview.get_id()))
+        })
+        .collect();
+
+    let best = scored.iter().map(|(score, _)| *score).max()?;
+    let mut best_ids = scored.iter().filter(|(score, _)| *score == best).map(|(_, id)| *id);
+
+    match (best_ids.next(), best_ids.next()) {
+        (Some(id), None) => Some(id),
+        _ => None,
+    }
+}
+
+fn has_image_extension(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str()))
+}
+
+/// Lowercases a text and drops everything but letters and digits, so "BR 218" and "br-218" are equal.
+fn normalize(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+#[cfg(test)]
+mod bulk_import_tests {
+    use crate::app::backend::database::preview_lok::PreviewLok;
+    use crate::app::bulk_import::{scan, suggest_matches, ImportMatch};
+    use std::path::PathBuf;
+
+    fn previews() -> Vec<PreviewLok> {
+        vec![
+            PreviewLok::new(1, Some(218), Some("BR 218".to_string()), Some("BR218".to_string())),
+            PreviewLok::new(2, Some(2181), Some("BR 218.1".to_string()), None),
+            PreviewLok::new(3, Some(3), Some("Köf II".to_string()), Some("KOEF".to_string())),
+            PreviewLok::new(4, Some(44), Some("V 100 Ost".to_string()), None),
+            PreviewLok::new(5, Some(45), Some("V 100 West".to_string()), None),
+        ]
+    }
+
+    fn suggestion(file_name: &str) -> Option<u32> {
+        suggest_matches(vec![PathBuf::from(file_name)], &previews()).pop().unwrap().lok_id
+    }
+
+    #[test]
+    fn files_are_matched_by_name_lokmaus_name_and_address() {
+        assert_eq!(suggestion("br-218.jpg"), Some(1));
+        assert_eq!(suggestion("BR218_front.jpg"), Some(1));
+        assert_eq!(suggestion("br_218_1_box.png"), Some(2));
+        assert_eq!(suggestion("koef side.jpeg"), Some(3));
+        assert_eq!(suggestion("IMG 44.jpg"), Some(4));
+    }
+
+    #[test]
+    fn unclear_files_are_not_matched() {
+        // both addresses fit equally well
+        assert_eq!(suggestion("IMG_44_45.jpg"), None);
+        assert_eq!(suggestion("V 100.jpg"), None);
+        assert_eq!(suggestion("IMG_0001.jpg"), None);
+    }
+
+    #[test]
+    fn only_images_are_scanned() {
+        let dir = PathBuf::from("test/bulk_import");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(dir.join("nested.png")).unwrap();
+
+        for file in ["b.JPG", "a.png", "notes.txt", "c.jpeg"] {
+            std::fs::write(dir.join(file), file).unwrap();
+        }
+
+        let files = scan(&dir).unwrap();
+
+        assert_eq!(files, vec![dir.join("a.png"), dir.join("b.JPG"), dir.join("c.jpeg")]);
+        assert_eq!(suggest_matches(files, &[])[0], ImportMatch { file: dir.join("a.png"), lok_id: None });
+    }
+}
@@ -0,0 +1,229 @@
+use crate::app::backend::database::sqlite_db::COMPANION_FILE_SUFFIXES;
+use crate::app::data_location::DataLocation;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Collection names are used as directory names, so they are kept short.
+const MAX_NAME_LENGTH: usize = 64;
+/// Characters that are not allowed in file names on at least one platform.
+const FORBIDDEN_CHARACTERS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Represents an error upon managing the named collections.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CollectionError {
+    InvalidName,
+    AlreadyExists,
+    NotFound,
+    Io(String),
+}
+
+impl Display for CollectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CollectionError::InvalidName => write!(f, "invalid collection name"),
+            CollectionError::AlreadyExists => write!(f, "collection already exists"),
+            CollectionError::NotFound => write!(f, "collection not found"),
+            CollectionError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CollectionError {}
+
+impl From<std::io::Error> for CollectionError {
+    fn from(err: std::io::Error) -> Self {
+        CollectionError::Io(err.to_string())
+    }
+}
+
+/// Checks whether the name can be used as a directory name and returns it without surrounding whitespace.
+pub fn validate_name(name: &str) -> Result<String, CollectionError> {
+    let name = name.trim();
+
+    if name.is_empty()
+        || name.chars().count() > MAX_NAME_LENGTH
+        || name.starts_with('.')
+        || name.contains(FORBIDDEN_CHARACTERS)
+        || name.chars().any(char::is_control) {
+        return Err(CollectionError::InvalidName);
+    }
+
+    Ok(name.to_string())
+}
+
+/// Returns the names of all named collections, sorted alphabetically.
+/// The default collection is not part of the list.
+pub fn list(location: &DataLocation) -> Result<Vec<String>, CollectionError> {
+    let dir = location.collections_dir();
+
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+
+        if entry.file_type()?.is_dir() {
+            if let Some(name) = entry.file_name().to_str() {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names.sort_by_key(|name| name.to_lowercase());
+
+    Ok(names)
+}
+
+/// Creates the directories of a new collection, its database is created when it is opened.
+pub fn create(location: &DataLocation, name: &str) -> Result<String, CollectionError> {
+    let name = validate_name(name)?;
+    let collection = location.with_collection(Some(name.clone()));
+
+    if collection.collection_dir().exists() {
+        return Err(CollectionError::AlreadyExists);
+    }
+
+    fs::create_dir_all(collection.images_dir())?;
+
+    Ok(name)
+}
+
+/// Renames a collection together with its images.
+pub fn rename(location: &DataLocation, name: &str, new_name: &str) -> Result<String, CollectionError> {
+    let new_name = validate_name(new_name)?;
+    let from = location.with_collection(Some(name.to_string())).collection_dir();
+    let to = location.with_collection(Some(new_name.clone())).collection_dir();
+
+    if !from.is_dir() {
+        return Err(CollectionError::NotFound);
+    }
+
+    if to.exists() {
+        return Err(CollectionError::AlreadyExists);
+    }
+
+    fs::rename(from, to)?;
+
+    Ok(new_name)
+}
+
+/// Deletes a collection with its database and all of its images.
+pub fn delete(location: &DataLocation, name: &str) -> Result<(), CollectionError> {
+    let dir = location.with_collection(Some(name.to_string())).collection_dir();
+
+    if !dir.is_dir() {
+        return Err(CollectionError::NotFound);
+    }
+
+    fs::remove_dir_all(dir)?;
+
+    Ok(())
+}
+
+/// Renames an existing collection file, so that a new collection file can be created in its place.
+/// The replaced file is kept next to it with a timestamp, together with the files SQLite keeps next to it.
+/// Returns the new path of the replaced file, or ```None``` if there was no file.
+pub fn move_replaced_file(path: &Path) -> Result<Option<PathBuf>, CollectionError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let datetime = chrono::Local::now().format("%Y-%m-%d_%H%M%S").to_string();
+    let moved_path = PathBuf::from(format!("{}.replaced-{}", path.display(), datetime));
+
+    fs::rename(path, &moved_path)?;
+
+    for suffix in COMPANION_FILE_SUFFIXES {
+        let companion = PathBuf::from(format!("{}{}", path.display(), suffix));
+
+        if companion.exists() {
+            fs::rename(&companion, format!("{}{}", moved_path.display(), suffix))?;
+        }
+    }
+
+    Ok(Some(moved_path))
+}
+
+#[cfg(test)]
+mod collections_tests {
+    use crate::app::collections::{create, delete, list, move_replaced_file, rename, validate_name, CollectionError};
+    use crate::app::data_location::DataLocation;
+    use std::path::PathBuf;
+
+    fn test_location(index: u32) -> DataLocation {
+        let dir = PathBuf::from(format!("test/collections{}", index));
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        DataLocation::new(dir)
+    }
+
+    #[test]
+    fn names_are_validated() {
+        assert_eq!(validate_name("  Club layout "), Ok("Club layout".to_string()));
+        assert_eq!(validate_name("Märklin H0"), Ok("Märklin H0".to_string()));
+
+        assert_eq!(validate_name(""), Err(CollectionError::InvalidName));
+        assert_eq!(validate_name("   "), Err(CollectionError::InvalidName));
+        assert_eq!(validate_name(".."), Err(CollectionError::InvalidName));
+        assert_eq!(validate_name("a/b"), Err(CollectionError::InvalidName));
+        assert_eq!(validate_name("a\\b"), Err(CollectionError::InvalidName));
+        assert_eq!(validate_name(&"x".repeat(65)), Err(CollectionError::InvalidName));
+    }
+
+    #[test]
+    fn create_rename_and_delete_work() {
+        let location = test_location(1);
+
+        assert_eq!(list(&location), Ok(vec![]));
+
+        assert_eq!(create(&location, "Club"), Ok("Club".to_string()));
+        assert_eq!(create(&location, "anna"), Ok("anna".to_string()));
+        assert_eq!(create(&location, "Club"), Err(CollectionError::AlreadyExists));
+
+        assert_eq!(list(&location), Ok(vec!["anna".to_string(), "Club".to_string()]));
+        assert!(location.with_collection(Some("Club".to_string())).images_dir().is_dir());
+
+        assert_eq!(rename(&location, "anna", "Club"), Err(CollectionError::AlreadyExists));
+        assert_eq!(rename(&location, "bert", "Bert"), Err(CollectionError::NotFound));
+        assert_eq!(rename(&location, "anna", "Berta"), Ok("Berta".to_string()));
+
+        assert_eq!(list(&location), Ok(vec!["Berta".to_string(), "Club".to_string()]));
+
+        assert_eq!(delete(&location, "Club"), Ok(()));
+        assert_eq!(delete(&location, "Club"), Err(CollectionError::NotFound));
+
+        assert_eq!(list(&location), Ok(vec!["Berta".to_string()]));
+    }
+
+    #[test]
+    fn collections_have_their_own_database_and_images() {
+        let location = test_location(2);
+        let collection = location.with_collection(Some("Club".to_string()));
+
+        assert_ne!(location.db_url(), collection.db_url());
+        assert_ne!(location.images_dir(), collection.images_dir());
+        assert!(collection.collection_dir().starts_with(location.collections_dir()));
+        assert_eq!(collection.image_path("images/a.png"), collection.collection_dir().join("images/a.png"));
+    }
+
+    #[test]
+    fn replaced_files_are_kept() {
+        let location = test_location(3);
+        std::fs::create_dir_all(location.collections_dir()).unwrap();
+        let path = location.collections_dir().join("lokbuch.json");
+
+        assert_eq!(move_replaced_file(&path), Ok(None));
+
+        std::fs::write(&path, b"old").unwrap();
+        let moved = move_replaced_file(&path).unwrap().unwrap();
+
+        assert!(!path.exists());
+        assert!(moved.to_string_lossy().contains("lokbuch.json.replaced-"));
+        assert_eq!(std::fs::read(moved).unwrap(), b"old");
+    }
+}
@@ -0,0 +1,297 @@
+use crate::app::backend::database::sqlite_db::SQLiteDB;
+use crate::app::settings::Settings;
+use std::path::{Path, PathBuf};
+
+/// Name of the directory inside the platform data directory.
+const APP_DIR_NAME: &str = "lokbuch";
+/// Name of the data directory next to the executable in portable mode.
+const PORTABLE_DIR_NAME: &str = "data";
+/// If this file exists next to the executable, Lokbuch runs in portable mode.
+const PORTABLE_MARKER_FILE: &str = "portable";
+/// Directory relative to the working directory, that was used by older versions.
+const LEGACY_DIR: &str = "data";
+/// Prefix of image paths stored by older versions, they are relative to the working directory.
+const LEGACY_IMAGE_PREFIX: &str = "./data/";
+
+const DB_FILE_NAME: &str = "lokbuch.db";
+const SETTINGS_FILE_NAME: &str = "settings.json";
+const IMAGES_DIR_NAME: &str = "images";
+const COLLECTIONS_DIR_NAME: &str = "collections";
+const SNAPSHOTS_DIR_NAME: &str = "snapshots";
+const THUMBNAILS_DIR_NAME: &str = "thumbnails";
+
+/// Options given on the command line.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Arguments {
+    pub data_dir: Option<PathBuf>,
+    pub portable: bool,
+    pub demo: bool,
+}
+
+impl Arguments {
+    /// Parses ```--data-dir <path>```, ```--portable``` and ```--demo```.
+    /// Unknown arguments are ignored.
+    pub fn parse(args: impl IntoIterator<Item=String>) -> Arguments {
+        let mut arguments = Arguments::default();
+        let mut args = args.into_iter().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--data-dir" => { arguments.data_dir = args.next().map(PathBuf::from); }
+                "--portable" => { arguments.portable = true; }
+                "--demo" => { arguments.demo = true; }
+                _ => {
+                    if let Some(dir) = arg.strip_prefix("--data-dir=") {
+                        arguments.data_dir = Some(PathBuf::from(dir));
+                    }
+                }
+            }
+        }
+
+        arguments
+    }
+}
+
+/// Knows where Lokbuch keeps its database, its images and its settings.
+///
+/// The settings always live in the base directory, which is chosen in this order:
+/// the ```--data-dir``` argument, the portable directory next to the executable,
+/// the ```data``` directory of older versions if it holds a database, and the platform data directory.
+/// The data directory can be moved away from the base directory in the settings.
+/// The default collection lives directly in the data directory, every named collection
+/// has its own directory with a database and images below ```collections```.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataLocation {
+    base_dir: PathBuf,
+    data_dir: PathBuf,
+    collection: Option<String>,
+    is_fixed: bool,
+}
+
+impl DataLocation {
+    pub fn resolve(arguments: &Arguments) -> DataLocation {
+        if let Some(dir) = &arguments.data_dir {
+            return DataLocation::fixed(dir.clone());
+        }
+
+        let executable_dir = std::env::current_exe()
+            .ok()
+            .and_then(|executable| executable.parent().map(Path::to_path_buf));
+
+        if let Some(executable_dir) = executable_dir {
+            if arguments.portable || executable_dir.join(PORTABLE_MARKER_FILE).exists() {
+                return DataLocation::new(executable_dir.join(PORTABLE_DIR_NAME));
+            }
+        }
+
+        let platform_dir = dirs::data_dir().map(|dir| dir.join(APP_DIR_NAME));
+        let legacy_dir = std::env::current_dir().map(|dir| dir.join(LEGACY_DIR)).ok();
+
+        DataLocation::new(DataLocation::choose_default_dir(platform_dir, legacy_dir))
+    }
+
+    /// Prefers the platform directory, but keeps using the directory of older versions as long as only it holds a database.
+    fn choose_default_dir(platform_dir: Option<PathBuf>, legacy_dir: Option<PathBuf>) -> PathBuf {
+        match (platform_dir, legacy_dir) {
+            (Some(platform_dir), Some(legacy_dir)) => {
+                if !platform_dir.join(DB_FILE_NAME).exists() && legacy_dir.join(DB_FILE_NAME).exists() {
+                    legacy_dir
+                } else {
+                    platform_dir
+                }
+            }
+            (Some(platform_dir), None) => platform_dir,
+            (None, Some(legacy_dir)) => legacy_dir,
+            (None, None) => PathBuf::from(LEGACY_DIR),
+        }
+    }
+
+    pub fn new(base_dir: PathBuf) -> DataLocation {
+        DataLocation {
+            data_dir: base_dir.clone(),
+            base_dir,
+            collection: None,
+            is_fixed: false,
+        }
+    }
+
+    /// A location given on the command line, it cannot be changed in the settings.
+    fn fixed(base_dir: PathBuf) -> DataLocation {
+        DataLocation {
+            is_fixed: true,
+            ..DataLocation::new(base_dir)
+        }
+    }
+
+    /// Applies the data directory and the collection chosen in the settings.
+    pub fn with_settings(&self, settings: &Settings) -> DataLocation {
+        let data_dir = match &settings.data_dir {
+            Some(data_dir) if !self.is_fixed => data_dir.clone(),
+            _ => self.base_dir.clone(),
+        };
+
+        DataLocation {
+            data_dir,
+            ..self.with_collection(settings.collection.clone())
+        }
+    }
+
+    /// Selects a named collection, ```None``` selects the default collection.
+    pub fn with_collection(&self, collection: Option<String>) -> DataLocation {
+        DataLocation {
+            collection,
+            ..self.clone()
+        }
+    }
+
+    /// Returns true, if the data directory was given on the command line.
+    pub fn is_fixed(&self) -> bool {
+        self.is_fixed
+    }
+
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Returns the name of the selected collection, ```None``` for the default collection.
+    pub fn collection(&self) -> Option<&str> {
+        self.collection.as_deref()
+    }
+
+    pub fn collections_dir(&self) -> PathBuf {
+        self.data_dir.join(COLLECTIONS_DIR_NAME)
+    }
+
+    /// Returns the directory holding the database and the images of the selected collection.
+    pub fn collection_dir(&self) -> PathBuf {
+        match &self.collection {
+            Some(collection) => self.collections_dir().join(collection),
+            None => self.data_dir.clone(),
+        }
+    }
+
+    pub fn settings_path(&self) -> PathBuf {
+        self.base_dir.join(SETTINGS_FILE_NAME)
+    }
+
+    pub fn db_url(&self) -> String {
+        SQLiteDB::url_from_path(&self.collection_dir().join(DB_FILE_NAME))
+    }
+
+    /// Returns the location, if the database at db_url may delete images from the image directory.
+    /// Only the database of the selected collection owns them, the demo and collection files
+    /// opened from elsewhere share the directory and may refer to the same images.
+    pub fn owned_by(&self, db_url: &str) -> Option<DataLocation> {
+        (db_url == self.db_url()).then(|| self.clone())
+    }
+
+    pub fn images_dir(&self) -> PathBuf {
+        self.collection_dir().join(IMAGES_DIR_NAME)
+    }
+
+    /// Returns the directory of the automatic snapshots of the selected collection.
+    pub fn snapshots_dir(&self) -> PathBuf {
+        self.collection_dir().join(SNAPSHOTS_DIR_NAME)
+    }
+
+    /// Returns the directory of the cached thumbnails of the selected collection.
+    pub fn thumbnails_dir(&self) -> PathBuf {
+        self.collection_dir().join(THUMBNAILS_DIR_NAME)
+    }
+
+    /// Returns the path of the cached thumbnail of a stored image, the file may not exist yet.
+    pub fn thumbnail_path(&self, stored_path: &str) -> PathBuf {
+        let file_name = Path::new(stored_path).file_name().unwrap_or_default();
+
+        self.thumbnails_dir().join(file_name)
+    }
+
+    /// Returns the path an image is stored with, relative to the collection directory.
+    pub fn stored_image_path(file_name: &str) -> String {
+        format!("{}/{}", IMAGES_DIR_NAME, file_name)
+    }
+
+    /// Resolves a stored image path to a file path.
+    /// Supports absolute paths and the paths stored by older versions.
+    pub fn image_path(&self, stored_path: &str) -> PathBuf {
+        let path = Path::new(stored_path);
+
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else if let Some(relative) = stored_path.strip_prefix(LEGACY_IMAGE_PREFIX) {
+            self.collection_dir().join(relative)
+        } else {
+            self.collection_dir().join(path)
+        }
+    }
+
+    /// Creates every directory Lokbuch writes to.
+    pub fn create_dirs(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.base_dir)?;
+        std::fs::create_dir_all(&self.data_dir)?;
+        std::fs::create_dir_all(self.images_dir())
+    }
+}
+
+impl Default for DataLocation {
+    fn default() -> Self {
+        DataLocation::new(PathBuf::from(LEGACY_DIR))
+    }
+}
+
+#[cfg(test)]
+mod data_location_tests {
+    use crate::app::data_location::{Arguments, DataLocation};
+    use crate::app::settings::Settings;
+    use std::path::PathBuf;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        std::iter::once("lokbuch").chain(args.iter().copied()).map(String::from).collect()
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        assert_eq!(Arguments::parse(args(&[])), Arguments::default());
+
+        let arguments = Arguments::parse(args(&["--data-dir", "/tmp/lokbuch", "--demo"]));
+        assert_eq!(arguments.data_dir, Some(PathBuf::from("/tmp/lokbuch")));
+        assert!(arguments.demo);
+        assert!(!arguments.portable);
+
+        let arguments = Arguments::parse(args(&["--portable", "--data-dir=/tmp/other", "--unknown"]));
+        assert_eq!(arguments.data_dir, Some(PathBuf::from("/tmp/other")));
+        assert!(arguments.portable);
+    }
+
+    #[test]
+    fn settings_move_the_data_dir() {
+        let mut settings = Settings {
+            data_dir: Some(PathBuf::from("/elsewhere")),
+            ..Settings::default()
+        };
+
+        let location = DataLocation::new(PathBuf::from("/base")).with_settings(&settings);
+        assert_eq!(location.data_dir(), PathBuf::from("/elsewhere"));
+        assert_eq!(location.settings_path(), PathBuf::from("/base/settings.json"));
+
+        let fixed = DataLocation::resolve(&Arguments {
+            data_dir: Some(PathBuf::from("/fixed")),
+            ..Arguments::default()
+        }).with_settings(&settings);
+        assert!(fixed.is_fixed());
+        assert_eq!(fixed.data_dir(), PathBuf::from("/fixed"));
+
+        settings.data_dir = None;
+        assert_eq!(location.with_settings(&settings).data_dir(), PathBuf::from("/base"));
+    }
+
+    #[test]
+    fn image_paths_are_resolved() {
+        let location = DataLocation::new(PathBuf::from("/base"));
+
+        assert_eq!(location.image_path("images/a.png"), PathBuf::from("/base/images/a.png"));
+        assert_eq!(location.image_path("./data/images/a.png"), PathBuf::from("/base/images/a.png"));
+        assert_eq!(location.image_path("/abs/a.png"), PathBuf::from("/abs/a.png"));
+        assert_eq!(DataLocation::stored_image_path("a.png"), "images/a.png");
+    }
+}
@@ -0,0 +1,69 @@
+use crate::app::backend::database::lok::LokImage;
+use crate::app::data_location::DataLocation;
+use crate::app::image_store;
+use crate::app::image_store::{Crop, ImageStoreError};
+use crate::app::message::Message;
+use iced::widget::image::Handle;
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+/// Longest side of the copy the crop is previewed on.
+const PREVIEW_SOURCE_SIZE: u32 = 800;
+
+/// Crops and rotates one image of the lok that is added or edited.
+/// The crop is previewed on a scaled down copy, only applying it crops the original itself.
+#[derive(Clone)]
+pub struct ImageEditor {
+    /// Index of the edited image in the images input.
+    pub index: usize,
+    /// Stored path of the uncropped image, every crop starts from it.
+    pub original: String,
+    pub crop: Crop,
+    preview_source: DynamicImage,
+    preview: Handle,
+}
+
+impl ImageEditor {
+    /// Opens the editor for an image.
+    /// Images that were cropped before are cropped again from their original.
+    pub fn open(location: &DataLocation, index: usize, lok_image: &LokImage) -> Result<ImageEditor, ImageStoreError> {
+        let original = lok_image.original.clone().unwrap_or_else(|| lok_image.path.clone());
+        let preview_source = image_store::load(location, &original)?
+            .resize(PREVIEW_SOURCE_SIZE, PREVIEW_SOURCE_SIZE, FilterType::Triangle);
+
+        let mut editor = ImageEditor {
+            index,
+            original,
+            crop: Crop::default(),
+            preview_source,
+            preview: Handle::from_rgba(1, 1, vec![0; 4]),
+        };
+        editor.render_preview();
+
+        Ok(editor)
+    }
+
+    /// Returns the preview of the image as it would be cropped now.
+    pub fn preview(&self) -> &Handle {
+        &self.preview
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::RotateImageLeft => { self.crop.rotate_counterclockwise(); }
+            Message::RotateImageRight => { self.crop.rotate_clockwise(); }
+            Message::CropZoomChanged(zoom) => { self.crop.zoom = zoom; }
+            Message::CropFocusXChanged(focus_x) => { self.crop.focus_x = focus_x; }
+            Message::CropFocusYChanged(focus_y) => { self.crop.focus_y = focus_y; }
+            _ => { return; }
+        }
+
+        self.render_preview();
+    }
+
+    fn render_preview(&mut self) {
+        let cropped = self.crop.apply(&self.preview_source).to_rgba8();
+
+        self.preview = Handle::from_rgba(cropped.width(), cropped.height(), cropped.into_raw());
+    }
+}
@@ -0,0 +1,419 @@
+use crate::app::backend::database::lok::LokImage;
+use crate::app::data_location::DataLocation;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, RgbaImage};
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// Neither side of a thumbnail is longer than this many pixels.
+pub const THUMBNAIL_SIZE: u32 = 256;
+/// Quality of re-encoded JPEG images, from 1 to 100.
+const JPEG_QUALITY: u8 = 85;
+/// Width divided by height of the image on the show page.
+pub const DISPLAY_ASPECT_RATIO: f32 = 2.0;
+/// Cropping zooms in at most this far.
+pub const MAX_CROP_ZOOM: f32 = 4.0;
+
+/// Represents an error upon importing an image.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImageStoreError {
+    /// The file is not an image or its format is not supported.
+    Decode(String),
+    Encode(String),
+    Io(String),
+}
+
+impl Display for ImageStoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageStoreError::Decode(message) => write!(f, "{}", message),
+            ImageStoreError::Encode(message) => write!(f, "{}", message),
+            ImageStoreError::Io(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ImageStoreError {}
+
+impl From<std::io::Error> for ImageStoreError {
+    fn from(err: std::io::Error) -> Self {
+        ImageStoreError::Io(err.to_string())
+    }
+}
+
+/// How an image is cut to the display aspect ratio.
+/// The image is rotated first, then the crop area is cut out of the rotated image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crop {
+    /// Clockwise rotation in steps of 90 degrees.
+    pub quarter_turns: u8,
+    /// 1.0 keeps the largest area of the display aspect ratio, larger values cut out less.
+    pub zoom: f32,
+    /// Center of the crop area, from 0.0 at the left or top to 1.0 at the right or bottom.
+    pub focus_x: f32,
+    pub focus_y: f32,
+}
+
+impl Default for Crop {
+    fn default() -> Self {
+        Crop {
+            quarter_turns: 0,
+            zoom: 1.0,
+            focus_x: 0.5,
+            focus_y: 0.5,
+        }
+    }
+}
+
+impl Crop {
+    pub fn rotate_clockwise(&mut self) {
+        self.quarter_turns = (self.quarter_turns + 1) % 4;
+    }
+
+    pub fn rotate_counterclockwise(&mut self) {
+        self.quarter_turns = (self.quarter_turns + 3) % 4;
+    }
+
+    /// Rotates the image and cuts out the crop area.
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let rotated = match self.quarter_turns % 4 {
+            1 => image.rotate90(),
+            2 => image.rotate180(),
+            3 => image.rotate270(),
+            _ => image.clone(),
+        };
+
+        let (x, y, width, height) = self.area(rotated.width(), rotated.height());
+        rotated.crop_imm(x, y, width, height)
+    }
+
+    /// Returns x, y, width and height of the crop area within an image of the given size.
+    fn area(&self, image_width: u32, image_height: u32) -> (u32, u32, u32, u32) {
+        let (full_width, full_height) = if image_width as f32 > image_height as f32 * DISPLAY_ASPECT_RATIO {
+            (image_height as f32 * DISPLAY_ASPECT_RATIO, image_height as f32)
+        } else {
+            (image_width as f32, image_width as f32 / DISPLAY_ASPECT_RATIO)
+        };
+
+        let zoom = self.zoom.clamp(1.0, MAX_CROP_ZOOM);
+        let width = ((full_width / zoom).round() as u32).clamp(1, image_width);
+        let height = ((full_height / zoom).round() as u32).clamp(1, image_height);
+
+        let x = ((image_width - width) as f32 * self.focus_x.clamp(0.0, 1.0)).round() as u32;
+        let y = ((image_height - height) as f32 * self.focus_y.clamp(0.0, 1.0)).round() as u32;
+
+        (x, y, width, height)
+    }
+}
+
+/// Imports an image file into the image directory of the collection and returns its stored path.
+/// The image is turned upright according to its EXIF orientation, scaled down to max_size and re-encoded.
+pub fn import(location: &DataLocation, source: &Path, max_size: u32) -> Result<String, ImageStoreError> {
+    store_image(location, decode(&fs::read(source)?)?, max_size)
+}
+
+/// Imports an image given as RGBA pixels, e.g. one pasted from the clipboard, and returns its stored path.
+/// Fully opaque images lose their alpha channel, so they are stored as JPEG like imported photos.
+pub fn import_pixels(location: &DataLocation, width: u32, height: u32, rgba: Vec<u8>, max_size: u32) -> Result<String, ImageStoreError> {
+    let image = RgbaImage::from_raw(width, height, rgba)
+        .ok_or_else(|| ImageStoreError::Decode(format!("{}x{} pixels don't match the image data", width, height)))?;
+
+    let image = if image.pixels().all(|pixel| pixel.0[3] == u8::MAX) {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(image).to_rgb8())
+    } else {
+        DynamicImage::ImageRgba8(image)
+    };
+
+    store_image(location, image, max_size)
+}
+
+/// Crops a stored image and stores the result as a new image, the original is left untouched.
+/// Returns the stored path of the cropped image.
+pub fn crop(location: &DataLocation, original: &str, crop: &Crop, max_size: u32) -> Result<String, ImageStoreError> {
+    store_image(location, crop.apply(&load(location, original)?), max_size)
+}
+
+/// Reads and decodes a stored image.
+pub fn load(location: &DataLocation, stored_path: &str) -> Result<DynamicImage, ImageStoreError> {
+    decode(&fs::read(location.image_path(stored_path))?)
+}
+
+/// Stores a decoded image and caches a thumbnail of it for lists and galleries.
+/// Returns the stored path of the image.
+/// Images with transparency are stored as PNG, all others as JPEG.
+pub fn store_image(location: &DataLocation, image: DynamicImage, max_size: u32) -> Result<String, ImageStoreError> {
+    let image = if image.width() > max_size || image.height() > max_size {
+        image.resize(max_size, max_size, FilterType::CatmullRom)
+    } else {
+        image
+    };
+
+    let (content, extension) = encode(&image)?;
+    let stored_path = store(location, &content, extension)?;
+
+    write_thumbnail(location, &stored_path, &image)?;
+
+    Ok(stored_path)
+}
+
+/// Returns the cached thumbnail of a stored image, or the image itself as long as there is no thumbnail.
+pub fn thumbnail_or_image(location: &DataLocation, stored_path: &str) -> PathBuf {
+    let thumbnail = location.thumbnail_path(stored_path);
+
+    if thumbnail.is_file() { thumbnail } else { location.image_path(stored_path) }
+}
+
+/// Creates the thumbnails that are missing, e.g. of images imported by older versions.
+/// Images that cannot be read keep their missing thumbnail.
+pub fn create_missing_thumbnails(location: &DataLocation, images: &[LokImage]) {
+    for image in images {
+        if location.thumbnail_path(&image.path).is_file() {
+            continue;
+        }
+
+        let result = load(location, &image.path)
+            .and_then(|decoded| write_thumbnail(location, &image.path, &decoded));
+
+        if let Err(err) = result {
+            println!("Couldn't create thumbnail of {}: {}", image.path, err);
+        }
+    }
+}
+
+/// Decodes an image and turns it upright according to its EXIF orientation.
+fn decode(content: &[u8]) -> Result<DynamicImage, ImageStoreError> {
+    let mut decoder = ImageReader::new(Cursor::new(content))
+        .with_guessed_format()?
+        .into_decoder()
+        .map_err(|err| ImageStoreError::Decode(err.to_string()))?;
+
+    let orientation = decoder.orientation().map_err(|err| ImageStoreError::Decode(err.to_string()))?;
+
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|err| ImageStoreError::Decode(err.to_string()))?;
+    image.apply_orientation(orientation);
+
+    Ok(image)
+}
+
+/// Encodes an image as JPEG, or as PNG if it has an alpha channel.
+/// Returns the content and its file extension.
+fn encode(image: &DynamicImage) -> Result<(Vec<u8>, &'static str), ImageStoreError> {
+    let mut content = Vec::new();
+
+    if image.color().has_alpha() {
+        image.write_to(&mut Cursor::new(&mut content), ImageFormat::Png)
+            .map_err(|err| ImageStoreError::Encode(err.to_string()))?;
+
+        Ok((content, "png"))
+    } else {
+        image.to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut content, JPEG_QUALITY))
+            .map_err(|err| ImageStoreError::Encode(err.to_string()))?;
+
+        Ok((content, "jpg"))
+    }
+}
+
+/// Writes the content into the image directory and returns its stored path.
+/// Images are named after the SHA-256 hash of their content, so the same image is only stored once
+/// and two different images never share a name.
+fn store(location: &DataLocation, content: &[u8], extension: &str) -> Result<String, ImageStoreError> {
+    let file_name = format!("{:x}.{}", Sha256::digest(content), extension);
+    let stored_path = DataLocation::stored_image_path(&file_name);
+
+    write_atomically(&location.image_path(&stored_path), content)?;
+
+    Ok(stored_path)
+}
+
+fn write_thumbnail(location: &DataLocation, stored_path: &str, image: &DynamicImage) -> Result<(), ImageStoreError> {
+    let path = location.thumbnail_path(stored_path);
+    let format = ImageFormat::from_path(&path).unwrap_or(ImageFormat::Png);
+
+    // small images are not scaled up
+    let thumbnail = if image.width() > THUMBNAIL_SIZE || image.height() > THUMBNAIL_SIZE {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    } else {
+        image.clone()
+    };
+    // JPEG has no alpha channel
+    let thumbnail = if format == ImageFormat::Jpeg { DynamicImage::ImageRgb8(thumbnail.to_rgb8()) } else { thumbnail };
+
+    let mut content = Vec::new();
+    thumbnail.write_to(&mut Cursor::new(&mut content), format)
+        .map_err(|err| ImageStoreError::Encode(err.to_string()))?;
+
+    write_atomically(&path, &content)
+}
+
+/// Writes a file through a temporary file, so an interrupted write never leaves half a file behind.
+/// An existing file is kept, as files are named after their content.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), ImageStoreError> {
+    if path.is_file() {
+        return Ok(());
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    fs::write(&temporary, content)?;
+    fs::rename(&temporary, path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod image_store_tests {
+    use crate::app::data_location::DataLocation;
+    use crate::app::backend::database::lok::LokImage;
+    use crate::app::image_store::{create_missing_thumbnails, crop, import, import_pixels, thumbnail_or_image, Crop, THUMBNAIL_SIZE};
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{GenericImageView, ImageReader, Rgb, RgbImage, RgbaImage};
+    use std::path::{Path, PathBuf};
+
+    fn test_location(index: u32) -> DataLocation {
+        let dir = PathBuf::from(format!("test/image_store{}", index));
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let location = DataLocation::new(dir);
+        location.create_dirs().unwrap();
+        location
+    }
+
+    fn dimensions(path: &Path) -> (u32, u32) {
+        ImageReader::open(path).unwrap().decode().unwrap().dimensions()
+    }
+
+    /// Writes a JPEG with an EXIF block, that only holds the orientation tag.
+    fn write_jpeg_with_orientation(path: &Path, image: &RgbImage, orientation: u8) {
+        let mut content = Vec::new();
+        image.write_with_encoder(JpegEncoder::new(&mut content)).unwrap();
+
+        let exif: Vec<u8> = [
+            b"Exif\0\0".as_slice(),
+            b"MM\0\x2a\0\0\0\x08",
+            b"\0\x01\x01\x12\0\x03\0\0\0\x01\0",
+            &[orientation, 0, 0],
+            b"\0\0\0\0",
+        ].concat();
+
+        let mut segment = vec![0xff, 0xe1];
+        segment.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(&exif);
+
+        // the APP1 segment follows the start of image marker
+        content.splice(2..2, segment);
+
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn images_are_normalized_on_import() {
+        let location = test_location(1);
+        let source = location.data_dir().join("portrait.jpeg");
+
+        // stored in landscape, but shown rotated by 90 degrees
+        write_jpeg_with_orientation(&source, &RgbImage::from_pixel(400, 200, Rgb([200, 30, 30])), 6);
+
+        let stored_path = import(&location, &source, 100).unwrap();
+
+        assert!(stored_path.ends_with(".jpg"));
+        assert_eq!(dimensions(&location.image_path(&stored_path)), (50, 100));
+        assert_eq!(dimensions(&location.thumbnail_path(&stored_path)), (50, 100));
+    }
+
+    #[test]
+    fn the_same_image_is_stored_once() {
+        let location = test_location(2);
+
+        let source = location.data_dir().join("photo.png");
+        RgbaImage::from_pixel(600, 300, image::Rgba([0, 0, 255, 128])).save(&source).unwrap();
+
+        let first = import(&location, &source, 1600).unwrap();
+        let second = import(&location, &source, 1600).unwrap();
+
+        assert_eq!(first, second);
+        assert!(first.ends_with(".png"));
+        assert_eq!(std::fs::read_dir(location.images_dir()).unwrap().count(), 1);
+        assert_eq!(dimensions(&location.image_path(&first)), (600, 300));
+        assert_eq!(dimensions(&location.thumbnail_path(&first)), (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2));
+    }
+
+    #[test]
+    fn thumbnails_are_created_for_older_images() {
+        let location = test_location(3);
+
+        let stored_path = DataLocation::stored_image_path("2024-11-17_120439.png");
+        RgbImage::from_pixel(512, 512, Rgb([0, 128, 0])).save(location.image_path(&stored_path)).unwrap();
+
+        assert_eq!(thumbnail_or_image(&location, &stored_path), location.image_path(&stored_path));
+
+        create_missing_thumbnails(&location, &[LokImage::new(stored_path.clone()), LokImage::new("images/missing.png".to_string())]);
+
+        assert_eq!(thumbnail_or_image(&location, &stored_path), location.thumbnail_path(&stored_path));
+        assert_eq!(dimensions(&location.thumbnail_path(&stored_path)), (THUMBNAIL_SIZE, THUMBNAIL_SIZE));
+    }
+
+    #[test]
+    fn pasted_pixels_are_imported() {
+        let location = test_location(5);
+
+        let opaque = RgbaImage::from_pixel(40, 20, image::Rgba([10, 20, 30, 255])).into_raw();
+        let stored_path = import_pixels(&location, 40, 20, opaque, 1600).unwrap();
+
+        assert!(stored_path.ends_with(".jpg"));
+        assert_eq!(dimensions(&location.image_path(&stored_path)), (40, 20));
+
+        let transparent = RgbaImage::from_pixel(40, 20, image::Rgba([10, 20, 30, 0])).into_raw();
+        assert!(import_pixels(&location, 40, 20, transparent, 1600).unwrap().ends_with(".png"));
+
+        assert!(import_pixels(&location, 41, 20, vec![0; 40 * 20 * 4], 1600).is_err());
+    }
+
+    #[test]
+    fn crop_areas_keep_the_display_aspect_ratio() {
+        let centered = Crop::default();
+
+        assert_eq!(centered.area(400, 400), (0, 100, 400, 200));
+        assert_eq!(centered.area(1000, 200), (300, 0, 400, 200));
+
+        let zoomed = Crop { zoom: 2.0, focus_x: 0.0, focus_y: 1.0, ..Crop::default() };
+        assert_eq!(zoomed.area(400, 400), (0, 300, 200, 100));
+    }
+
+    #[test]
+    fn cropped_images_are_stored_next_to_the_original() {
+        let location = test_location(6);
+
+        let source = location.data_dir().join("tall.png");
+        RgbImage::from_pixel(300, 600, Rgb([90, 90, 90])).save(&source).unwrap();
+        let original = import(&location, &source, 1600).unwrap();
+
+        let turned = Crop { quarter_turns: 1, ..Crop::default() };
+        let cropped = crop(&location, &original, &turned, 1600).unwrap();
+
+        assert_ne!(cropped, original);
+        assert_eq!(dimensions(&location.image_path(&original)), (300, 600));
+        assert_eq!(dimensions(&location.image_path(&cropped)), (600, 300));
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        let location = test_location(4);
+
+        let source = location.data_dir().join("notes.png");
+        std::fs::write(&source, b"not an image").unwrap();
+
+        assert!(import(&location, &source, 1600).is_err());
+        assert_eq!(std::fs::read_dir(location.images_dir()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,317 @@
+use crate::app::backend::any_backend::AnyBackend;
+use crate::app::backend::database::lok::{ADDRESS_RANGE, MAX_LOKMAUS_NAME_LENGTH};
+use crate::app::backend::database::sqlite_db::SQLiteDB;
+use crate::app::backend::database::{Database, DatabaseError};
+use crate::app::backend::resource_manager::{BackendHandle, LokChange};
+use crate::app::data_location::DataLocation;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// A problem found by the database check.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue {
+    /// A problem reported by SQLite's integrity check.
+    Integrity(String),
+    /// A problem reported by SQLite's foreign key check.
+    ForeignKey(String),
+    /// The database lists the lok more than once.
+    DuplicateId(u32),
+    MissingImage { id: u32, name: String, image_path: String },
+    /// An image in the image directory no lok refers to.
+    OrphanImage(PathBuf),
+    /// A cached thumbnail that belongs to no image of a lok.
+    OrphanThumbnail(PathBuf),
+    InvalidAddress { id: u32, name: String, address: i32 },
+    LokmausNameTooLong { id: u32, name: String, lokmaus_name: String },
+}
+
+/// What can be done about an issue.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fix {
+    RebuildIndexes,
+    RemoveImageReference,
+    DeleteImageFile,
+    DeleteThumbnail,
+    RemoveAddress,
+    ShortenLokmausName,
+}
+
+impl Issue {
+    /// Returns the fix for the issue, ```None``` if it has to be fixed by hand.
+    pub fn fix(&self) -> Option<Fix> {
+        match self {
+            Issue::Integrity(_) | Issue::DuplicateId(_) => Some(Fix::RebuildIndexes),
+            Issue::ForeignKey(_) => None,
+            Issue::MissingImage { .. } => Some(Fix::RemoveImageReference),
+            Issue::OrphanImage(_) => Some(Fix::DeleteImageFile),
+            Issue::OrphanThumbnail(_) => Some(Fix::DeleteThumbnail),
+            Issue::InvalidAddress { .. } => Some(Fix::RemoveAddress),
+            Issue::LokmausNameTooLong { .. } => Some(Fix::ShortenLokmausName),
+        }
+    }
+}
+
+/// Checks the open collection.
+/// The SQLite checks are only run for SQLite databases. Orphaned images and thumbnails are only searched,
+/// if the image directory belongs to the open collection alone.
+pub async fn check(handle: &BackendHandle<AnyBackend>, db_url: &str, location: &DataLocation, check_orphans: bool) -> Result<Vec<Issue>, DatabaseError> {
+    let mut issues = Vec::new();
+
+    if AnyBackend::is_sqlite_url(db_url) {
+        let database = SQLiteDB::build(db_url).await?;
+
+        let integrity = SQLiteDB::integrity_check(database.connection()).await;
+        let foreign_keys = SQLiteDB::foreign_key_check(database.connection()).await;
+        database.close().await;
+
+        issues.extend(integrity?.into_iter().map(Issue::Integrity));
+        issues.extend(foreign_keys?.into_iter().map(Issue::ForeignKey));
+    }
+
+    let loks = handle.load_all_loks().await?;
+
+    let mut id_counts = BTreeMap::new();
+    for (id, _) in &loks {
+        *id_counts.entry(*id).or_insert(0) += 1;
+    }
+    issues.extend(id_counts.into_iter().filter(|(_, count)| *count > 1).map(|(id, _)| Issue::DuplicateId(id)));
+
+    let mut referenced_images = BTreeSet::new();
+    let mut used_thumbnails = BTreeSet::new();
+    let mut checked_ids = BTreeSet::new();
+
+    for (id, lok) in loks {
+        if !checked_ids.insert(id) {
+            continue;
+        }
+
+        for image_path in lok.image_paths() {
+            let path = location.image_path(&image_path);
+            used_thumbnails.insert(location.thumbnail_path(&image_path));
+
+            if !path.is_file() {
+                issues.push(Issue::MissingImage { id, name: lok.name.clone(), image_path });
+            }
+
+            referenced_images.insert(path);
+        }
+
+        if let Some(address) = lok.address {
+            if !ADDRESS_RANGE.contains(&address) {
+                issues.push(Issue::InvalidAddress { id, name: lok.name.clone(), address });
+            }
+        }
+
+        if let Some(lokmaus_name) = lok.lokmaus_name.clone() {
+            if lokmaus_name.len() > MAX_LOKMAUS_NAME_LENGTH {
+                issues.push(Issue::LokmausNameTooLong { id, name: lok.name.clone(), lokmaus_name });
+            }
+        }
+    }
+
+    if !check_orphans {
+        return Ok(issues);
+    }
+
+    let orphans = unused_files(&location.images_dir(), &referenced_images)?;
+    // the thumbnail of an orphaned image is deleted together with it
+    used_thumbnails.extend(orphans.iter().map(|path| orphan_thumbnail_path(location, path)));
+    let orphaned_thumbnails = unused_files(&location.thumbnails_dir(), &used_thumbnails)?;
+
+    issues.extend(orphans.into_iter().map(Issue::OrphanImage));
+    issues.extend(orphaned_thumbnails.into_iter().map(Issue::OrphanThumbnail));
+
+    Ok(issues)
+}
+
+/// Returns the sorted files of a directory that are not listed in used, nothing if the directory does not exist.
+fn unused_files(dir: &Path, used: &BTreeSet<PathBuf>) -> Result<Vec<PathBuf>, DatabaseError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let entries = std::fs::read_dir(dir).map_err(|err| DatabaseError::Io(err.to_string()))?;
+
+    let mut unused: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && !used.contains(path))
+        .collect();
+    unused.sort();
+
+    Ok(unused)
+}
+
+/// Returns the thumbnail path of an orphaned image, through the path it would be stored with.
+fn orphan_thumbnail_path(location: &DataLocation, path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    location.thumbnail_path(&DataLocation::stored_image_path(&file_name))
+}
+
+/// Applies the fix of an issue.
+/// Returns the change of the fixed lok, it has to be applied to the resource manager, so its caches stay up to date.
+pub async fn fix(handle: &BackendHandle<AnyBackend>, db_url: &str, location: &DataLocation, issue: &Issue) -> Result<Option<LokChange>, DatabaseError> {
+    match issue {
+        Issue::Integrity(_) | Issue::DuplicateId(_) => {
+            let database = SQLiteDB::build(db_url).await?;
+            let result = SQLiteDB::reindex(database.connection()).await;
+            database.close().await;
+
+            result.map(|_| None)
+        }
+        Issue::ForeignKey(_) => Ok(None),
+        Issue::MissingImage { id, image_path, .. } => {
+            let mut lok = handle.get_lok(*id).await?;
+            lok.images.retain(|image| &image.path != image_path);
+            // the cropped image is still fine without its original, it just can't be cropped again
+            for image in lok.images.iter_mut().filter(|image| image.original.as_ref() == Some(image_path)) {
+                image.original = None;
+            }
+
+            handle.update_lok(*id, lok).await.map(Some)
+        }
+        Issue::OrphanImage(path) => {
+            std::fs::remove_file(path).map_err(|err| DatabaseError::Io(err.to_string()))?;
+
+            // not every image has a thumbnail yet
+            match std::fs::remove_file(orphan_thumbnail_path(location, path)) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(DatabaseError::Io(err.to_string())),
+                _ => Ok(None),
+            }
+        }
+        Issue::OrphanThumbnail(path) => {
+            std::fs::remove_file(path).map(|_| None).map_err(|err| DatabaseError::Io(err.to_string()))
+        }
+        Issue::InvalidAddress { id, .. } => {
+            let mut lok = handle.get_lok(*id).await?;
+            lok.address = None;
+
+            handle.update_lok(*id, lok).await.map(Some)
+        }
+        Issue::LokmausNameTooLong { id, .. } => {
+            let mut lok = handle.get_lok(*id).await?;
+            lok.lokmaus_name = lok.lokmaus_name.map(|name| shorten(&name, MAX_LOKMAUS_NAME_LENGTH));
+
+            handle.update_lok(*id, lok).await.map(Some)
+        }
+    }
+}
+
+/// Rebuilds the database file with SQLite's VACUUM.
+pub async fn vacuum(db_url: &str) -> Result<(), DatabaseError> {
+    let database = SQLiteDB::build(db_url).await?;
+    let result = SQLiteDB::vacuum(database.connection()).await;
+    database.close().await;
+
+    result
+}
+
+/// Cuts a text to at most max_length bytes without splitting a character.
+fn shorten(text: &str, max_length: usize) -> String {
+    let mut end = max_length.min(text.len());
+
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    text[..end].to_string()
+}
+
+#[cfg(test)]
+mod maintenance_tests {
+    use crate::app::backend::any_backend::AnyBackend;
+    use crate::app::backend::database::lok::{Lok, LokImage};
+    use crate::app::backend::resource_manager::{LokChange, LokResourceManager};
+    use crate::app::data_location::DataLocation;
+    use crate::app::maintenance::{check, fix, shorten, vacuum, Issue};
+    use async_std::task;
+    use std::path::PathBuf;
+
+    fn test_location(index: u32) -> DataLocation {
+        let dir = PathBuf::from(format!("test/maintenance{}", index));
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let location = DataLocation::new(dir);
+        location.create_dirs().unwrap();
+        location
+    }
+
+    fn lok(name: &str, address: i32, lokmaus_name: &str, image_path: &str) -> Lok {
+        Lok::new_from_raw_data(name.to_string(), Some(address), lokmaus_name.to_string(), "".to_string(), "".to_string(), true, images(image_path))
+    }
+
+    fn images(image_path: &str) -> Vec<LokImage> {
+        Some(image_path).filter(|image_path| !image_path.is_empty()).map(|image_path| LokImage::new(image_path.to_string())).into_iter().collect()
+    }
+
+    fn add_lok(lrm: &mut LokResourceManager<AnyBackend>, lok: Lok) -> u32 {
+        let change = task::block_on(lrm.handle().add_lok(lok)).unwrap();
+        let LokChange::Added(id, _) = change else {
+            panic!("adding a lok has to return LokChange::Added");
+        };
+        lrm.apply(change);
+
+        id
+    }
+
+    #[test]
+    fn problems_are_found_and_fixed() {
+        let location = test_location(1);
+        let db_url = location.db_url();
+        std::fs::write(location.image_path("images/used.png"), b"used").unwrap();
+        std::fs::write(location.image_path("images/orphan.png"), b"orphan").unwrap();
+        std::fs::create_dir_all(location.thumbnails_dir()).unwrap();
+        std::fs::write(location.thumbnail_path("images/used.png"), b"used").unwrap();
+        std::fs::write(location.thumbnail_path("images/orphan.png"), b"orphan").unwrap();
+        std::fs::write(location.thumbnail_path("images/deleted.png"), b"deleted").unwrap();
+
+        let mut lrm = task::block_on(LokResourceManager::<AnyBackend>::build(&db_url)).unwrap();
+        add_lok(&mut lrm, lok("FINE", 3, "FINE", "images/used.png"));
+        let missing = add_lok(&mut lrm, lok("MISSING", 4, "", "images/missing.png"));
+        let address = add_lok(&mut lrm, lok("ADDRESS", 0, "", ""));
+        let name = add_lok(&mut lrm, lok("NAME", 5, "TOOLONG", ""));
+
+        let issues = task::block_on(check(&lrm.handle(), &db_url, &location, true)).unwrap();
+
+        assert_eq!(issues, vec![
+            Issue::MissingImage { id: missing, name: "MISSING".to_string(), image_path: "images/missing.png".to_string() },
+            Issue::InvalidAddress { id: address, name: "ADDRESS".to_string(), address: 0 },
+            Issue::LokmausNameTooLong { id: name, name: "NAME".to_string(), lokmaus_name: "TOOLONG".to_string() },
+            Issue::OrphanImage(location.image_path("images/orphan.png")),
+            Issue::OrphanThumbnail(location.thumbnail_path("images/deleted.png")),
+        ]);
+
+        for issue in &issues {
+            if let Some(change) = task::block_on(fix(&lrm.handle(), &db_url, &location, issue)).unwrap() {
+                lrm.apply(change);
+            }
+        }
+
+        assert_eq!(task::block_on(check(&lrm.handle(), &db_url, &location, true)).unwrap(), vec![]);
+        assert_eq!(task::block_on(lrm.get_lok(name)).unwrap().lokmaus_name, Some("TOOLO".to_string()));
+        assert!(location.image_path("images/used.png").exists());
+        assert!(location.thumbnail_path("images/used.png").exists());
+        assert!(!location.thumbnail_path("images/orphan.png").exists());
+
+        task::block_on(vacuum(&db_url)).unwrap();
+    }
+
+    #[test]
+    fn orphans_are_only_searched_when_asked() {
+        let location = test_location(2);
+        std::fs::write(location.image_path("images/orphan.png"), b"orphan").unwrap();
+
+        let lrm = task::block_on(LokResourceManager::<AnyBackend>::build(&location.db_url())).unwrap();
+
+        assert_eq!(task::block_on(check(&lrm.handle(), &location.db_url(), &location, false)).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn texts_are_shortened_at_character_boundaries() {
+        assert_eq!(shorten("ABCDEFG", 5), "ABCDE");
+        assert_eq!(shorten("ABC", 5), "ABC");
+        assert_eq!(shorten("ABCDÖ", 5), "ABCD");
+    }
+}
@@ -1,30 +1,106 @@
-use crate::app::backend::sqlite_backend::SQLiteBackend;
+use crate::app::backend::database::lok::Lok;
+use crate::app::backend::database::preview_query::{PreviewPage, PreviewQuery, PreviewSort};
+use crate::app::backend::database::DatabaseError;
+use crate::app::backend::resource_manager::{LokChange, LokResourceManager};
+use crate::app::backend::any_backend::AnyBackend;
+use crate::app::backup::{BackupError, BackupSummary};
+use crate::app::image_store::ImageStoreError;
+use crate::app::maintenance::Issue;
 use crate::app::persistent_data::PersistentData;
+use crate::app::settings::Settings;
 use crate::app::settings::languages::Languages;
+use crate::app::ui::notification::Notification;
+use iced::widget::scrollable::Viewport;
 use iced::Event;
 use rfd::MessageDialogResult;
+use std::path::PathBuf;
 
 #[derive(Clone, Debug)]
 pub enum Message {
     Add,
     AddNewLok,
     AddressInputChanged(i32),
+    ApplyImageEdit,
+    BackupCreated(Result<BackupSummary, BackupError>),
+    BackupRestored(Result<Option<Settings>, BackupError>),
+    BulkImportFinished(usize, usize, Vec<LokChange>, Result<(), DatabaseError>),
+    BulkImportMatchChanged(usize, Option<u32>),
+    BulkImportScanned(Vec<PathBuf>, Result<PreviewPage, DatabaseError>),
     Cancel,
+    CheckDatabase,
+    CloseImageEditor,
+    CollectionDeleteConfirmed(String, MessageDialogResult),
+    CollectionNameInputChanged(String),
+    ConfirmBulkImport,
+    CreateBackup,
+    CreateCollection,
+    CreateDatabase,
+    CropFocusXChanged(f32),
+    CropFocusYChanged(f32),
+    CropZoomChanged(f32),
+    DatabaseOpened(String, Result<LokResourceManager<AnyBackend>, DatabaseError>),
+    DeleteCollection(String),
+    DismissNotification(u32),
     Edit(u32),
+    EditImage,
     EditLok,
     EventOccurred(Event),
+    FixIssue(usize),
+    IssueFixed(usize, Result<Option<LokChange>, DatabaseError>),
     HasDecoderInputChanged(bool),
+    ImageCaptionChanged(usize, String),
+    ImageEdited(usize, String, Result<String, ImageStoreError>),
+    ImageFileDropped(PathBuf),
+    ImageImported(Result<String, ImageStoreError>),
+    ImportImages,
     InputFailure(MessageDialogResult),
     LanguageSelected(usize, Languages),
-    Loaded(PersistentData<SQLiteBackend>),
+    LeaveDemo,
+    LoadMorePreviews,
+    Loaded(PersistentData<AnyBackend>),
+    LokListScrolled(Viewport),
+    LokLoaded(u32, Result<Lok, DatabaseError>),
     LokMausNameInputChanged(String),
+    LokRemoved(Result<LokChange, DatabaseError>),
+    LokSaved(Result<LokChange, DatabaseError>),
+    Maintenance,
+    MaintenanceChecked(Result<Vec<Issue>, DatabaseError>),
+    MaxImageSizeChanged(u32),
     ManagementInputChanged(String),
     ManufacturerInputChanged(String),
+    MoveImageLeft(usize),
+    MoveImageRight(usize),
     NameInputChanged(String),
+    NewCollectionFile,
+    NextImage,
+    Notify(Notification),
+    OpenDatabase,
+    PasteImage,
+    PreviewsLoaded(PreviewQuery, Result<PreviewPage, DatabaseError>),
+    PreviousImage,
     Remove(u32),
+    RemoveImage(usize),
+    RenameCollection(String),
+    RestoreBackup,
+    RestoreSnapshot(PathBuf),
+    ResetDataDir,
+    RestoreDatabase,
+    RestoreOriginalImage,
+    RotateImageLeft,
+    RotateImageRight,
     Saved(u32),
     SearchInputChanged(String),
+    SelectDataDir,
+    SelectImage(usize),
     SelectImageFile,
+    SetPrimaryImage(usize),
     Settings,
+    SnapshotCountChanged(usize),
+    SortPreviews(PreviewSort),
+    SnapshotRestored(Result<(), DatabaseError>),
     ShowLok(u32),
+    StartDemo,
+    SwitchCollection(Option<String>),
+    Vacuum,
+    Vacuumed(Result<(), DatabaseError>),
 }
\ No newline at end of file
@@ -4,16 +4,45 @@ pub mod add_page;
 pub mod edit_page;
 pub mod show_page;
 pub mod settings_page;
+pub mod recovery_page;
+pub mod maintenance_page;
+pub mod bulk_import_page;
 
+use crate::app::backend::database::lok::Lok;
+use crate::app::backend::database::DatabaseError;
+use crate::app::backend::resource_manager::LokChange;
+use crate::app::image_editor::ImageEditor;
+use crate::app::image_store;
+use crate::app::image_store::ImageStoreError;
 use crate::app::message::Message;
 use crate::app::page::add_page::AddPage;
+use crate::app::page::bulk_import_page::BulkImportPage;
 use crate::app::page::edit_page::EditPage;
 use crate::app::page::home_page::HomePage;
 use crate::app::page::loading_page::LoadingPage;
+use crate::app::page::maintenance_page::MaintenancePage;
+use crate::app::page::recovery_page::RecoveryPage;
 use crate::app::page::settings_page::SettingsPage;
 use crate::app::page::show_page::ShowPage;
+use crate::app::state::State;
+use crate::app::ui::notification::Notification;
 use crate::app::Lokbuch;
 use iced::{Element, Task};
+use rfd::FileDialog;
+use std::path::Path;
+
+/// Progress of the database operation the current page waits for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum PageStatus {
+    #[default]
+    Ready,
+    /// The data of the page is loaded, there is nothing to show yet.
+    Loading,
+    /// Changes are written, the page is shown but can't be changed.
+    Saving,
+    /// The data of the page could not be loaded.
+    Failed(DatabaseError),
+}
 
 pub trait Page {
     fn update(&self, lokbuch: &mut Lokbuch, message: Message) -> Task<Message>;
@@ -27,6 +56,9 @@ pub enum Pages {
     Settings,
     Home,
     Loading,
+    Recovery,
+    Maintenance,
+    BulkImport,
 }
 
 impl Pages {
@@ -38,6 +70,324 @@ impl Pages {
             Pages::Settings => { Box::new(SettingsPage) }
             Pages::Home => { Box::new(HomePage) }
             Pages::Loading => { Box::new(LoadingPage) }
+            Pages::Recovery => { Box::new(RecoveryPage) }
+            Pages::Maintenance => { Box::new(MaintenancePage) }
+            Pages::BulkImport => { Box::new(BulkImportPage) }
+        }
+    }
+}
+
+/// Opens a file dialog and copies the selected image into the user data.
+/// On success the copy becomes the current image input, otherwise a notification is emitted.
+pub(crate) fn select_image_file(lokbuch: &mut Lokbuch, filter_name: String) -> Task<Message> {
+    let file = FileDialog::new()
+        .add_filter(filter_name, &["png", "jpg", "jpeg"])
+        .pick_file();
+
+    // if a file was selected, it is copied to user data
+    match file {
+        Some(image_file) => import_image_file(lokbuch, &image_file),
+        None => Task::none(),
+    }
+}
+
+/// Copies an image file, e.g. one dropped onto the window, into the user data in the background.
+/// The result is delivered with ```Message::ImageImported```, meanwhile the input can't be saved.
+pub(crate) fn import_image_file(lokbuch: &mut Lokbuch, image_file: &Path) -> Task<Message> {
+    if lokbuch.is_busy() {
+        return Task::none();
+    }
+
+    println!("Selected image file: {:?}", image_file);
+
+    lokbuch.page_status = PageStatus::Saving;
+
+    let data_location = lokbuch.data_location.clone();
+    let image_file = image_file.to_path_buf();
+    let max_image_size = lokbuch.settings.max_image_size;
+
+    Task::perform(
+        async move { image_store::import(&data_location, &image_file, max_image_size) },
+        Message::ImageImported,
+    )
+}
+
+/// Copies the image of the clipboard into the user data in the background.
+/// The result is delivered with ```Message::ImageImported```, meanwhile the input can't be saved.
+pub(crate) fn paste_image(lokbuch: &mut Lokbuch) -> Task<Message> {
+    if lokbuch.is_busy() {
+        return Task::none();
+    }
+
+    let clipboard_image = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_image());
+
+    match clipboard_image {
+        Ok(clipboard_image) => {
+            lokbuch.page_status = PageStatus::Saving;
+
+            let data_location = lokbuch.data_location.clone();
+            let max_image_size = lokbuch.settings.max_image_size;
+
+            Task::perform(
+                async move {
+                    image_store::import_pixels(
+                        &data_location,
+                        clipboard_image.width as u32,
+                        clipboard_image.height as u32,
+                        clipboard_image.bytes.into_owned(),
+                        max_image_size,
+                    )
+                },
+                Message::ImageImported,
+            )
+        }
+        Err(err) => {
+            println!("Failed to read image from clipboard: {}", err);
+
+            Task::done(Message::Notify(Notification::error(t!("notification.no_clipboard_image"))))
+        }
+    }
+}
+
+/// Adds a copied image to the images of the current input, otherwise a notification is emitted.
+/// If the input mask was left meanwhile, the copy is discarded.
+pub(crate) fn image_imported(lokbuch: &mut Lokbuch, result: Result<String, ImageStoreError>) -> Task<Message> {
+    if lokbuch.page_status != PageStatus::Saving {
+        return discard_image(lokbuch, result);
+    }
+
+    lokbuch.page_status = PageStatus::Ready;
+
+    match result {
+        Ok(image_path) => {
+            lokbuch.state.unsaved_images.push(image_path.clone());
+            lokbuch.state.add_image(image_path);
+
+            Task::none()
+        }
+        Err(err) => {
+            println!("Failed to copy image file: {}", err);
+
+            Task::done(Message::Notify(Notification::error(
+                t!("notification.image_copy_failed", message = err.to_string())
+            )))
+        }
+    }
+}
+
+/// Opens the image editor for the selected image.
+/// If the image cannot be read, a notification is emitted.
+pub(crate) fn open_image_editor(lokbuch: &mut Lokbuch) -> Task<Message> {
+    let Some(lok_image) = lokbuch.state.get_selected_image() else {
+        return Task::none();
+    };
+
+    match ImageEditor::open(&lokbuch.data_location, lokbuch.state.selected_image, lok_image) {
+        Ok(editor) => {
+            lokbuch.state.image_editor = Some(editor);
+            Task::none()
+        }
+        Err(err) => {
+            println!("Failed to open image editor: {}", err);
+
+            Task::done(Message::Notify(Notification::error(
+                t!("notification.image_edit_failed", message = err.to_string())
+            )))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Crops the original of the edited image in the background, the result is delivered with ```Message::ImageEdited```.
+/// The editor stays open until then.
+pub(crate) fn apply_image_edit(lokbuch: &mut Lokbuch) -> Task<Message> {
+    let Some(editor) = lokbuch.state.image_editor.as_ref().filter(|_| !lokbuch.is_busy()) else {
+        return Task::none();
+    };
+
+    let index = editor.index;
+    let original = editor.original.clone();
+    let crop = editor.crop;
+    let data_location = lokbuch.data_location.clone();
+    let max_image_size = lokbuch.settings.max_image_size;
+
+    lokbuch.page_status = PageStatus::Saving;
+
+    Task::perform(
+        async move {
+            let result = image_store::crop(&data_location, &original, &crop, max_image_size);
+            (original, result)
+        },
+        move |(original, result)| Message::ImageEdited(index, original, result),
+    )
+}
+
+/// Replaces the edited image with the cropped one and closes the editor.
+/// The original is kept, so the image can be cropped again later.
+/// If the input mask was left meanwhile, the cropped image is discarded.
+pub(crate) fn image_edited(lokbuch: &mut Lokbuch, index: usize, original: String, result: Result<String, ImageStoreError>) -> Task<Message> {
+    if lokbuch.page_status != PageStatus::Saving {
+        return discard_image(lokbuch, result);
+    }
+
+    lokbuch.page_status = PageStatus::Ready;
+
+    match result {
+        Ok(image_path) => {
+            lokbuch.state.image_editor = None;
+            lokbuch.state.unsaved_images.push(image_path.clone());
+
+            if let Some(image) = lokbuch.state.images_input.get_mut(index) {
+                image.path = image_path;
+                image.original = Some(original);
+            }
+
+            Task::none()
+        }
+        Err(err) => {
+            println!("Failed to crop image: {}", err);
+
+            Task::done(Message::Notify(Notification::error(
+                t!("notification.image_edit_failed", message = err.to_string())
+            )))
+        }
+    }
+}
+
+/// Deletes an image, that was copied for an input mask that is closed by now, in the background.
+fn discard_image(lokbuch: &Lokbuch, result: Result<String, ImageStoreError>) -> Task<Message> {
+    let Ok(image_path) = result else {
+        return Task::none();
+    };
+    let handle = lokbuch.lok_resource_manager.handle();
+
+    Task::future(async move { handle.discard_unused_images(&[image_path]).await }).discard()
+}
+
+/// Deletes the images copied while adding or editing a lok in the background, unless a saved lok uses them.
+/// Must be called before the state is cleared on cancel, saving discards them by itself.
+pub(crate) fn discard_unsaved_images(lokbuch: &mut Lokbuch) -> Task<Message> {
+    let unsaved_images = std::mem::take(&mut lokbuch.state.unsaved_images);
+    let handle = lokbuch.lok_resource_manager.handle();
+
+    Task::future(async move { handle.discard_unused_images(&unsaved_images).await }).discard()
+}
+
+/// Loads a lok in the background, the result is delivered with ```Message::LokLoaded```.
+/// The thumbnails it is missing are created on the way.
+/// Until then the page shows, that it is loading.
+pub(crate) fn load_lok(lokbuch: &mut Lokbuch, id: u32) -> Task<Message> {
+    lokbuch.state.clear();
+    lokbuch.state.selected_lok_id = Some(id);
+    lokbuch.page_status = PageStatus::Loading;
+
+    let lok = lokbuch.lok_resource_manager.get_lok(id);
+    let data_location = lokbuch.data_location.clone();
+
+    Task::perform(
+        async move {
+            let lok = lok.await?;
+            image_store::create_missing_thumbnails(&data_location, &lok.images);
+            Ok(lok)
+        },
+        move |result| Message::LokLoaded(id, result),
+    )
+}
+
+/// Fills the state with a loaded lok and returns on_loaded.
+/// The result is dropped, if another lok was selected in the meantime.
+pub(crate) fn lok_loaded(lokbuch: &mut Lokbuch, id: u32, result: Result<Lok, DatabaseError>, on_loaded: Task<Message>) -> Task<Message> {
+    if lokbuch.state.selected_lok_id != Some(id) {
+        return Task::none();
+    }
+
+    match result {
+        Ok(lok) => {
+            lokbuch.state = State::create_state_from_id_and_lok(id, &lok);
+            lokbuch.page_status = PageStatus::Ready;
+
+            on_loaded
+        }
+        Err(err) => {
+            lokbuch.page_status = PageStatus::Failed(err.clone());
+
+            Task::done(Message::Notify(Notification::database_error(t!("notification.load_failed"), &err)))
+        }
+    }
+}
+
+/// Saves the lok of the input mask in the background, as a new lok or over the lok with the given id.
+/// The result is delivered with ```Message::LokSaved```. Copied images the lok doesn't use are discarded after saving.
+pub(crate) fn save_lok(lokbuch: &mut Lokbuch, id: Option<u32>) -> Task<Message> {
+    if let Some(error_task) = lokbuch.state.validate().err() {
+        return error_task;
+    }
+
+    lokbuch.page_status = PageStatus::Saving;
+
+    let lok = lokbuch.state.get_lok_from_current_state();
+    // kept in the state, so they are still discarded on cancel if saving fails
+    let unsaved_images = lokbuch.state.unsaved_images.clone();
+    let handle = lokbuch.lok_resource_manager.handle();
+
+    Task::perform(
+        async move {
+            let change = match id {
+                Some(id) => handle.update_lok(id, lok).await?,
+                None => handle.add_lok(lok).await?,
+            };
+            handle.discard_unused_images(&unsaved_images).await;
+
+            Ok(change)
+        },
+        Message::LokSaved,
+    )
+}
+
+/// Applies the saved lok to the caches of the resource manager and returns to the home page.
+/// If saving failed, the input mask stays open.
+pub(crate) fn lok_saved(lokbuch: &mut Lokbuch, result: Result<LokChange, DatabaseError>) -> Task<Message> {
+    match result {
+        Ok(change) => {
+            lokbuch.lok_resource_manager.apply(change);
+            lokbuch.state.clear();
+            lokbuch.change_page_to(Pages::Home);
+
+            HomePage::restore_scroll_position(lokbuch)
+        }
+        Err(err) => {
+            lokbuch.page_status = PageStatus::Ready;
+
+            Task::done(Message::Notify(Notification::database_error(t!("notification.save_failed"), &err)))
+        }
+    }
+}
+
+/// Removes a lok in the background, the result is delivered with ```Message::LokRemoved```.
+pub(crate) fn remove_lok(lokbuch: &mut Lokbuch, id: u32) -> Task<Message> {
+    lokbuch.page_status = PageStatus::Saving;
+
+    let handle = lokbuch.lok_resource_manager.handle();
+
+    Task::perform(
+        async move { handle.remove_lok(id).await },
+        Message::LokRemoved,
+    )
+}
+
+/// Applies the removal to the caches of the resource manager and returns to the home page.
+pub(crate) fn lok_removed(lokbuch: &mut Lokbuch, result: Result<LokChange, DatabaseError>) -> Task<Message> {
+    match result {
+        Ok(change) => {
+            lokbuch.lok_resource_manager.apply(change);
+            lokbuch.state.clear();
+            lokbuch.change_page_to(Pages::Home);
+
+            HomePage::restore_scroll_position(lokbuch)
+        }
+        Err(err) => {
+            lokbuch.page_status = PageStatus::Ready;
+
+            Task::done(Message::Notify(Notification::database_error(t!("notification.delete_failed"), &err)))
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::app::backend::database::preview_query::{PreviewPage, PreviewQuery};
+use crate::app::backend::database::DatabaseError;
+use crate::app::backend::resource_manager::LokChange;
+use crate::app::bulk_import;
+use crate::app::image_store;
+use crate::app::message::Message;
+use crate::app::page::{Page, PageStatus, Pages};
+use crate::app::ui;
+use crate::app::ui::notification::Notification;
+use crate::app::ui::widgets::page_layout;
+use crate::app::Lokbuch;
+use iced::widget::{button, column, container, image, pick_list, row, scrollable, text};
+use iced::{Center, ContentFit, Element, Fill, Task};
+use rfd::FileDialog;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// Lets the user review the suggested lok of every image of a folder, before the images are attached.
+pub struct BulkImportPage;
+
+/// An entry of the lok selection of an image.
+#[derive(Clone, Debug, PartialEq)]
+struct LokChoice {
+    /// ```None``` skips the image.
+    id: Option<u32>,
+    label: String,
+}
+
+impl Display for LokChoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+impl BulkImportPage {
+    /// Asks for a folder and loads the loks in the background, to suggest a lok for every image in it.
+    /// The result is delivered with ```Message::BulkImportScanned```.
+    pub fn start(lokbuch: &mut Lokbuch) -> Task<Message> {
+        let Some(dir) = FileDialog::new().pick_folder() else {
+            return Task::none();
+        };
+
+        match bulk_import::scan(&dir) {
+            Ok(files) if files.is_empty() => {
+                Task::done(Message::Notify(Notification::info(t!("notification.bulk_import_empty"))))
+            }
+            // every lok is a candidate, not only the loaded previews
+            Ok(files) => Task::perform(
+                lokbuch.lok_resource_manager.query_previews(PreviewQuery::default()),
+                move |result| Message::BulkImportScanned(files, result),
+            ),
+            Err(err) => {
+                Task::done(Message::Notify(Notification::error(t!("notification.image_copy_failed", message = err.to_string()))))
+            }
+        }
+    }
+
+    /// Suggests a lok for every image and opens the review list.
+    pub fn scanned(lokbuch: &mut Lokbuch, files: Vec<PathBuf>, result: Result<PreviewPage, DatabaseError>) -> Task<Message> {
+        match result {
+            Ok(page) => {
+                lokbuch.bulk_import = bulk_import::suggest_matches(files, &page.previews);
+                lokbuch.bulk_import_loks = page.previews;
+                lokbuch.change_page_to(Pages::BulkImport);
+
+                Task::none()
+            }
+            Err(err) => Task::done(Message::Notify(Notification::database_error(t!("notification.search_failed"), &err))),
+        }
+    }
+
+    /// Copies the images with a lok into the user data and attaches them to their loks in the background.
+    /// The result is delivered with ```Message::BulkImportFinished```, together with the changed loks,
+    /// as some may have their images even if attaching failed.
+    fn attach(lokbuch: &mut Lokbuch) -> Task<Message> {
+        lokbuch.page_status = PageStatus::Saving;
+
+        let matches: Vec<(u32, PathBuf)> = lokbuch.bulk_import.iter()
+            .filter_map(|import_match| import_match.lok_id.map(|id| (id, import_match.file.clone())))
+            .collect();
+        let data_location = lokbuch.data_location.clone();
+        let max_image_size = lokbuch.settings.max_image_size;
+        let handle = lokbuch.lok_resource_manager.handle();
+
+        Task::perform(
+            async move {
+                let mut images = Vec::new();
+                let mut failed = 0;
+
+                for (id, file) in matches {
+                    match image_store::import(&data_location, &file, max_image_size) {
+                        Ok(image_path) => images.push((id, image_path)),
+                        Err(err) => {
+                            println!("Failed to import {}: {}", file.display(), err);
+                            failed += 1;
+                        }
+                    }
+                }
+
+                let (attached, changes, result) = handle.attach_images(&images).await;
+
+                if result.is_err() {
+                    // the attached images are still used, only the others are deleted
+                    let image_paths: Vec<String> = images.into_iter().map(|(_, image_path)| image_path).collect();
+                    handle.discard_unused_images(&image_paths).await;
+                }
+
+                (attached, failed, changes, result)
+            },
+            |(attached, failed, changes, result)| Message::BulkImportFinished(attached, failed, changes, result),
+        )
+    }
+
+    /// Applies the changed loks to the caches of the resource manager and returns to the home page.
+    fn finish(lokbuch: &mut Lokbuch, attached: usize, failed: usize, changes: Vec<LokChange>, result: Result<(), DatabaseError>) -> Task<Message> {
+        lokbuch.bulk_import.clear();
+        lokbuch.bulk_import_loks.clear();
+
+        for change in changes {
+            lokbuch.lok_resource_manager.apply(change);
+        }
+
+        let notification = match result {
+            Ok(_) if failed == 0 => Notification::info(t!("notification.bulk_import_done", count = attached)),
+            Ok(_) => Notification::error(t!("notification.bulk_import_incomplete", count = attached, failed = failed)),
+            Err(err) => Notification::database_error(t!("notification.bulk_import_failed", count = attached), &err),
+        };
+
+        lokbuch.change_page_to(Pages::Home);
+
+        Task::done(Message::Notify(notification))
+    }
+}
+
+impl Page for BulkImportPage {
+    fn update(&self, lokbuch: &mut Lokbuch, message: Message) -> Task<Message> {
+        match message {
+            Message::BulkImportMatchChanged(index, lok_id) => {
+                if let Some(import_match) = lokbuch.bulk_import.get_mut(index) {
+                    import_match.lok_id = lok_id;
+                }
+            }
+
+            Message::ConfirmBulkImport => {
+                return BulkImportPage::attach(lokbuch);
+            }
+
+            Message::BulkImportFinished(attached, failed, changes, result) => {
+                return BulkImportPage::finish(lokbuch, attached, failed, changes, result);
+            }
+
+            // the images are attached already, they are not skipped anymore
+            Message::Cancel if !lokbuch.is_busy() => {
+                lokbuch.bulk_import.clear();
+                lokbuch.bulk_import_loks.clear();
+                lokbuch.change_page_to(Pages::Home);
+            }
+
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn view<'a>(&self, lokbuch: &'a Lokbuch) -> Element<'a, Message> {
+        let mut choices: Vec<LokChoice> = lokbuch.bulk_import_loks.iter()
+            .map(|preview| LokChoice { id: Some(preview.get_id()), label: preview.get_name_pretty() })
+            .collect();
+        choices.sort_by(|a, b| a.label.cmp(&b.label));
+        choices.insert(0, LokChoice { id: None, label: t!("bulk_import.skip").to_string() });
+
+        let rows = lokbuch.bulk_import.iter().enumerate().map(|(index, import_match)| {
+            let selected = choices.iter().find(|choice| choice.id == import_match.lok_id).cloned();
+            let file_name = import_match.file.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+            container(
+                row![
+                    image(&import_match.file)
+                        .width(120)
+                        .height(80)
+                        .content_fit(ContentFit::Cover),
+                    text(file_name).width(Fill),
+                    pick_list(choices.clone(), selected, move |choice: LokChoice| Message::BulkImportMatchChanged(index, choice.id))
+                        .width(250),
+                ].spacing(10).align_y(Center)
+            )
+                .padding(10)
+                .width(Fill)
+                .style(container::rounded_box)
+                .into()
+        });
+
+        let content = container(
+            column![
+                text(t!("bulk_import.review"))
+                    .size(ui::HEADING_TEXT_SIZE)
+                    .font(ui::font::bold_font()),
+                scrollable(column(rows).spacing(10)),
+            ].spacing(20)
+        ).padding(10);
+
+        let count = lokbuch.bulk_import.iter().filter(|import_match| import_match.lok_id.is_some()).count();
+
+        let sidebar_buttons = column![
+            button(text(t!("bulk_import.attach", count = count)))
+                .on_press_maybe((count > 0 && !lokbuch.is_busy()).then_some(Message::ConfirmBulkImport))
+                .padding(15)
+                .width(Fill),
+        ];
+
+        page_layout(t!("bulk_import.import_images").to_string(), sidebar_buttons, content, true)
+    }
+}
@@ -1,17 +1,93 @@
+use crate::app::backend::database::preview_query::{PreviewFilter, PreviewSort};
 use crate::app::message::Message;
-use crate::app::page::{Page, Pages};
-use crate::app::state::State;
+use crate::app::page::bulk_import_page::BulkImportPage;
+use crate::app::page::{load_lok, lok_removed, remove_lok, Page, Pages};
 use crate::app::ui;
+use crate::app::ui::notification::localized_search_query_error;
 use crate::app::ui::widgets::{button_decorations, page_layout, preview_widget};
 use crate::app::ui::SvgIcon;
 use crate::app::Lokbuch;
-use async_std::task;
-use iced::widget::operation::focus;
+use iced::widget::operation::{focus, scroll_to, snap_to, AbsoluteOffset, RelativeOffset};
 use iced::widget::{button, column, container, keyed_column, row, scrollable, space, text, text_input};
 use iced::{Center, Element, Fill, FillPortion, Task};
+use std::ops::Range;
+
+/// Id of the scrollable of the lok list.
+const LOK_LIST_ID: &str = "lok-list";
+/// Height of a row of the lok list, including the space below it.
+/// Rows have a fixed height, so the visible rows follow from the scroll offset.
+const ROW_HEIGHT: f32 = 72.0;
+const ROW_SPACING: f32 = 10.0;
+/// Rows that are built above and below the visible ones, so fast scrolling shows no gaps.
+const OVERSCAN_ROWS: usize = 10;
+/// The next page is loaded, once the visible rows come this close to the end of the loaded ones.
+const LOAD_AHEAD_ROWS: usize = 50;
 
 pub struct HomePage;
 
+/// The scroll position of the lok list.
+/// It is kept to build only the visible rows, and to scroll back when the list is shown again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LokListState {
+    pub offset: f32,
+    pub height: f32,
+    /// True while the next page of the list is loaded.
+    pub is_loading_more: bool,
+}
+
+impl Default for LokListState {
+    /// Until the list was scrolled, its height is unknown, so enough rows for a large screen are built.
+    fn default() -> Self {
+        LokListState {
+            offset: 0.0,
+            height: 1600.0,
+            is_loading_more: false,
+        }
+    }
+}
+
+impl LokListState {
+    /// Returns the rows to build, out of count loaded rows.
+    fn visible_rows(&self, count: usize) -> Range<usize> {
+        let first = ((self.offset / ROW_HEIGHT) as usize).saturating_sub(OVERSCAN_ROWS).min(count);
+        let last = (((self.offset + self.height) / ROW_HEIGHT).ceil() as usize + OVERSCAN_ROWS).min(count);
+
+        first..last
+    }
+}
+
+impl HomePage {
+    /// Scrolls the lok list back to where it was left, after another page was shown.
+    pub fn restore_scroll_position(lokbuch: &Lokbuch) -> Task<Message> {
+        scroll_to(LOK_LIST_ID, AbsoluteOffset { x: 0.0, y: lokbuch.lok_list.offset })
+    }
+
+    /// Changes the search and scrolls back to the top of the list.
+    fn search(lokbuch: &mut Lokbuch, filter: PreviewFilter, sort: PreviewSort) -> Task<Message> {
+        lokbuch.lok_list.offset = 0.0;
+
+        Task::batch([
+            lokbuch.search_previews(filter, sort),
+            snap_to(LOK_LIST_ID, RelativeOffset::START),
+        ])
+    }
+
+    /// Loads the next page of the list, unless every match is loaded or it is loaded already.
+    fn load_more(lokbuch: &mut Lokbuch) -> Task<Message> {
+        if lokbuch.lok_list.is_loading_more {
+            return Task::none();
+        }
+
+        match lokbuch.lok_resource_manager.next_page_query() {
+            Some(query) => {
+                lokbuch.lok_list.is_loading_more = true;
+                lokbuch.load_previews(query)
+            }
+            None => Task::none(),
+        }
+    }
+}
+
 impl Page for HomePage {
     fn update(&self, lokbuch: &mut Lokbuch, message: Message) -> Task<Message> {
         match message {
@@ -21,33 +97,75 @@ impl Page for HomePage {
                 return focus("new-lok-name");
             }
 
+            Message::ImportImages => {
+                return BulkImportPage::start(lokbuch);
+            }
+
+            Message::BulkImportScanned(files, result) => {
+                return BulkImportPage::scanned(lokbuch, files, result);
+            }
+
             Message::SearchInputChanged(search_input) => {
-                lokbuch.state.search_input = search_input.clone();
+                let filter = PreviewFilter::from_search_input(&search_input);
+                lokbuch.search_input = search_input;
 
-                lokbuch.lok_resource_manager.search_and_store_previews_containing(search_input.to_lowercase());
+                // the list keeps the results of the last valid search, while the input is not
+                let filter = match filter {
+                    Ok(filter) => filter,
+                    Err(err) => {
+                        lokbuch.search_error = Some(err);
+                        return Task::none();
+                    }
+                };
+                lokbuch.search_error = None;
+
+                let current = lokbuch.lok_resource_manager.get_search();
+                // a new search is ranked, a column the results were sorted by afterwards is kept
+                let sort = if current.filter == PreviewFilter::All || current.sort == PreviewSort::Relevance {
+                    filter.default_sort()
+                } else {
+                    current.sort
+                };
+
+                return HomePage::search(lokbuch, filter, sort);
             }
 
-            Message::ShowLok(id) => {
-                let lok = task::block_on(lokbuch.lok_resource_manager.get_lok(id)).expect("lok not found");
+            Message::SortPreviews(sort) => {
+                let filter = lokbuch.lok_resource_manager.get_search().filter.clone();
 
-                lokbuch.state = State::create_state_from_id_and_lok(id, &lok);
+                return HomePage::search(lokbuch, filter, sort);
+            }
 
-                lokbuch.change_page_to(Pages::Show);
+            Message::LoadMorePreviews => {
+                return HomePage::load_more(lokbuch);
             }
 
-            Message::Edit(id) => {
-                let lok = task::block_on(lokbuch.lok_resource_manager.get_lok(id)).expect("Lok does not exist!"); // TODO async edit
+            Message::LokListScrolled(viewport) => {
+                lokbuch.lok_list.offset = viewport.absolute_offset().y;
+                lokbuch.lok_list.height = viewport.bounds().height;
 
-                lokbuch.state = State::create_state_from_id_and_lok(id, &lok);
+                let loaded = lokbuch.lok_resource_manager.get_previews().len();
+                if lokbuch.lok_list.visible_rows(loaded).end + LOAD_AHEAD_ROWS >= loaded {
+                    return HomePage::load_more(lokbuch);
+                }
+            }
 
+            Message::ShowLok(id) => {
+                lokbuch.change_page_to(Pages::Show);
+                return load_lok(lokbuch, id);
+            }
+
+            Message::Edit(id) => {
                 lokbuch.change_page_to(Pages::Edit);
-                return focus("new-lok-name");
+                return load_lok(lokbuch, id);
             }
 
             Message::Remove(id) => {
-                task::block_on(lokbuch.lok_resource_manager.remove_lok(id)); // TODO async remove
+                return remove_lok(lokbuch, id);
+            }
 
-                lokbuch.change_page_to(Pages::Home);
+            Message::LokRemoved(result) => {
+                return lok_removed(lokbuch, result);
             }
 
             _ => {}
@@ -57,39 +175,45 @@ impl Page for HomePage {
 
     fn view<'a>(&self, lokbuch: &'a Lokbuch) -> Element<'a, Message> {
         let num_of_loks = lokbuch.lok_resource_manager.number_of_loks();
-        let mut previews = lokbuch.lok_resource_manager.get_all_previews().clone();
 
-        let input_search = text_input(t!("home.search").to_string().as_str(), lokbuch.state.search_input.as_str())
+        let input_search = text_input(t!("home.search").to_string().as_str(), lokbuch.search_input.as_str())
             .id("lok-search")
             .on_input(Message::SearchInputChanged)
             .padding(15)
             .size(ui::HEADING_TEXT_SIZE)
             .align_x(Center);
 
+        let search_error = lokbuch.search_error.as_ref().map(|err| {
+            text(localized_search_query_error(err))
+                .style(text::danger)
+        });
+
         let add_button = button(button_decorations(t!("home.new_loco").to_string(), SvgIcon::Plus))
             .on_press(Message::Add)
             .padding(15)
             .width(Fill);
 
+        let import_button = button(text(t!("bulk_import.import_images_from_folder")))
+            .on_press(Message::ImportImages)
+            .style(button::secondary)
+            .padding(15)
+            .width(Fill);
+
+        let sort = lokbuch.lok_resource_manager.get_search().sort;
+
         let text_row = row![
                     space::horizontal()
                     .width(10),
 
-                    text(t!("home.address"))
-                    .size(ui::HEADING_TEXT_SIZE)
-                    .font(ui::font::bold_font()),
+                    sort_button(t!("home.address").to_string(), PreviewSort::Address, sort),
 
                     space::horizontal(),
 
-                    text(t!("home.lm_name"))
-                    .size(ui::HEADING_TEXT_SIZE)
-                    .font(ui::font::bold_font()),
+                    sort_button(t!("home.lm_name").to_string(), PreviewSort::LokmausName, sort),
 
                     space::horizontal(),
 
-                    text(t!("home.name"))
-                    .size(ui::HEADING_TEXT_SIZE)
-                    .font(ui::font::bold_font()),
+                    sort_button(t!("home.name").to_string(), PreviewSort::Name, sort),
 
                     space::horizontal(),
 
@@ -98,49 +222,82 @@ impl Page for HomePage {
                     space::horizontal()
                 ];
 
-        let loks =
-            if lokbuch.state.search_input.is_empty() {
-                keyed_column(
-                    previews.into_iter().map(move |item| {
-                        let preview = item.clone();
-                        (0, iced::widget::column!(
-                        button(preview_widget(preview.clone()))
-                        .style(button::text)
-                        .on_press_with(move || {
-                            Message::ShowLok(item.clone().get_id())
-                        }),
-                        space::vertical()
-                            .height(10))
-                            .into())
-                    })
-                ).width(Fill)
-            } else {
-                keyed_column(
-                    lokbuch.lok_resource_manager.get_search_results()
-                        .into_iter().map(move |item| {
-                        let preview = item.clone();
-                        (0, iced::widget::column!(
-                        button(preview_widget(preview.clone()))
-                        .style(button::text)
-                        .on_press_with(move || {
-                            Message::ShowLok(item.clone().get_id())
-                        }),
+        // the loaded previews are shared with the resource manager, only the visible rows are built
+        // and the rows above and below them are replaced by empty space of the same height
+        let previews = lokbuch.lok_resource_manager.get_previews();
+        let rows = lokbuch.lok_list.visible_rows(previews.len());
+
+        let loks = column![
+            space::vertical()
+                .height(rows.start as f32 * ROW_HEIGHT),
+            keyed_column(
+                previews[rows.clone()].iter().map(|preview| {
+                    let id = preview.get_id();
+                    (id, column![
+                        button(preview_widget(preview))
+                            .style(button::text)
+                            .height(ROW_HEIGHT - ROW_SPACING)
+                            .on_press(Message::ShowLok(id)),
                         space::vertical()
-                            .height(10))
-                            .into())
-                    })
-                ).width(Fill)
-            };
+                            .height(ROW_SPACING)
+                    ].into())
+                })
+            ),
+            space::vertical()
+                .height((previews.len() - rows.end) as f32 * ROW_HEIGHT),
+        ].width(Fill);
 
+        let show_more: Option<Element<'a, Message>> = lokbuch.lok_resource_manager.next_page_query().map(|_| {
+            button(text(t!("home.show_more", shown = previews.len(), total = lokbuch.lok_resource_manager.number_of_matches())))
+                .on_press(Message::LoadMorePreviews)
+                .style(button::secondary)
+                .padding(10)
+                .into()
+        });
 
         let content = container(
             column!(
-                input_search,
+                column![input_search].push(search_error).spacing(5).align_x(Center),
                 text_row,
-                scrollable(container(loks))
+                scrollable(column![loks].push(show_more).align_x(Center))
+                    .id(LOK_LIST_ID)
+                    .on_scroll(Message::LokListScrolled)
             ).align_x(Center).spacing(20).width(FillPortion(7))
         ).padding(10);
 
-        page_layout(t!("home.locos_available", num=num_of_loks).to_string(), column![add_button], content, false)
+        let title = match lokbuch.data_location.collection() {
+            _ if lokbuch.is_demo() => format!("{} - {}", t!("home.locos_available", num=num_of_loks), t!("home.demo_mode")),
+            Some(collection) if lokbuch.is_open_collection(Some(collection)) => format!("{} - {}", collection, t!("home.locos_available", num=num_of_loks)),
+            _ => t!("home.locos_available", num=num_of_loks).to_string(),
+        };
+
+        page_layout(title, column![add_button, import_button].spacing(10), content, false)
+    }
+}
+
+/// A column heading, that sorts the list by its column.
+fn sort_button<'a>(label: String, sort: PreviewSort, current_sort: PreviewSort) -> Element<'a, Message> {
+    button(
+        text(label)
+            .size(ui::HEADING_TEXT_SIZE)
+            .font(ui::font::bold_font())
+    )
+        .on_press(Message::SortPreviews(sort))
+        .style(if sort == current_sort { button::secondary } else { button::text })
+        .into()
+}
+
+#[cfg(test)]
+mod home_page_tests {
+    use crate::app::page::home_page::{LokListState, OVERSCAN_ROWS, ROW_HEIGHT};
+
+    #[test]
+    fn only_visible_rows_are_built() {
+        let list = LokListState { offset: 100.0 * ROW_HEIGHT, height: 10.0 * ROW_HEIGHT, is_loading_more: false };
+
+        assert_eq!(list.visible_rows(1000), 100 - OVERSCAN_ROWS..110 + OVERSCAN_ROWS);
+        assert_eq!(list.visible_rows(105), 100 - OVERSCAN_ROWS..105);
+        assert_eq!(list.visible_rows(50), 50..50);
+        assert_eq!(LokListState::default().visible_rows(0), 0..0);
     }
-}
\ No newline at end of file
+}
@@ -14,12 +14,23 @@ impl Page for LoadingPage {
 
         match message {
             Message::Loaded(persistent_data) => {
-                lokbuch.lok_resource_manager = persistent_data.get_lok_resource_manager();
                 lokbuch.settings = persistent_data.get_settings();
+                lokbuch.data_location = persistent_data.get_data_location();
+                lokbuch.db_url = persistent_data.get_db_url();
+                lokbuch.refresh_collections();
 
                 set_locale(lokbuch.settings.language.as_str());
 
-                lokbuch.change_page_to(Pages::Home);
+                match persistent_data.get_lok_resource_manager() {
+                    Ok(lrm) => {
+                        lokbuch.lok_resource_manager = lrm.with_image_location(lokbuch.owned_image_location());
+                        lokbuch.change_page_to(Pages::Home);
+                    }
+                    Err(err) => {
+                        lokbuch.database_error = Some(err);
+                        lokbuch.change_page_to(Pages::Recovery);
+                    }
+                }
             }
             _ => {}
         }
@@ -0,0 +1,203 @@
+use crate::app::backend::any_backend::AnyBackend;
+use crate::app::maintenance;
+use crate::app::maintenance::{Fix, Issue};
+use crate::app::message::Message;
+use crate::app::page::{Page, PageStatus, Pages};
+use crate::app::ui;
+use crate::app::ui::notification::Notification;
+use crate::app::ui::widgets::page_layout;
+use crate::app::Lokbuch;
+use iced::widget::{button, column, container, row, scrollable, text};
+use iced::{Center, Element, Fill, Task};
+
+/// Shows the result of the database check and offers a fix for every issue.
+pub struct MaintenancePage;
+
+impl MaintenancePage {
+    /// Runs the database check in the background.
+    /// The result is delivered with ```Message::MaintenanceChecked```.
+    pub fn check(lokbuch: &mut Lokbuch) -> Task<Message> {
+        lokbuch.maintenance_issues = None;
+
+        let handle = lokbuch.lok_resource_manager.handle();
+        let db_url = lokbuch.db_url.clone();
+        let data_location = lokbuch.data_location.clone();
+        // the image directory is shared with collection files opened from elsewhere
+        let check_orphans = lokbuch.has_snapshots();
+
+        Task::perform(
+            async move { maintenance::check(&handle, &db_url, &data_location, check_orphans).await },
+            Message::MaintenanceChecked,
+        )
+    }
+}
+
+impl Page for MaintenancePage {
+    fn update(&self, lokbuch: &mut Lokbuch, message: Message) -> Task<Message> {
+        match message {
+            Message::Cancel => {
+                lokbuch.maintenance_issues = None;
+                lokbuch.change_page_to(Pages::Settings);
+            }
+
+            Message::CheckDatabase => {
+                return MaintenancePage::check(lokbuch);
+            }
+
+            Message::MaintenanceChecked(result) => {
+                match result {
+                    Ok(issues) => { lokbuch.maintenance_issues = Some(issues); }
+                    Err(err) => {
+                        lokbuch.maintenance_issues = Some(Vec::new());
+                        return Task::done(Message::Notify(Notification::database_error(t!("notification.check_failed"), &err)));
+                    }
+                }
+            }
+
+            Message::FixIssue(index) => {
+                let Some(issue) = lokbuch.maintenance_issues.as_ref().and_then(|issues| issues.get(index)).cloned() else {
+                    return Task::none();
+                };
+
+                lokbuch.page_status = PageStatus::Saving;
+
+                let handle = lokbuch.lok_resource_manager.handle();
+                let db_url = lokbuch.db_url.clone();
+                let data_location = lokbuch.data_location.clone();
+
+                return Task::perform(
+                    async move { maintenance::fix(&handle, &db_url, &data_location, &issue).await },
+                    move |result| Message::IssueFixed(index, result),
+                );
+            }
+
+            Message::IssueFixed(index, result) => {
+                lokbuch.page_status = PageStatus::Ready;
+
+                let fix = lokbuch.maintenance_issues.as_ref().and_then(|issues| issues.get(index)).and_then(Issue::fix);
+
+                match result {
+                    // rebuilding the indexes fixes every index problem at once, so the search and the check are run again
+                    Ok(_) if fix == Some(Fix::RebuildIndexes) => {
+                        let search = lokbuch.lok_resource_manager.get_search().clone();
+                        let search = lokbuch.search_previews(search.filter, search.sort);
+                        return Task::batch([search, MaintenancePage::check(lokbuch)]);
+                    }
+                    Ok(change) => {
+                        if let Some(issues) = lokbuch.maintenance_issues.as_mut() {
+                            issues.remove(index);
+                        }
+
+                        if let Some(change) = change {
+                            lokbuch.lok_resource_manager.apply(change);
+                        }
+                    }
+                    Err(err) => {
+                        return Task::done(Message::Notify(Notification::database_error(t!("notification.fix_failed"), &err)));
+                    }
+                }
+            }
+
+            Message::Vacuum => {
+                let db_url = lokbuch.db_url.clone();
+
+                return Task::perform(
+                    async move { maintenance::vacuum(&db_url).await },
+                    Message::Vacuumed,
+                );
+            }
+
+            Message::Vacuumed(result) => {
+                let notification = match result {
+                    Ok(_) => Notification::info(t!("notification.vacuum_done")),
+                    Err(err) => Notification::database_error(t!("notification.vacuum_failed"), &err),
+                };
+
+                return Task::done(Message::Notify(notification));
+            }
+
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn view<'a>(&self, lokbuch: &'a Lokbuch) -> Element<'a, Message> {
+        let report: Element<'a, Message> = match &lokbuch.maintenance_issues {
+            None => text(t!("maintenance.checking")).into(),
+            Some(issues) if issues.is_empty() => text(t!("maintenance.no_issues")).into(),
+            Some(issues) => scrollable(
+                column(issues.iter().enumerate().map(|(index, issue)| issue_widget(index, issue, lokbuch.is_busy())))
+                    .spacing(10)
+            ).into(),
+        };
+
+        let content = container(
+            column![
+                text(t!("maintenance.report"))
+                    .size(ui::HEADING_TEXT_SIZE)
+                    .font(ui::font::bold_font()),
+                report,
+            ].spacing(20)
+        ).padding(10);
+
+        let sidebar_buttons = column![
+            button(text(t!("maintenance.check_again")))
+                .on_press_maybe(lokbuch.maintenance_issues.is_some().then_some(Message::CheckDatabase))
+                .padding(15)
+                .width(Fill),
+            button(text(t!("maintenance.vacuum")))
+                .on_press_maybe(AnyBackend::is_sqlite_url(&lokbuch.db_url).then_some(Message::Vacuum))
+                .style(button::secondary)
+                .padding(15)
+                .width(Fill),
+        ];
+
+        page_layout(t!("maintenance.maintenance").to_string(), sidebar_buttons, content, true)
+    }
+}
+
+/// One fix runs at a time, as issues are identified by their index.
+fn issue_widget<'a>(index: usize, issue: &Issue, is_busy: bool) -> Element<'a, Message> {
+    let fix_button: Element<'a, Message> = match issue.fix() {
+        Some(fix) => button(text(fix_label(fix)))
+            .on_press_maybe((!is_busy).then_some(Message::FixIssue(index)))
+            .style(button::secondary)
+            .into(),
+        None => text(t!("maintenance.fix_by_hand")).into(),
+    };
+
+    container(
+        row![
+            text(issue_description(issue)).width(Fill),
+            fix_button,
+        ].spacing(10).align_y(Center)
+    )
+        .padding(10)
+        .width(Fill)
+        .style(container::rounded_box)
+        .into()
+}
+
+fn issue_description(issue: &Issue) -> String {
+    match issue {
+        Issue::Integrity(message) => t!("maintenance.integrity", message = message).to_string(),
+        Issue::ForeignKey(message) => t!("maintenance.foreign_key", message = message).to_string(),
+        Issue::DuplicateId(id) => t!("maintenance.duplicate_id", id = id).to_string(),
+        Issue::MissingImage { name, image_path, .. } => t!("maintenance.missing_image", name = name, path = image_path).to_string(),
+        Issue::OrphanImage(path) => t!("maintenance.orphan_image", path = path.display().to_string()).to_string(),
+        Issue::OrphanThumbnail(path) => t!("maintenance.orphan_thumbnail", path = path.display().to_string()).to_string(),
+        Issue::InvalidAddress { name, address, .. } => t!("maintenance.invalid_address", name = name, address = address).to_string(),
+        Issue::LokmausNameTooLong { name, lokmaus_name, .. } => t!("maintenance.lokmaus_name_too_long", name = name, lokmaus_name = lokmaus_name).to_string(),
+    }
+}
+
+fn fix_label(fix: Fix) -> String {
+    match fix {
+        Fix::RebuildIndexes => t!("maintenance.rebuild_indexes"),
+        Fix::RemoveImageReference => t!("maintenance.remove_image_reference"),
+        Fix::DeleteImageFile => t!("maintenance.delete_image_file"),
+        Fix::DeleteThumbnail => t!("maintenance.delete_thumbnail"),
+        Fix::RemoveAddress => t!("maintenance.remove_address"),
+        Fix::ShortenLokmausName => t!("maintenance.shorten_lokmaus_name"),
+    }.to_string()
+}
@@ -0,0 +1,114 @@
+use crate::app::backend::any_backend::{AnyBackend, JSON_FILE_EXTENSION, SQLITE_FILE_EXTENSION};
+use crate::app::backup::BACKUP_FILE_EXTENSION;
+use crate::app::backend::database::sqlite_db::SQLiteDB;
+use crate::app::backend::database::DatabaseError;
+use crate::app::message::Message;
+use crate::app::page::Page;
+use crate::app::ui;
+use crate::app::ui::notification::localized_database_error;
+use crate::app::ui::widgets::header;
+use crate::app::Lokbuch;
+use iced::widget::{button, center, column, container, text};
+use iced::{Center, Element, Fill, Task};
+use rfd::FileDialog;
+
+/// Is shown instead of the home page, if the database could not be opened on startup.
+pub struct RecoveryPage;
+
+impl Page for RecoveryPage {
+    fn update(&self, lokbuch: &mut Lokbuch, message: Message) -> Task<Message> {
+        match message {
+            Message::CreateDatabase => {
+                let path = AnyBackend::path_from_url(&lokbuch.db_url);
+
+                let result = SQLiteDB::move_aside(&path).and_then(|_| {
+                    match path.parent() {
+                        Some(dir) => std::fs::create_dir_all(dir).map_err(|err| DatabaseError::Io(err.to_string())),
+                        None => Ok(()),
+                    }
+                });
+
+                match result {
+                    Ok(_) => { return Lokbuch::open_database(lokbuch.db_url.clone()); }
+                    Err(err) => { lokbuch.database_error = Some(err); }
+                }
+            }
+
+            Message::RestoreDatabase => {
+                let file = FileDialog::new()
+                    .add_filter(t!("recovery.database_files"), &[SQLITE_FILE_EXTENSION, JSON_FILE_EXTENSION, BACKUP_FILE_EXTENSION])
+                    .pick_file();
+
+                if let Some(backup) = file {
+                    let is_archive = backup.extension()
+                        .is_some_and(|extension| extension.eq_ignore_ascii_case(BACKUP_FILE_EXTENSION));
+
+                    if is_archive {
+                        return lokbuch.restore_backup(backup);
+                    }
+
+                    let path = AnyBackend::path_from_url(&lokbuch.db_url);
+
+                    match SQLiteDB::restore_from(&backup, &path) {
+                        Ok(_) => { return Lokbuch::open_database(lokbuch.db_url.clone()); }
+                        Err(err) => { lokbuch.database_error = Some(err); }
+                    }
+                }
+            }
+
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn view<'a>(&self, lokbuch: &'a Lokbuch) -> Element<'a, Message> {
+        let error_text = match &lokbuch.database_error {
+            Some(err) => localized_database_error(err),
+            None => String::new(),
+        };
+
+        let path = AnyBackend::path_from_url(&lokbuch.db_url);
+
+        let content = column![
+            text(t!("recovery.description"))
+                .size(ui::HEADING_TEXT_SIZE)
+                .font(ui::font::bold_font()),
+
+            container(text(error_text))
+                .padding(15)
+                .width(Fill)
+                .style(container::danger),
+
+            text(t!("recovery.database_location", path = path.display().to_string())),
+
+            button(text(t!("recovery.open")))
+                .on_press(Message::OpenDatabase)
+                .padding(15)
+                .width(Fill),
+
+            button(text(t!("recovery.create")))
+                .on_press(Message::CreateDatabase)
+                .padding(15)
+                .width(Fill),
+
+            button(text(t!("recovery.restore")))
+                .on_press(Message::RestoreDatabase)
+                .padding(15)
+                .width(Fill),
+
+            button(text(t!("recovery.demo")))
+                .on_press(Message::StartDemo)
+                .style(button::secondary)
+                .padding(15)
+                .width(Fill),
+        ]
+            .spacing(20)
+            .width(600)
+            .align_x(Center);
+
+        column![
+            header(t!("recovery.recovery").to_string()),
+            center(content),
+        ].into()
+    }
+}
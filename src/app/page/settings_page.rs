@@ -1,14 +1,27 @@
+use crate::app::backend::any_backend::AnyBackend;
+use crate::app::collections;
+use crate::app::backend::database::sqlite_db::SQLiteDB;
+use crate::app::collections::CollectionError;
 use crate::app::message::Message;
+use crate::app::page::maintenance_page::MaintenancePage;
 use crate::app::page::{Page, Pages};
 use crate::app::settings::languages::Languages;
+use crate::app::snapshots::SnapshotReason;
+use crate::app::ui::notification::Notification;
 use crate::app::ui::widgets::page_layout;
 use crate::app::Lokbuch;
-use iced::widget::{column, container, text};
-use iced::{Element, Task};
-use iced_aw::SelectionList;
+use iced::widget::{button, column, container, row, text, text_input};
+use iced::{Center, Element, Fill, Task};
+use iced_aw::{number_input, SelectionList};
+use rfd::{FileDialog, MessageDialogResult};
 use rust_i18n::set_locale;
+use std::path::PathBuf;
 
 const LANGUAGES: &[&str] = &["en", "de"];
+/// Bounds of the maximum size of imported images, in pixels.
+const MIN_IMAGE_SIZE: u32 = 256;
+const MAX_IMAGE_SIZE: u32 = 8192;
+const IMAGE_SIZE_STEP: u32 = 128;
 
 pub struct SettingsPage;
 
@@ -23,6 +36,120 @@ impl Page for SettingsPage {
                 println!("{} {}", index, language);
                 lokbuch.settings.language = language.short_language_code();
                 set_locale(&language.short_language_code());
+
+                if let Err(err) = lokbuch.settings.save(&lokbuch.data_location.settings_path()) {
+                    return Task::done(Message::Notify(Notification::error(
+                        t!("notification.settings_save_failed", message = err.to_string())
+                    )));
+                }
+            }
+
+            Message::SelectDataDir => {
+                if let Some(dir) = FileDialog::new().pick_folder() {
+                    return change_data_dir(lokbuch, Some(dir));
+                }
+            }
+
+            Message::ResetDataDir => {
+                return change_data_dir(lokbuch, None);
+            }
+
+            Message::Maintenance => {
+                lokbuch.change_page_to(Pages::Maintenance);
+
+                return MaintenancePage::check(lokbuch);
+            }
+
+            Message::SnapshotCountChanged(count) => {
+                lokbuch.settings.snapshot_count = count;
+
+                if let Err(err) = lokbuch.settings.save(&lokbuch.data_location.settings_path()) {
+                    return Task::done(Message::Notify(Notification::error(
+                        t!("notification.settings_save_failed", message = err.to_string())
+                    )));
+                }
+            }
+
+            Message::MaxImageSizeChanged(size) => {
+                lokbuch.settings.max_image_size = size;
+
+                if let Err(err) = lokbuch.settings.save(&lokbuch.data_location.settings_path()) {
+                    return Task::done(Message::Notify(Notification::error(
+                        t!("notification.settings_save_failed", message = err.to_string())
+                    )));
+                }
+            }
+
+            Message::RestoreSnapshot(snapshot) => {
+                let handle = lokbuch.lok_resource_manager.handle();
+                let db_path = SQLiteDB::path_from_url(&lokbuch.db_url);
+
+                // the replaced database is kept next to it by restore_from
+                return Task::perform(
+                    async move {
+                        handle.close().await;
+                        SQLiteDB::restore_from(&snapshot, &db_path)
+                    },
+                    Message::SnapshotRestored,
+                );
+            }
+
+            Message::CollectionNameInputChanged(name) => {
+                lokbuch.state.collection_name_input = name;
+            }
+
+            Message::SwitchCollection(collection) => {
+                return switch_collection(lokbuch, collection);
+            }
+
+            Message::CreateCollection => {
+                match collections::create(&lokbuch.data_location, &lokbuch.state.collection_name_input) {
+                    Ok(name) => {
+                        lokbuch.state.collection_name_input.clear();
+                        lokbuch.refresh_collections();
+
+                        return switch_collection(lokbuch, Some(name));
+                    }
+                    Err(err) => { return collection_error(t!("notification.collection_create_failed"), &err); }
+                }
+            }
+
+            Message::RenameCollection(name) => {
+                if lokbuch.is_selected_collection(Some(&name)) {
+                    return Task::none();
+                }
+
+                match collections::rename(&lokbuch.data_location, &name, &lokbuch.state.collection_name_input) {
+                    Ok(_) => {
+                        lokbuch.state.collection_name_input.clear();
+                        lokbuch.refresh_collections();
+                    }
+                    Err(err) => { return collection_error(t!("notification.collection_rename_failed"), &err); }
+                }
+            }
+
+            Message::DeleteCollection(name) => {
+                let dialog = rfd::AsyncMessageDialog::new()
+                    .set_title(t!("settings.delete_collection"))
+                    .set_description(t!("settings.delete_collection_confirm", name = name))
+                    .set_buttons(rfd::MessageButtons::YesNo);
+
+                return Task::perform(dialog.show(), move |result| Message::CollectionDeleteConfirmed(name.clone(), result));
+            }
+
+            Message::CollectionDeleteConfirmed(name, MessageDialogResult::Yes) => {
+                // the collection may have been selected while the dialog was open
+                if lokbuch.is_selected_collection(Some(&name)) {
+                    return Task::none();
+                }
+
+                let result = collections::delete(&lokbuch.data_location, &name);
+
+                lokbuch.refresh_collections();
+
+                if let Err(err) = result {
+                    return collection_error(t!("notification.collection_delete_failed"), &err);
+                }
             }
             _ => {}
         }
@@ -30,13 +157,231 @@ impl Page for SettingsPage {
     }
 
     fn view<'a>(&self, lokbuch: &'a Lokbuch) -> Element<'a, Message> {
+        let demo_button = if lokbuch.is_demo() {
+            button(text(t!("settings.leave_demo"))).on_press(Message::LeaveDemo)
+        } else {
+            button(text(t!("settings.start_demo"))).on_press(Message::StartDemo)
+        };
+
         let content = container(
             column![
                 text(t!("settings.language")),
                 SelectionList::new(&Languages::ALL, Message::LanguageSelected),
-            ]
+                text(t!("settings.collections")),
+                collection_list(lokbuch),
+                row![
+                    text_input(t!("settings.collection_name").to_string().as_str(), lokbuch.state.collection_name_input.as_str())
+                        .id("collection-name")
+                        .on_input(Message::CollectionNameInputChanged)
+                        .on_submit(Message::CreateCollection)
+                        .padding(15)
+                        .width(Fill),
+                    button(text(t!("settings.new_collection")))
+                        .on_press(Message::CreateCollection)
+                        .padding(15),
+                ].spacing(10).align_y(Center),
+                text(t!("settings.backup")),
+                button(text(t!("settings.create_backup")))
+                    .on_press_maybe(AnyBackend::is_sqlite_url(&lokbuch.db_url).then_some(Message::CreateBackup))
+                    .padding(15)
+                    .width(Fill),
+                button(text(t!("settings.restore_backup")))
+                    .on_press_maybe(lokbuch.has_snapshots().then_some(Message::RestoreBackup))
+                    .style(button::secondary)
+                    .padding(15)
+                    .width(Fill),
+                text(t!("settings.snapshots")),
+                row![
+                    text(t!("settings.snapshot_count")).width(Fill),
+                    number_input(&lokbuch.settings.snapshot_count, 0..100, Message::SnapshotCountChanged)
+                        .padding(10),
+                ].spacing(10).align_y(Center),
+                snapshot_list(lokbuch),
+                text(t!("settings.images")),
+                row![
+                    text(t!("settings.max_image_size")).width(Fill),
+                    number_input(&lokbuch.settings.max_image_size, MIN_IMAGE_SIZE..=MAX_IMAGE_SIZE, Message::MaxImageSizeChanged)
+                        .step(IMAGE_SIZE_STEP)
+                        .padding(10),
+                ].spacing(10).align_y(Center),
+                text(t!("settings.maintenance")),
+                button(text(t!("settings.check_database")))
+                    .on_press(Message::Maintenance)
+                    .padding(15)
+                    .width(Fill),
+                text(t!("settings.collection_files")),
+                button(text(t!("settings.open_collection_file")))
+                    .on_press(Message::OpenDatabase)
+                    .padding(15)
+                    .width(Fill),
+                button(text(t!("settings.new_collection_file")))
+                    .on_press(Message::NewCollectionFile)
+                    .padding(15)
+                    .width(Fill),
+                text(t!("settings.data_dir")),
+                text(lokbuch.data_location.data_dir().display().to_string()),
+                text(t!("settings.data_dir_not_moved")).size(12),
+                button(text(t!("settings.select_data_dir")))
+                    .on_press_maybe((!lokbuch.data_location.is_fixed()).then_some(Message::SelectDataDir))
+                    .padding(15)
+                    .width(Fill),
+                button(text(t!("settings.reset_data_dir")))
+                    .on_press_maybe((!lokbuch.data_location.is_fixed() && lokbuch.settings.data_dir.is_some()).then_some(Message::ResetDataDir))
+                    .style(button::secondary)
+                    .padding(15)
+                    .width(Fill),
+                text(t!("settings.demo_mode")),
+                demo_button
+                    .style(button::secondary)
+                    .padding(15)
+                    .width(Fill),
+            ].spacing(10)
         );
 
         page_layout(t!("settings.settings").to_string(), iced::widget::Column::new(), content, true)
     }
-}
\ No newline at end of file
+}
+
+/// Switches to another data directory and opens the default collection found there, or creates an empty one.
+/// ```None``` returns to the default directory.
+/// Nothing is moved, the collections of the former directory stay there and are shown again after switching back.
+fn change_data_dir(lokbuch: &mut Lokbuch, data_dir: Option<PathBuf>) -> Task<Message> {
+    lokbuch.settings.data_dir = data_dir;
+    lokbuch.settings.collection = None;
+    lokbuch.data_location = lokbuch.data_location.with_settings(&lokbuch.settings);
+    lokbuch.refresh_collections();
+
+    if let Err(err) = lokbuch.settings.save(&lokbuch.data_location.settings_path()) {
+        return Task::done(Message::Notify(Notification::error(
+            t!("notification.settings_save_failed", message = err.to_string())
+        )));
+    }
+
+    if let Err(err) = lokbuch.data_location.create_dirs() {
+        return Task::done(Message::Notify(Notification::error(
+            t!("notification.data_dir_failed", message = err.to_string())
+        )));
+    }
+
+    if lokbuch.is_demo() {
+        Task::none()
+    } else {
+        Lokbuch::open_database(lokbuch.data_location.db_url())
+    }
+}
+
+/// Opens a collection of the data directory and remembers it for the next start.
+/// ```None``` opens the default collection.
+fn switch_collection(lokbuch: &mut Lokbuch, collection: Option<String>) -> Task<Message> {
+    lokbuch.settings.collection = collection.clone();
+    lokbuch.data_location = lokbuch.data_location.with_collection(collection);
+
+    let notification = lokbuch.settings.save(&lokbuch.data_location.settings_path())
+        .err()
+        .map(|err| Task::done(Message::Notify(Notification::error(
+            t!("notification.settings_save_failed", message = err.to_string())
+        ))));
+
+    let open = match lokbuch.data_location.create_dirs() {
+        Ok(_) => Lokbuch::open_database(lokbuch.data_location.db_url()),
+        Err(err) => Task::done(Message::Notify(Notification::error(
+            t!("notification.data_dir_failed", message = err.to_string())
+        ))),
+    };
+
+    Task::batch(notification.into_iter().chain([open]))
+}
+
+pub(crate) fn collection_error(summary: impl Into<String>, error: &CollectionError) -> Task<Message> {
+    let summary = summary.into();
+
+    println!("{}: {}", summary, error);
+
+    let reason = match error {
+        CollectionError::InvalidName => t!("notification.collection_invalid_name").to_string(),
+        CollectionError::AlreadyExists => t!("notification.collection_exists").to_string(),
+        CollectionError::NotFound => t!("notification.collection_not_found").to_string(),
+        CollectionError::Io(message) => message.clone(),
+    };
+
+    Task::done(Message::Notify(Notification::error(format!("{} {}", summary, reason))))
+}
+
+/// Lists the default collection and all named collections with buttons to open, rename and delete them.
+/// The open collection can neither be renamed nor deleted, another one has to be opened first.
+fn collection_list(lokbuch: &Lokbuch) -> Element<'_, Message> {
+    let default_row = collection_row(lokbuch, None, t!("settings.default_collection").to_string());
+
+    let named_rows = lokbuch.collections.iter()
+        .map(|name| collection_row(lokbuch, Some(name.clone()), name.clone()));
+
+    column(std::iter::once(default_row).chain(named_rows))
+        .spacing(10)
+        .into()
+}
+
+fn collection_row<'a>(lokbuch: &Lokbuch, collection: Option<String>, label: String) -> Element<'a, Message> {
+    let is_open = lokbuch.is_open_collection(collection.as_deref());
+    let can_change = !lokbuch.is_selected_collection(collection.as_deref()) && collection.is_some();
+    let has_new_name = !lokbuch.state.collection_name_input.trim().is_empty();
+
+    let label = if is_open {
+        format!("{} ({})", label, t!("settings.open"))
+    } else {
+        label
+    };
+
+    let rename_message = collection.clone()
+        .filter(|_| can_change && has_new_name)
+        .map(Message::RenameCollection);
+    let delete_message = collection.clone()
+        .filter(|_| can_change)
+        .map(Message::DeleteCollection);
+
+    container(
+        row![
+            text(label).width(Fill),
+            button(text(t!("settings.open_collection")))
+                .on_press_maybe((!is_open).then_some(Message::SwitchCollection(collection))),
+            button(text(t!("settings.rename_collection")))
+                .on_press_maybe(rename_message)
+                .style(button::secondary),
+            button(text(t!("settings.delete_collection")))
+                .on_press_maybe(delete_message)
+                .style(button::danger),
+        ].spacing(10).align_y(Center)
+    )
+        .padding(10)
+        .width(Fill)
+        .style(container::rounded_box)
+        .into()
+}
+
+/// Lists the snapshots of the open collection, the newest first, each with a button to restore it.
+fn snapshot_list(lokbuch: &Lokbuch) -> Element<'_, Message> {
+    if lokbuch.snapshots.is_empty() {
+        return text(t!("settings.no_snapshots")).into();
+    }
+
+    column(lokbuch.snapshots.iter().map(|snapshot| {
+        let reason = match snapshot.reason {
+            SnapshotReason::Startup => t!("settings.snapshot_startup"),
+            SnapshotReason::Shutdown => t!("settings.snapshot_shutdown"),
+        };
+
+        container(
+            row![
+                text(format!("{} ({})", snapshot.created.format("%d.%m.%Y %H:%M:%S"), reason)).width(Fill),
+                button(text(t!("settings.restore_snapshot")))
+                    .on_press(Message::RestoreSnapshot(snapshot.path.clone()))
+                    .style(button::secondary),
+            ].spacing(10).align_y(Center)
+        )
+            .padding(10)
+            .width(Fill)
+            .style(container::rounded_box)
+            .into()
+    }))
+        .spacing(10)
+        .into()
+}
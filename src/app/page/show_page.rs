@@ -1,14 +1,13 @@
 use crate::app::message::Message;
-use crate::app::page::{Page, Pages};
-use crate::app::state::State;
+use crate::app::page::home_page::HomePage;
+use crate::app::page::{load_lok, lok_loaded, lok_removed, remove_lok, Page, Pages};
 use crate::app::ui;
-use crate::app::ui::widgets::{button_decorations, page_layout};
+use crate::app::ui::widgets::{button_decorations, image_carousel, page_layout, page_status_widget};
 use crate::app::ui::SvgIcon;
 use crate::app::Lokbuch;
-use async_std::task;
 use iced::widget::operation::focus;
-use iced::widget::{button, container, image, row, space, text};
-use iced::{ContentFit, Element, Fill, Task};
+use iced::widget::{button, container, row, space, text};
+use iced::{Element, Fill, Task};
 
 pub struct ShowPage;
 
@@ -23,21 +22,29 @@ impl Page for ShowPage {
             Message::Cancel => {
                 lokbuch.state.clear();
                 lokbuch.change_page_to(Pages::Home);
-            }
 
-            Message::Edit(id) => {
-                let lok = task::block_on(lokbuch.lok_resource_manager.get_lok(id)).expect("Lok does not exist!"); // TODO async edit
+                return HomePage::restore_scroll_position(lokbuch);
+            }
 
-                lokbuch.state = State::create_state_from_id_and_lok(id, &lok);
+            Message::LokLoaded(id, result) => {
+                return lok_loaded(lokbuch, id, result, Task::none());
+            }
 
+            Message::Edit(id) => {
                 lokbuch.change_page_to(Pages::Edit);
-                return focus("new-lok-name");
+                return load_lok(lokbuch, id);
             }
 
             Message::Remove(id) => {
-                task::block_on(lokbuch.lok_resource_manager.remove_lok(id)); // TODO async remove
+                return remove_lok(lokbuch, id);
+            }
 
-                lokbuch.change_page_to(Pages::Home);
+            Message::LokRemoved(result) => {
+                return lok_removed(lokbuch, result);
+            }
+
+            Message::PreviousImage | Message::NextImage => {
+                lokbuch.state.update(message);
             }
 
             _ => {}
@@ -46,14 +53,16 @@ impl Page for ShowPage {
     }
 
     fn view<'a>(&self, lokbuch: &'a Lokbuch) -> Element<'a, Message> {
-        let mut lrm = lokbuch.lok_resource_manager.clone();
-        let lok = task::block_on(lrm.get_lok(lokbuch.state.selected_lok_id.unwrap())).expect("lok not found"); // TODO async show
+        if let Some(status) = page_status_widget(lokbuch, t!("show.loco").to_string()) {
+            return status;
+        }
+
+        // the state was filled from the selected lok, so the database is not needed here
+        let lok = lokbuch.state.get_lok_from_current_state();
+        let selected_lok_id = lokbuch.state.selected_lok_id.filter(|_| !lokbuch.is_busy());
 
         let left_column = iced::widget::column!(
-                    image(lokbuch.state.get_current_lok_image_path())
-                    .width(400)
-                    .height(200)
-                    .content_fit(ContentFit::Cover),
+                    image_carousel(lokbuch),
 
                     space::vertical(),
 
@@ -102,16 +111,12 @@ impl Page for ShowPage {
                 ].spacing(10);
 
         let edit_button = button(button_decorations(t!("show.edit").to_string(), SvgIcon::Edit))
-            .on_press_with(move || {
-                Message::Edit(lokbuch.state.selected_lok_id.clone().unwrap())
-            })
+            .on_press_maybe(selected_lok_id.map(Message::Edit))
             .padding(15)
             .width(Fill);
 
         let remove_button = button(button_decorations(t!("show.delete").to_string(), SvgIcon::Trash))
-            .on_press_with(move || {
-                Message::Remove(lokbuch.state.selected_lok_id.clone().unwrap())
-            })
+            .on_press_maybe(selected_lok_id.map(Message::Remove))
             .style(button::danger)
             .padding(15)
             .width(Fill);
@@ -1,32 +1,80 @@
+use crate::app::backend::database::DatabaseError;
+use crate::app::backend::memory_backend::MEMORY_DB_URL;
 use crate::app::backend::resource_manager::LokResourceManager;
 use crate::app::backend::Backend;
+use crate::app::data_location::DataLocation;
 use crate::app::settings::Settings;
+use crate::app::snapshots;
+use crate::app::snapshots::SnapshotReason;
 
 #[derive(Clone, Debug)]
 pub struct PersistentData<BE: Backend> {
-    pub lrm: LokResourceManager<BE>,
+    pub lrm: Result<LokResourceManager<BE>, DatabaseError>,
     settings: Settings,
+    data_location: DataLocation,
+    db_url: String,
 }
 
 impl<BE: Backend> PersistentData<BE> {
-    pub async fn init_app_and_backend(db_url: &str) -> PersistentData<BE> {
-        let lrm = LokResourceManager::<BE>::build(db_url)
-            .await
-            .expect("Couldn't create LokResourceManager");
+    /// Loads the settings, creates the data directories and opens the database.
+    /// A database that cannot be opened does not abort the start, the error is kept for the recovery page instead.
+    /// In demo mode an empty in-memory database is opened.
+    pub async fn init_app_and_backend(base_location: DataLocation, demo: bool) -> PersistentData<BE> {
+        let mut settings = Settings::load(&base_location.settings_path()).await;
 
-        let settings = Settings::load().await;
+        let mut data_location = base_location.with_settings(&settings);
+
+        // a collection that was deleted outside of Lokbuch is not created again
+        if data_location.collection().is_some() && !data_location.collection_dir().is_dir() {
+            println!("Collection {:?} does not exist anymore", settings.collection);
+
+            settings.collection = None;
+            data_location = data_location.with_collection(None);
+        }
+
+        let db_url = if demo {
+            MEMORY_DB_URL.to_string()
+        } else {
+            data_location.db_url()
+        };
+
+        // the snapshot is taken before opening, so it still holds the data from before any migration
+        if !demo {
+            if let Err(err) = snapshots::rotate(&db_url, &data_location, SnapshotReason::Startup, settings.snapshot_count).await {
+                println!("Couldn't take startup snapshot: {}", err);
+            }
+        }
+
+        let lrm = match data_location.create_dirs() {
+            Ok(_) => LokResourceManager::<BE>::build(&db_url).await,
+            Err(err) => Err(DatabaseError::Io(format!("{}: {}", data_location.data_dir().display(), err))),
+        };
+
+        if let Err(err) = &lrm {
+            println!("Couldn't create LokResourceManager: {}", err);
+        }
 
         PersistentData {
             lrm,
             settings,
+            data_location,
+            db_url,
         }
     }
 
-    pub fn get_lok_resource_manager(&self) -> LokResourceManager<BE> {
+    pub fn get_lok_resource_manager(&self) -> Result<LokResourceManager<BE>, DatabaseError> {
         self.lrm.clone()
     }
 
     pub fn get_settings(&self) -> Settings {
         self.settings.clone()
     }
+
+    pub fn get_data_location(&self) -> DataLocation {
+        self.data_location.clone()
+    }
+
+    pub fn get_db_url(&self) -> String {
+        self.db_url.clone()
+    }
 }
@@ -1,11 +1,33 @@
 pub mod languages;
 
 use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Settings {
     pub language: String,
+    /// Directory for the database and the images, if it differs from the default location.
+    #[serde(default)]
+    pub data_dir: Option<PathBuf>,
+    /// The collection that was opened last, ```None``` for the default collection.
+    #[serde(default)]
+    pub collection: Option<String>,
+    /// Number of automatic snapshots that are kept, zero turns them off.
+    #[serde(default = "default_snapshot_count")]
+    pub snapshot_count: usize,
+    /// Imported images are scaled down, so that neither side is longer than this many pixels.
+    #[serde(default = "default_max_image_size")]
+    pub max_image_size: u32,
+}
+
+fn default_snapshot_count() -> usize {
+    5
+}
+
+fn default_max_image_size() -> u32 {
+    1600
 }
 
 #[derive(Debug)]
@@ -15,36 +37,44 @@ enum LoadError {
     Format,
 }
 
-impl Settings {
-    pub fn path() -> std::path::PathBuf {
-        let mut path = std::env::current_dir().unwrap();
-
-        path.push("data/settings.json");
+/// Represents an error upon writing the settings file.
+#[derive(Debug, Clone)]
+pub enum SaveError {
+    Format,
+    CreateDir(String),
+    WriteFile(String),
+}
 
-        path
+impl Display for SaveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Format => write!(f, "settings could not be serialized"),
+            SaveError::CreateDir(message) => write!(f, "{}", message),
+            SaveError::WriteFile(message) => write!(f, "{}", message),
+        }
     }
+}
 
-    pub fn save(&self) {
+impl Settings {
+    pub fn save(&self, path: &Path) -> Result<(), SaveError> {
         println!("Saving settings");
 
-        let json = serde_json::to_string_pretty(&self).unwrap();
-
-        let path = Self::path();
+        let json = serde_json::to_string_pretty(&self).map_err(|_| SaveError::Format)?;
 
         if let Some(dir) = path.parent() {
-            std::fs::create_dir_all(dir).unwrap();
+            std::fs::create_dir_all(dir).map_err(|err| SaveError::CreateDir(err.to_string()))?;
         }
 
-        let mut file = std::fs::File::create(path).unwrap();
+        let mut file = std::fs::File::create(path).map_err(|err| SaveError::WriteFile(err.to_string()))?;
 
-        file.write_all(json.as_bytes()).unwrap();
+        file.write_all(json.as_bytes()).map_err(|err| SaveError::WriteFile(err.to_string()))
     }
 
-    pub async fn load() -> Settings {
-        fn get_saved_settings() -> Result<Settings, LoadError> {
+    pub async fn load(path: &Path) -> Settings {
+        fn get_saved_settings(path: &Path) -> Result<Settings, LoadError> {
             let mut contents = String::new();
 
-            let mut file = std::fs::File::open(Settings::path()).map_err(|_| LoadError::OpenFile)?;
+            let mut file = std::fs::File::open(path).map_err(|_| LoadError::OpenFile)?;
 
             file.read_to_string(&mut contents).map_err(|_| LoadError::ReadFile)?;
 
@@ -53,7 +83,7 @@ impl Settings {
 
         println!("Loading settings");
 
-        get_saved_settings().unwrap_or_else(|error| {
+        get_saved_settings(path).unwrap_or_else(|error| {
             println!("Failed to load settings: {:?}", error);
             Self::default()
         })
@@ -64,6 +94,10 @@ impl Default for Settings {
     fn default() -> Self {
         Settings {
             language: "en".to_string(),
+            data_dir: None,
+            collection: None,
+            snapshot_count: default_snapshot_count(),
+            max_image_size: default_max_image_size(),
         }
     }
 }
\ No newline at end of file
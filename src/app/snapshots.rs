@@ -0,0 +1,189 @@
+use crate::app::backend::database::sqlite_db::SQLiteDB;
+use crate::app::backend::database::DatabaseError;
+use crate::app::data_location::DataLocation;
+use chrono::NaiveDateTime;
+use std::path::{Path, PathBuf};
+
+const SNAPSHOT_PREFIX: &str = "lokbuch-";
+const SNAPSHOT_EXTENSION: &str = ".db";
+const DATETIME_FORMAT: &str = "%Y-%m-%d_%H%M%S";
+
+/// The moment an automatic snapshot was taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotReason {
+    Startup,
+    Shutdown,
+}
+
+impl SnapshotReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SnapshotReason::Startup => "startup",
+            SnapshotReason::Shutdown => "shutdown",
+        }
+    }
+
+    fn from_str(reason: &str) -> Option<SnapshotReason> {
+        match reason {
+            "startup" => Some(SnapshotReason::Startup),
+            "shutdown" => Some(SnapshotReason::Shutdown),
+            _ => None,
+        }
+    }
+}
+
+/// A copy of the database, that was taken automatically.
+/// Snapshots are named ```lokbuch-<datetime>-<reason>.db``` and kept in the snapshot directory of a collection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub created: NaiveDateTime,
+    pub reason: SnapshotReason,
+}
+
+impl Snapshot {
+    fn file_name(created: &NaiveDateTime, reason: SnapshotReason) -> String {
+        format!("{}{}-{}{}", SNAPSHOT_PREFIX, created.format(DATETIME_FORMAT), reason.as_str(), SNAPSHOT_EXTENSION)
+    }
+
+    /// Parses a snapshot file name, other files are ignored.
+    fn from_path(path: &Path) -> Option<Snapshot> {
+        let name = path.file_name()?.to_str()?
+            .strip_prefix(SNAPSHOT_PREFIX)?
+            .strip_suffix(SNAPSHOT_EXTENSION)?;
+
+        let (datetime, reason) = name.rsplit_once('-')?;
+
+        Some(Snapshot {
+            path: path.to_path_buf(),
+            created: NaiveDateTime::parse_from_str(datetime, DATETIME_FORMAT).ok()?,
+            reason: SnapshotReason::from_str(reason)?,
+        })
+    }
+}
+
+/// Takes a snapshot of the database at db_url and deletes the oldest snapshots, so that at most keep are left.
+/// Nothing happens if keep is zero or the database file does not exist yet.
+pub async fn rotate(db_url: &str, location: &DataLocation, reason: SnapshotReason, keep: usize) -> Result<(), DatabaseError> {
+    if keep == 0 || !SQLiteDB::path_from_url(db_url).is_file() {
+        return Ok(());
+    }
+
+    let dir = location.snapshots_dir();
+    std::fs::create_dir_all(&dir).map_err(|err| DatabaseError::Io(err.to_string()))?;
+
+    let created = chrono::Local::now().naive_local();
+    let path = dir.join(Snapshot::file_name(&created, reason));
+
+    // two snapshots within one second replace each other
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|err| DatabaseError::Io(err.to_string()))?;
+    }
+
+    SQLiteDB::snapshot(db_url, &path).await?;
+
+    prune(location, keep)
+}
+
+/// Returns every snapshot of the collection, the newest first.
+pub fn list(location: &DataLocation) -> Result<Vec<Snapshot>, DatabaseError> {
+    let dir = location.snapshots_dir();
+
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = std::fs::read_dir(dir).map_err(|err| DatabaseError::Io(err.to_string()))?;
+
+    let mut snapshots: Vec<Snapshot> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| Snapshot::from_path(&entry.path()))
+        .collect();
+
+    snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.created));
+
+    Ok(snapshots)
+}
+
+/// Deletes the oldest snapshots, so that at most keep are left.
+pub fn prune(location: &DataLocation, keep: usize) -> Result<(), DatabaseError> {
+    for snapshot in list(location)?.into_iter().skip(keep) {
+        std::fs::remove_file(&snapshot.path).map_err(|err| DatabaseError::Io(err.to_string()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod snapshots_tests {
+    use crate::app::backend::any_backend::AnyBackend;
+    use crate::app::backend::database::lok::Lok;
+    use crate::app::backend::resource_manager::LokResourceManager;
+    use crate::app::data_location::DataLocation;
+    use crate::app::snapshots::{list, prune, rotate, Snapshot, SnapshotReason};
+    use async_std::task;
+    use chrono::NaiveDate;
+    use std::path::{Path, PathBuf};
+
+    fn test_location(index: u32) -> DataLocation {
+        let dir = PathBuf::from(format!("test/snapshots{}", index));
+
+        std::fs::remove_dir_all(&dir).ok();
+
+        let location = DataLocation::new(dir);
+        location.create_dirs().unwrap();
+        location
+    }
+
+    #[test]
+    fn file_names_are_parsed() {
+        let created = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap().and_hms_opt(12, 30, 5).unwrap();
+        let name = Snapshot::file_name(&created, SnapshotReason::Shutdown);
+
+        assert_eq!(name, "lokbuch-2026-10-18_123005-shutdown.db");
+
+        let snapshot = Snapshot::from_path(Path::new(&name)).unwrap();
+        assert_eq!(snapshot.created, created);
+        assert_eq!(snapshot.reason, SnapshotReason::Shutdown);
+
+        assert_eq!(Snapshot::from_path(Path::new("lokbuch.db")), None);
+        assert_eq!(Snapshot::from_path(Path::new("lokbuch-2026-10-18_123005-other.db")), None);
+    }
+
+    #[test]
+    fn old_snapshots_are_pruned() {
+        let location = test_location(1);
+        let lrm = task::block_on(LokResourceManager::<AnyBackend>::build(&location.db_url())).unwrap();
+        task::block_on(lrm.handle().add_lok(Lok::new_from_raw_data("SNAP".to_string(), Some(3), "".to_string(), "".to_string(), "".to_string(), true, Vec::new()))).unwrap();
+
+        task::block_on(rotate(&location.db_url(), &location, SnapshotReason::Startup, 3)).unwrap();
+
+        let snapshots = list(&location).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].reason, SnapshotReason::Startup);
+
+        let snapshot = task::block_on(LokResourceManager::<AnyBackend>::build(&AnyBackend::url_from_path(&snapshots[0].path))).unwrap();
+        assert_eq!(snapshot.number_of_loks(), 1);
+
+        for day in 1..=4 {
+            let created = NaiveDate::from_ymd_opt(2020, 1, day).unwrap().and_hms_opt(0, 0, 0).unwrap();
+            std::fs::copy(&snapshots[0].path, location.snapshots_dir().join(Snapshot::file_name(&created, SnapshotReason::Shutdown))).unwrap();
+        }
+
+        prune(&location, 3).unwrap();
+
+        let created: Vec<String> = list(&location).unwrap().iter().map(|snapshot| snapshot.created.format("%d").to_string()).collect();
+        assert_eq!(created.len(), 3);
+        assert_eq!(created[1..], ["04".to_string(), "03".to_string()]);
+    }
+
+    #[test]
+    fn nothing_is_kept_when_disabled() {
+        let location = test_location(2);
+        let _lrm = task::block_on(LokResourceManager::<AnyBackend>::build(&location.db_url())).unwrap();
+
+        task::block_on(rotate(&location.db_url(), &location, SnapshotReason::Shutdown, 0)).unwrap();
+
+        assert!(list(&location).unwrap().is_empty());
+    }
+}
@@ -1,7 +1,10 @@
-use crate::app::backend::database::lok::Lok;
+use crate::app::backend::database::lok::{Lok, LokImage, ADDRESS_RANGE, MAX_LOKMAUS_NAME_LENGTH};
+use crate::app::data_location::DataLocation;
+use crate::app::image_editor::ImageEditor;
 use crate::app::ui;
 use crate::app::Message;
 use iced::Task;
+use std::path::PathBuf;
 
 /// The State holds data for a session.
 /// Does not hold persistent data.
@@ -13,9 +16,15 @@ pub struct State {
     pub manufacturer_input: String,
     pub management_input: String,
     pub has_decoder: bool,
-    pub image_path_input: String,
-    pub search_input: String,
+    pub images_input: Vec<LokImage>,
+    /// Index of the image shown large, in the input mask and on the show page.
+    pub selected_image: usize,
+    /// Images that were copied into the image directory while adding or editing a lok, but are not saved yet.
+    pub unsaved_images: Vec<String>,
+    /// The editor cropping one of the images, if it is open.
+    pub image_editor: Option<ImageEditor>,
     pub selected_lok_id: Option<u32>,
+    pub collection_name_input: String,
 }
 
 impl State {
@@ -28,9 +37,12 @@ impl State {
         self.manufacturer_input.clear();
         self.management_input.clear();
         self.has_decoder = false;
-        self.image_path_input.clear();
-        self.search_input.clear();
+        self.images_input.clear();
+        self.selected_image = 0;
+        self.unsaved_images.clear();
+        self.image_editor = None;
         self.selected_lok_id = None;
+        self.collection_name_input.clear();
     }
 
     /// Returns a new Lok instance built from the inputted data.
@@ -38,9 +50,9 @@ impl State {
         Lok::new_from_raw_data(
             self.name_input.clone(),
             if self.has_decoder {
-                self.address_input as i32
+                Some(self.address_input)
             } else {
-                -1
+                None
             },
             if self.has_decoder {
                 self.lok_maus_name_input.clone().to_string().to_uppercase()
@@ -50,7 +62,7 @@ impl State {
             self.manufacturer_input.clone(),
             self.management_input.clone(),
             self.has_decoder.clone(),
-            self.image_path_input.clone(),
+            self.images_input.clone(),
         )
     }
 
@@ -67,7 +79,16 @@ impl State {
         }
 
         if self.has_decoder {
-            if self.lok_maus_name_input.len() > 5 {
+            if !ADDRESS_RANGE.contains(&self.address_input) {
+                let res = rfd::AsyncMessageDialog::new()
+                    .set_title(t!("state.input_error"))
+                    .set_description(t!("state.address_invalid", min = ADDRESS_RANGE.start(), max = ADDRESS_RANGE.end()))
+                    .set_buttons(rfd::MessageButtons::Ok);
+
+                return Err(Task::perform(res.show(), Message::InputFailure));
+            }
+
+            if self.lok_maus_name_input.len() > MAX_LOKMAUS_NAME_LENGTH {
                 let res = rfd::AsyncMessageDialog::new()
                     .set_title(t!("state.input_error"))
                     .set_description(t!("state.lm_name_too_long"))
@@ -101,17 +122,85 @@ impl State {
             Message::HasDecoderInputChanged(_) => {
                 self.has_decoder = !self.has_decoder;
             }
+            Message::ImageCaptionChanged(index, caption) => {
+                if let Some(image) = self.images_input.get_mut(index) {
+                    image.caption = Some(caption);
+                }
+            }
+            Message::MoveImageLeft(index) if index > 0 && index < self.images_input.len() => {
+                self.images_input.swap(index - 1, index);
+                self.selected_image = index - 1;
+            }
+            Message::MoveImageRight(index) if index + 1 < self.images_input.len() => {
+                self.images_input.swap(index, index + 1);
+                self.selected_image = index + 1;
+            }
+            Message::RemoveImage(index) if index < self.images_input.len() => {
+                let removed = self.images_input.remove(index);
+
+                // another image takes over, so there is always a primary image
+                if removed.is_primary {
+                    if let Some(first) = self.images_input.first_mut() {
+                        first.is_primary = true;
+                    }
+                }
+
+                self.selected_image = self.selected_image.min(self.images_input.len().saturating_sub(1));
+            }
+            Message::SetPrimaryImage(index) if index < self.images_input.len() => {
+                for (image_index, image) in self.images_input.iter_mut().enumerate() {
+                    image.is_primary = image_index == index;
+                }
+            }
+            Message::SelectImage(index) if index < self.images_input.len() => {
+                self.selected_image = index;
+            }
+            Message::PreviousImage if !self.images_input.is_empty() => {
+                self.selected_image = (self.selected_image + self.images_input.len() - 1) % self.images_input.len();
+            }
+            Message::NextImage if !self.images_input.is_empty() => {
+                self.selected_image = (self.selected_image + 1) % self.images_input.len();
+            }
+            Message::RotateImageLeft | Message::RotateImageRight | Message::CropZoomChanged(_) | Message::CropFocusXChanged(_) | Message::CropFocusYChanged(_) => {
+                if let Some(editor) = self.image_editor.as_mut() {
+                    editor.update(message);
+                }
+            }
+            Message::RestoreOriginalImage => {
+                if let Some(editor) = self.image_editor.take() {
+                    if let Some(image) = self.images_input.get_mut(editor.index) {
+                        image.path = editor.original;
+                        image.original = None;
+                    }
+                }
+            }
+            Message::CloseImageEditor => {
+                self.image_editor = None;
+            }
             _ => {}
         }
     }
 
-    /// Returns the path to the current lok image.
-    /// If no image is selected, the default image is returned.
-    pub fn get_current_lok_image_path(&self) -> String {
-        if self.image_path_input.is_empty() {
-            String::from(ui::DEFAULT_LOCO_IMAGE_PATH)
-        } else {
-            self.image_path_input.clone()
+    /// Appends a new image and shows it.
+    /// The first image of a lok becomes its primary image.
+    pub fn add_image(&mut self, image_path: String) {
+        let is_primary = self.images_input.iter().all(|image| !image.is_primary);
+
+        self.images_input.push(LokImage { is_primary, ..LokImage::new(image_path) });
+        self.selected_image = self.images_input.len() - 1;
+    }
+
+    /// Returns the image that is shown large, if the lok has any.
+    pub fn get_selected_image(&self) -> Option<&LokImage> {
+        self.images_input.get(self.selected_image)
+    }
+
+    /// Returns the path to the image that is shown large.
+    /// If the lok has no image, the default image is returned.
+    pub fn get_current_lok_image_path(&self, data_location: &DataLocation) -> PathBuf {
+        match self.get_selected_image() {
+            Some(image) => data_location.image_path(&image.path),
+            None => PathBuf::from(ui::DEFAULT_LOCO_IMAGE_PATH),
         }
     }
 
@@ -133,9 +222,8 @@ impl State {
         let management_input = if let Some(management) = lok.management.clone() {
             management
         } else { String::new() };
-        let image_path_input = if let Some(image_path) = lok.image_path.clone() {
-            image_path
-        } else { String::new() };
+        // the show page starts with the primary image
+        let selected_image = lok.primary_image_index();
 
         State {
             selected_lok_id: Some(id),
@@ -145,7 +233,8 @@ impl State {
             manufacturer_input: producer_input,
             management_input,
             has_decoder,
-            image_path_input,
+            images_input: lok.images.clone(),
+            selected_image,
             ..State::default()
         }
     }
@@ -160,9 +249,12 @@ impl Default for State {
             manufacturer_input: String::default(),
             management_input: String::default(),
             has_decoder: false,
-            image_path_input: String::default(),
-            search_input: String::default(),
+            images_input: Vec::default(),
+            selected_image: 0,
+            unsaved_images: Vec::default(),
+            image_editor: None,
             selected_lok_id: None,
+            collection_name_input: String::default(),
         }
     }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 pub mod widgets;
 pub mod font;
+pub mod notification;
 
 const VIEW_NAME_TEXT_SIZE: u32 = 25;
 const VIEW_TITLE_TEXT_SIZE: u32 = 75;
@@ -0,0 +1,148 @@
+use crate::app::backend::database::search_query::SearchQueryError;
+use crate::app::backend::database::DatabaseError;
+use crate::app::backup::BackupError;
+use crate::app::message::Message;
+use iced::widget::{button, column, container, row, space, text};
+use iced::{Bottom, Center, Element, Fill, Right};
+
+const NOTIFICATION_WIDTH: u32 = 450;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum NotificationKind {
+    Info,
+    Error,
+}
+
+/// A dismissable message shown on top of the current page.
+#[derive(Clone, Debug)]
+pub struct Notification {
+    id: u32,
+    kind: NotificationKind,
+    text: String,
+}
+
+impl Notification {
+    /// Builds a notification confirming that something succeeded.
+    pub fn info(text: impl Into<String>) -> Notification {
+        Notification {
+            id: 0,
+            kind: NotificationKind::Info,
+            text: text.into(),
+        }
+    }
+
+    pub fn error(text: impl Into<String>) -> Notification {
+        Notification {
+            id: 0,
+            kind: NotificationKind::Error,
+            text: text.into(),
+        }
+    }
+
+    /// Builds an error notification for a failed database operation.
+    /// The summary is a translated text describing what the user tried to do.
+    pub fn database_error(summary: impl Into<String>, error: &DatabaseError) -> Notification {
+        let summary = summary.into();
+
+        println!("{}: {}", summary, error);
+
+        Notification::error(format!("{} {}", summary, localized_database_error(error)))
+    }
+}
+
+/// Holds every notification that has not been dismissed yet.
+#[derive(Clone, Debug, Default)]
+pub struct Notifications {
+    notifications: Vec<Notification>,
+    next_id: u32,
+}
+
+impl Notifications {
+    /// Adds a notification and assigns it a unique id.
+    pub fn push(&mut self, mut notification: Notification) {
+        notification.id = self.next_id;
+        self.next_id += 1;
+
+        self.notifications.push(notification);
+    }
+
+    /// Removes the notification with the given id.
+    pub fn dismiss(&mut self, id: u32) {
+        self.notifications.retain(|notification| notification.id != id);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notifications.is_empty()
+    }
+
+    /// Lays out all notifications in the bottom right corner.
+    pub fn view(&self) -> Element<'_, Message> {
+        let list = column(self.notifications.iter().map(notification_widget))
+            .spacing(10)
+            .width(NOTIFICATION_WIDTH);
+
+        container(list)
+            .padding(20)
+            .width(Fill)
+            .height(Fill)
+            .align_x(Right)
+            .align_y(Bottom)
+            .into()
+    }
+}
+
+fn notification_widget(notification: &Notification) -> Element<'_, Message> {
+    let id = notification.id;
+
+    container(
+        row![
+            text(notification.text.as_str()).width(Fill),
+            space::horizontal().width(10),
+            button(text(t!("notification.dismiss")))
+                .on_press(Message::DismissNotification(id))
+                .style(button::secondary),
+        ].align_y(Center)
+    )
+        .padding(15)
+        .width(Fill)
+        .style(match notification.kind {
+            NotificationKind::Info => container::success,
+            NotificationKind::Error => container::danger,
+        })
+        .into()
+}
+
+/// Returns a translated description of a database error.
+pub fn localized_database_error(error: &DatabaseError) -> String {
+    match error {
+        DatabaseError::ConnectionError => t!("notification.db_connection").to_string(),
+        DatabaseError::NotFound => t!("notification.db_not_found").to_string(),
+        DatabaseError::ConstraintViolation(_) => t!("notification.db_constraint").to_string(),
+        DatabaseError::Io(message) => t!("notification.db_io", message = message).to_string(),
+        DatabaseError::Migration(message) => t!("notification.db_migration", message = message).to_string(),
+        DatabaseError::NewerSchema(_) => t!("notification.db_newer_schema").to_string(),
+        DatabaseError::Busy => t!("notification.db_busy").to_string(),
+        DatabaseError::GeneralError(message) => message.clone(),
+    }
+}
+
+/// Returns a translated description of a search that could not be parsed.
+pub fn localized_search_query_error(error: &SearchQueryError) -> String {
+    match error {
+        SearchQueryError::MissingValue(term) => t!("home.search_missing_value", term = term).to_string(),
+        SearchQueryError::InvalidAddress(value) => t!("home.search_invalid_address", value = value).to_string(),
+        SearchQueryError::InvalidDigital(value) => t!("home.search_invalid_digital", value = value).to_string(),
+        SearchQueryError::UnclosedQuote => t!("home.search_unclosed_quote").to_string(),
+    }
+}
+
+/// Returns a translated description of a backup error.
+pub fn localized_backup_error(error: &BackupError) -> String {
+    match error {
+        BackupError::Unsupported => t!("notification.backup_unsupported").to_string(),
+        BackupError::InvalidArchive(message) => t!("notification.backup_invalid", message = message).to_string(),
+        BackupError::NewerFormat(_) => t!("notification.backup_newer_format").to_string(),
+        BackupError::Database(err) => localized_database_error(err),
+        BackupError::Io(message) => t!("notification.backup_io", message = message).to_string(),
+    }
+}
@@ -1,9 +1,15 @@
 use crate::app::backend::database::preview_lok::PreviewLok;
+use crate::app::backend::database::search::{SearchField, SearchMatch};
 use crate::app::message::Message;
+use crate::app::image_editor::ImageEditor;
+use crate::app::page::PageStatus;
+use crate::app::ui::notification::localized_database_error;
 use crate::app::ui::{font, SvgIcon, VIEW_NAME_TEXT_SIZE, VIEW_TITLE_TEXT_SIZE};
-use crate::app::{ui, Lokbuch};
-use iced::widget::{button, checkbox, column, container, image, row, space, svg, text, text_input, Container};
-use iced::{Center, ContentFit, Element, Fill, FillPortion, Left};
+use crate::app::{image_store, ui, Lokbuch};
+use iced::widget::text::Span;
+use iced::widget::{button, center, checkbox, column, container, image, rich_text, row, scrollable, slider, space, span, svg, text, text_input, Container};
+use iced::{Center, ContentFit, Element, Fill, FillPortion, Font, Left};
+use std::ops::Range;
 use iced_aw::number_input;
 
 pub fn header<'a>(name: String) -> Element<'a, Message> {
@@ -33,8 +39,18 @@ pub fn header<'a>(name: String) -> Element<'a, Message> {
 
 /// Returns an inputted PreviewLok as a custom widget.
 /// This is used for the main page.
-pub fn preview_widget<'a>(preview_data: PreviewLok) -> Container<'a, Message> {
+/// The parts of the lok a search was found in are highlighted, hits in fields the row does not show are added to it.
+pub fn preview_widget<'a>(preview_data: &PreviewLok) -> Container<'a, Message> {
     let preview_id = preview_data.get_id();
+    let search_match = preview_data.get_search_match();
+
+    let hidden_hits = row(
+        [(SearchField::Producer, preview_data.get_producer()), (SearchField::Management, preview_data.get_management())]
+            .into_iter()
+            .filter_map(|(field, content)| Some((field, content?)))
+            .filter(|(field, _)| search_match.is_some_and(|search_match| search_match.highlights.iter().any(|highlight| highlight.field == *field)))
+            .map(|(field, content)| highlighted_text(content, field, search_match))
+    ).spacing(10);
 
     let button_row = row![
         space::horizontal(),
@@ -54,21 +70,24 @@ pub fn preview_widget<'a>(preview_data: PreviewLok) -> Container<'a, Message> {
 
     container(row![
         row![
-            text!("{}", preview_data.get_address_pretty()),
+            highlighted_text(preview_data.get_address_pretty(), SearchField::Address, search_match),
             space::horizontal(),
         ],
 
         row![
-            text!("{}", preview_data.get_lokmaus_name_pretty()),
+            highlighted_text(preview_data.get_lokmaus_name_pretty(), SearchField::LokmausName, search_match),
             space::horizontal(),
         ],
 
         row![
-            text!("{}", preview_data.get_name_pretty()),
+            highlighted_text(preview_data.get_name_pretty(), SearchField::Name, search_match),
             space::horizontal(),
         ],
 
-        space::horizontal(),
+        row![
+            hidden_hits,
+            space::horizontal(),
+        ],
         button_row
         ])
         .padding(10)
@@ -76,16 +95,193 @@ pub fn preview_widget<'a>(preview_data: PreviewLok) -> Container<'a, Message> {
         .width(Fill)
 }
 
+/// Shows the text of a field, the parts of it a search was found in are bold and underlined.
+fn highlighted_text<'a>(content: String, field: SearchField, search_match: Option<&SearchMatch>) -> Element<'a, Message> {
+    let mut ranges: Vec<Range<usize>> = search_match
+        .map(|search_match| search_match.highlights.iter()
+            .filter(|highlight| highlight.field == field)
+            .map(|highlight| highlight.range.clone())
+            .collect())
+        .unwrap_or_default();
+
+    if ranges.is_empty() {
+        return text(content).into();
+    }
+
+    ranges.sort_by_key(|range| range.start);
+
+    // several words of a search can be found in the same part of a field
+    let mut spans: Vec<Span<'a, (), Font>> = Vec::new();
+    let mut position = 0;
+    for range in ranges {
+        let start = range.start.max(position);
+        if range.end <= start {
+            continue;
+        }
+
+        spans.push(span(content[position..start].to_string()));
+        spans.push(span(content[start..range.end].to_string()).font(font::bold_font()).underline(true));
+        position = range.end;
+    }
+    spans.push(span(content[position..].to_string()));
+
+    rich_text(spans).into()
+}
+
+/// Shows the selected image of the current lok with its caption.
+/// If the lok has more than one image, the others can be browsed.
+pub fn image_carousel(lokbuch: &Lokbuch) -> Element<'_, Message> {
+    let state = &lokbuch.state;
+    let has_more_images = state.images_input.len() > 1;
+
+    let picture = image(state.get_current_lok_image_path(&lokbuch.data_location))
+        .width(400)
+        .height(200)
+        .content_fit(ContentFit::Cover);
+
+    if state.images_input.is_empty() {
+        return picture.into();
+    }
+
+    let caption = state.get_selected_image()
+        .and_then(|image| image.caption.clone())
+        .unwrap_or_default();
+
+    column![
+        picture,
+        row![
+            button(text("<"))
+                .on_press_maybe(has_more_images.then_some(Message::PreviousImage))
+                .style(button::secondary),
+            text(caption).width(Fill).align_x(Center),
+            text!("{} / {}", state.selected_image + 1, state.images_input.len()),
+            button(text(">"))
+                .on_press_maybe(has_more_images.then_some(Message::NextImage))
+                .style(button::secondary),
+        ].spacing(10).align_y(Center),
+    ]
+        .spacing(10)
+        .width(400)
+        .into()
+}
+
+/// Lists the images of the lok being edited as thumbnails.
+/// Every image can be moved, removed, captioned and made the primary image.
+fn image_gallery_editor(lokbuch: &Lokbuch) -> Element<'_, Message> {
+    let cards = lokbuch.state.images_input.iter().enumerate().map(|(index, lok_image)| {
+        let is_last = index + 1 == lokbuch.state.images_input.len();
+
+        column![
+            button(image(image_store::thumbnail_or_image(&lokbuch.data_location, &lok_image.path))
+                    .width(120)
+                    .height(80)
+                    .content_fit(ContentFit::Cover)
+                )
+                .on_press(Message::SelectImage(index))
+                .style(if index == lokbuch.state.selected_image { button::primary } else { button::text }),
+            text_input(t!("ui.caption").to_string().as_str(), lok_image.caption.as_deref().unwrap_or_default())
+                .on_input(move |caption| Message::ImageCaptionChanged(index, caption))
+                .width(120),
+            row![
+                button(text("<"))
+                    .on_press_maybe((index > 0).then_some(Message::MoveImageLeft(index)))
+                    .style(button::secondary),
+                button(text("★"))
+                    .on_press(Message::SetPrimaryImage(index))
+                    .style(if lok_image.is_primary { button::success } else { button::secondary }),
+                button(text(">"))
+                    .on_press_maybe((!is_last).then_some(Message::MoveImageRight(index)))
+                    .style(button::secondary),
+                button(font::delete_icon())
+                    .on_press(Message::RemoveImage(index))
+                    .style(button::danger),
+            ].spacing(5),
+        ].spacing(5).into()
+    });
+
+    column![
+        scrollable(row(cards).spacing(10))
+            .direction(scrollable::Direction::Horizontal(scrollable::Scrollbar::default()))
+            .width(400),
+        row![
+            button(text(t!("ui.add_image")))
+                .on_press(Message::SelectImageFile)
+                .style(button::secondary),
+            button(text(t!("ui.paste_image")))
+                .on_press(Message::PasteImage)
+                .style(button::secondary),
+            button(text(t!("ui.edit_image")))
+                .on_press_maybe(lokbuch.state.get_selected_image().map(|_| Message::EditImage))
+                .style(button::secondary),
+        ].spacing(10),
+        text(t!("ui.drop_images")).size(12),
+    ].spacing(10).into()
+}
+
+/// Shows the crop of the edited image as the show page will display it, with controls to rotate and move it.
+fn image_editor_widget<'a>(lokbuch: &Lokbuch, editor: &'a ImageEditor) -> Element<'a, Message> {
+    let is_cropped = lokbuch.state.images_input.get(editor.index).is_some_and(|image| image.original.is_some());
+
+    let slider_row = |label: String, range, value, on_change: fn(f32) -> Message| {
+        row![
+            text(label).width(150),
+            slider(range, value, on_change).step(0.01),
+        ].spacing(10).align_y(Center)
+    };
+
+    column![
+        text(t!("image_editor.title"))
+            .size(ui::HEADING_TEXT_SIZE)
+            .font(font::bold_font()),
+        image(editor.preview().clone())
+            .width(400)
+            .height(400.0 / image_store::DISPLAY_ASPECT_RATIO)
+            .content_fit(ContentFit::Fill),
+        row![
+            button(text(t!("image_editor.rotate_left")))
+                .on_press(Message::RotateImageLeft)
+                .style(button::secondary),
+            button(text(t!("image_editor.rotate_right")))
+                .on_press(Message::RotateImageRight)
+                .style(button::secondary),
+        ].spacing(10),
+        slider_row(t!("image_editor.zoom").to_string(), 1.0..=image_store::MAX_CROP_ZOOM, editor.crop.zoom, Message::CropZoomChanged),
+        slider_row(t!("image_editor.focus_x").to_string(), 0.0..=1.0, editor.crop.focus_x, Message::CropFocusXChanged),
+        slider_row(t!("image_editor.focus_y").to_string(), 0.0..=1.0, editor.crop.focus_y, Message::CropFocusYChanged),
+        row![
+            button(text(t!("image_editor.apply")))
+                .on_press(Message::ApplyImageEdit),
+            button(text(t!("image_editor.restore_original")))
+                .on_press_maybe(is_cropped.then_some(Message::RestoreOriginalImage))
+                .style(button::secondary),
+            button(text(t!("ui.cancel")))
+                .on_press(Message::CloseImageEditor)
+                .style(button::secondary),
+        ].spacing(10),
+    ]
+        .spacing(10)
+        .width(400)
+        .into()
+}
+
 /// Lays out the input mask for adding and editing a Lok.
 /// The message on finish is emitted when the save button was pressed.
 pub fn lok_data_input_mask(lokbuch: &Lokbuch, header_text: String, message_on_finish: Message) -> Element<Message> {
+    let images: Element<Message> = match &lokbuch.state.image_editor {
+        Some(editor) => image_editor_widget(lokbuch, editor),
+        None => column![
+                button(image(lokbuch.state.get_current_lok_image_path(&lokbuch.data_location))
+                        .width(400)
+                        .content_fit(ContentFit::Fill)
+                    )
+                    .on_press(Message::SelectImageFile)
+                    .style(button::text),
+                image_gallery_editor(lokbuch),
+            ].spacing(10).into(),
+    };
+
     let upper_row = row![
-            button(image(lokbuch.state.get_current_lok_image_path())
-                    .width(400)
-                    .content_fit(ContentFit::Fill)
-                )
-                .on_press(Message::SelectImageFile)
-                .style(button::text),
+            images,
             column![
                 column!(
                 text(t!("ui.name"))
@@ -196,7 +392,7 @@ pub fn lok_data_input_mask(lokbuch: &Lokbuch, header_text: String, message_on_fi
         ].spacing(20).padding(20);
 
     let add_button = button(text(t!("ui.save")))
-        .on_press(message_on_finish)
+        .on_press_maybe((!lokbuch.is_busy()).then_some(message_on_finish))
         .padding(15)
         .width(Fill);
 
@@ -252,6 +448,26 @@ pub fn sidebar(buttons: iced::widget::Column<Message>, has_cancel_button: bool)
         .into()
 }
 
+/// Shows that the page waits for its data, or why the data could not be loaded.
+/// Returns ```None``` if there is data to show, i.e. while the page is ready or saving.
+pub fn page_status_widget(lokbuch: &Lokbuch, title: String) -> Option<Element<'_, Message>> {
+    let status: Element<Message> = match &lokbuch.page_status {
+        PageStatus::Ready | PageStatus::Saving => return None,
+        PageStatus::Loading => column![
+                iced_gif::Gif::new(&lokbuch.moving_icon_frames),
+                text(t!("loading.loading")).size(ui::HEADING_TEXT_SIZE),
+            ].align_x(Center).into(),
+        PageStatus::Failed(err) => column![
+                text(t!("notification.load_failed"))
+                    .size(ui::HEADING_TEXT_SIZE)
+                    .font(font::bold_font()),
+                text(localized_database_error(err)),
+            ].spacing(10).align_x(Center).into(),
+    };
+
+    Some(page_layout(title, column![], container(center(status)), true))
+}
+
 pub fn page_layout<'a>(title: String, sidebar_buttons: iced::widget::Column<'a, Message>, content: Container<'a, Message>, has_cancel_button: bool) -> Element<'a, Message> {
     column![
         header(title),